@@ -0,0 +1,368 @@
+// src/app.rs
+//! The `spawnpoint` command line: logging setup, templates lookup and command dispatch.
+use clap::CommandFactory;
+use directories::ProjectDirs;
+use crate::cli::{Cli, Commands, GenerateArgs};
+use crate::error::SpawnError;
+use crate::registry::TemplateRegistry;
+use crate::{
+  archive, compare, config, describe, embedded, generate, hooks, lint, list, new_template, prompt, remote,
+  update, utils, validate,
+};
+use log::LevelFilter;
+use std::env;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Runs the parsed command line and returns the process exit code. A failure is printed to
+/// stderr (sensitive values masked) and mapped to its category's code (see `SpawnError::exit_code`).
+pub fn run(cli: Cli) -> i32 {
+  match run_command(cli) {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("Error: {}", e);
+      e.exit_code()
+    }
+  }
+}
+
+fn run_command(cli: Cli) -> Result<(), SpawnError> {
+  // Setup logging based on verbosity (an explicit -v overrides --quiet)
+  let log_level = match cli.verbose {
+    0 if cli.quiet => LevelFilter::Warn,
+    0 => LevelFilter::Info,
+    1 => LevelFilter::Debug,
+    _ => LevelFilter::Trace,
+  };
+  let colors = prompt::init_colors(cli.color);
+  let mut logger = env_logger::Builder::new();
+  logger.filter_level(log_level).write_style(if colors {
+    env_logger::WriteStyle::Always
+  } else {
+    env_logger::WriteStyle::Never
+  });
+  // -vv shows failed steps' whole output instead of the tail
+  utils::set_output_tail_lines((cli.verbose < 2).then_some(cli.output_tail_lines));
+  if cli.trace_transforms {
+    logger.filter_module(utils::TRANSFORM_LOG_TARGET, LevelFilter::Debug);
+  }
+  logger.init();
+
+  log::debug!("CLI args: {:?}", cli);
+
+  // Determine templates directory paths (a path list names several; later ones win).
+  // Only the commands that use templates look them up.
+  let find_templates_dirs = || -> Result<Vec<PathBuf>, SpawnError> {
+    let templates_dirs = match (&cli.template_repo, &cli.templates_archive) {
+      (Some(url), _) => vec![remote::fetch_template_repo(url, cli.refresh)?],
+      (None, Some(archive)) => vec![archive::extract_templates_archive(archive)?],
+      (None, None) => {
+        let mut templates_dirs = determine_templates_dirs(cli.templates_dir.clone(), cli.no_builtin)?;
+        // A root config can name a template repository, layered on top of the local templates
+        if let Some(url) = config::RootConfig::load_all(&templates_dirs)?.registry_url {
+          templates_dirs.push(remote::fetch_template_repo(&url, cli.refresh)?);
+        }
+        templates_dirs
+      }
+    };
+    for templates_path in &templates_dirs {
+      log::info!("Using templates directory: {}", templates_path.display());
+      if !templates_path.exists() {
+        log::warn!("Selected templates directory '{}' does not exist. 'list' and 'generate' commands may find no templates.", templates_path.display());
+      }
+    }
+    Ok(templates_dirs)
+  };
+  let scan_templates = |templates_dirs: &[PathBuf]| TemplateRegistry::scan_all(templates_dirs, cli.strict_warnings);
+  let find_templates = || scan_templates(&find_templates_dirs()?);
+
+  // Match on the command
+  match cli.command {
+    Commands::List(args) => {
+      list::run_list(args, &find_templates()?)?;
+    }
+    Commands::Generate(mut args) => {
+      let templates_dirs = find_templates_dirs()?;
+      // Team defaults shipped with the templates directories (CLI flags take precedence)
+      let root_config = config::RootConfig::load_all(&templates_dirs)?;
+      log::debug!("Root config: {:?}", root_config);
+      apply_root_config(&root_config, &mut args);
+      let registry = scan_templates(&templates_dirs)?;
+      generate::run_generate(args, &registry, cli.strict_warnings, cli.check_stderr, cli.hook_timeout)?;
+    }
+    Commands::Validate(args) => {
+      validate::run_validate(args, &find_templates()?, cli.strict_warnings, cli.check_stderr)?;
+    }
+    Commands::Update(args) => {
+      update::run_update(args, &find_templates()?, cli.strict_warnings)?;
+    }
+    Commands::Hooks(args) => {
+      let registry = find_templates()?;
+      hooks::run_hooks_command(args, &registry, cli.strict_warnings, cli.check_stderr, cli.hook_timeout)?;
+    }
+    Commands::Compare(args) => {
+      compare::run_compare(args, &find_templates()?)?;
+    }
+    Commands::Describe(args) => {
+      describe::run_describe(args, &find_templates()?)?;
+    }
+    Commands::Which(args) => {
+      list::run_which(args, &find_templates()?)?;
+    }
+    Commands::ValidateManifest(args) => {
+      lint::run_validate_manifest(&args.path, cli.strict_warnings)?;
+    }
+    Commands::NewTemplate(args) => {
+      // The highest-precedence directory; new templates are created there
+      let templates_path = find_templates_dirs()?
+        .pop()
+        .ok_or(SpawnError::CannotDetermineTemplatesDir)?;
+      if cli.template_repo.is_some() || cli.templates_archive.is_some() {
+        return Err(SpawnError::NewTemplate {
+          path: templates_path,
+          reason: "--template-repo checkouts and --templates-archive extractions are a cache; \
+                   use --templates-dir"
+            .to_string(),
+        });
+      }
+      new_template::run_new_template(args, &templates_path)?;
+    }
+    Commands::Completions(args) => {
+      let mut script = Vec::new();
+      clap_complete::generate(args.shell, &mut Cli::command(), "spawnpoint", &mut script);
+      std::io::stdout().write_all(&script)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Fills in `generate`'s language/template and hook trust from the root config. Explicit CLI
+/// values always win; the default template only applies when the language is the default one.
+fn apply_root_config(root_config: &config::RootConfig, args: &mut GenerateArgs) {
+  if args.language.is_none() {
+    args.language = root_config.default_language.clone();
+  }
+  if args.trust_hooks.is_none() {
+    args.trust_hooks = root_config.trusted;
+  }
+  let language_is_default =
+    root_config.default_language.is_none() || args.language == root_config.default_language;
+  if args.template.is_none() && args.template_index.is_none() && language_is_default {
+    args.template = root_config.default_template.clone();
+  }
+}
+
+/// Determines the templates directory paths using a prioritized search.
+///
+/// Order of preference:
+/// 1. --templates-dir CLI argument (a path list, like `PATH`)
+/// 2. SPAWNPOINT_TEMPLATES_DIR environment variable (a path list, like `PATH`)
+/// 3. User config directory (e.g., ~/.config/spawnpoint/templates)
+/// 4. templates/ subdirectory relative to the executable
+/// 5. templates/ subdirectory of the current working directory
+/// 6. Templates embedded in the binary (`embedded-templates` feature; skipped with `no_builtin`)
+///
+/// Path lists yield every valid directory in them, in order (later ones take precedence);
+/// the other locations yield a single directory. Fails if none are found and valid.
+fn determine_templates_dirs(
+  cli_path_opt: Option<PathBuf>,
+  no_builtin: bool,
+) -> Result<Vec<PathBuf>, SpawnError> {
+  // 1. Explicit CLI path(s)
+  if let Some(paths) = cli_path_opt {
+    let dirs = valid_dirs_in_path_list(paths.as_os_str(), "--templates-dir");
+    if !dirs.is_empty() {
+      log::trace!("Using CLI option --templates-dir path(s).");
+      return Ok(dirs);
+    }
+    // Invalid entries were warned about; continue searching other locations
+  }
+
+  // 2. Environment variable (Handled automatically by clap's `env` attribute if cli_path_opt was None,
+  //    but we re-check here explicitly in case the CLI path was provided but invalid)
+  if let Some(paths) = env::var_os("SPAWNPOINT_TEMPLATES_DIR") {
+    let dirs = valid_dirs_in_path_list(&paths, "SPAWNPOINT_TEMPLATES_DIR");
+    if !dirs.is_empty() {
+      log::trace!("Using env var SPAWNPOINT_TEMPLATES_DIR path(s).");
+      return Ok(dirs);
+    }
+  }
+
+  // 3. User config directory
+  // Choose unique qualifiers for your app. Using GitHub username is common.
+  if let Some(proj_dirs) = ProjectDirs::from("com", "excsn", "spawnpoint") {
+    // Adjust "github_normano" if needed
+    let config_dir = proj_dirs.config_dir();
+    let path = config_dir.join("templates");
+    log::debug!("Checking user config dir: {}", path.display());
+    if path.is_dir() {
+      log::trace!("Using user config directory path.");
+      return Ok(vec![path]);
+    } else {
+      log::trace!("User config templates directory not found or not a directory.");
+    }
+  } else {
+    log::warn!("Could not determine standard user config directory path.");
+  }
+
+  // 4. Relative to executable
+  if let Ok(mut exe_path) = env::current_exe() {
+    exe_path.pop(); // Remove the executable name itself
+    let path = exe_path.join("templates");
+    log::debug!("Checking executable relative dir: {}", path.display());
+    if path.is_dir() {
+      log::trace!("Using executable relative directory path.");
+      return Ok(vec![path]);
+    } else {
+      log::trace!("Executable relative templates directory not found or not a directory.");
+    }
+  } else {
+    log::warn!("Could not determine executable path.");
+  }
+
+  // 5. CWD relative (Removed - generally unreliable for installed tools)
+  let cwd_path = PathBuf::from("templates");
+  log::debug!("Checking CWD relative dir: {}", cwd_path.display());
+  if cwd_path.is_dir() {
+    return Ok(vec![cwd_path]);
+  }
+
+  // 6. Templates embedded in the binary (embedded-templates feature)
+  if no_builtin {
+    log::debug!("Skipping built-in templates (--no-builtin).");
+  } else if let Some(path) = embedded::extract_embedded_templates()? {
+    log::debug!("Using embedded templates extracted to: {}", path.display());
+    return Ok(vec![path]);
+  }
+
+  // If we reach here, no valid directory was found
+  log::error!("Could not find a valid templates directory. Searched CLI arg, env var, user config ({}), and executable relative paths.",
+        ProjectDirs::from("com", "github_normano", "spawnpoint")
+            .map(|p| p.config_dir().join("templates").display().to_string())
+            .unwrap_or_else(|| "<user config path unavailable>".to_string())
+    );
+  Err(SpawnError::CannotDetermineTemplatesDir)
+}
+
+/// The directories in a `PATH`-style list (`a:b` on Unix, `a;b` on Windows), skipping empty
+/// entries and warning about ones that aren't directories. `source` names the list in logs.
+fn valid_dirs_in_path_list(paths: &OsStr, source: &str) -> Vec<PathBuf> {
+  env::split_paths(paths)
+    .filter(|path| !path.as_os_str().is_empty())
+    .filter(|path| {
+      log::debug!("Checking {} path: {}", source, path.display());
+      let is_dir = path.is_dir();
+      if !is_dir {
+        log::warn!("{} path is not a valid directory: {}", source, path.display());
+      }
+      is_dir
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use clap::Parser;
+  use std::fs;
+
+  fn generate_args(flags: &[&str]) -> GenerateArgs {
+    let argv = ["spawnpoint", "generate"].iter().chain(flags);
+    match Cli::parse_from(argv).command {
+      Commands::Generate(args) => args,
+      _ => unreachable!(),
+    }
+  }
+
+  /// A templates root holding `.spawnpoint.yaml` with `content`.
+  fn templates_root(content: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(config::ROOT_CONFIG_FILE_NAME), content).unwrap();
+    dir
+  }
+
+  fn load_all(roots: &[&tempfile::TempDir]) -> config::RootConfig {
+    let dirs: Vec<PathBuf> = roots.iter().map(|root| root.path().to_path_buf()).collect();
+    config::RootConfig::load_all(&dirs).unwrap()
+  }
+
+  /// `generate` args from `flags` with the root configs of `roots` applied.
+  fn applied(roots: &[&tempfile::TempDir], flags: &[&str]) -> GenerateArgs {
+    let mut args = generate_args(flags);
+    apply_root_config(&load_all(roots), &mut args);
+    args
+  }
+
+  fn resolve(roots: &[&tempfile::TempDir], flags: &[&str]) -> (Option<String>, Option<String>) {
+    let args = applied(roots, flags);
+    (args.language, args.template)
+  }
+
+  fn some(language: &str, template: &str) -> (Option<String>, Option<String>) {
+    (Some(language.to_string()), Some(template.to_string()))
+  }
+
+  #[test]
+  fn root_config_supplies_the_default_language_and_template() {
+    let root = templates_root("defaultLanguage: rust\ndefaultTemplate: axum\n");
+    assert_eq!(resolve(&[&root], &[]), some("rust", "axum"));
+    // Explicit flags win
+    assert_eq!(resolve(&[&root], &["-t", "actix"]), some("rust", "actix"));
+    assert_eq!(resolve(&[&root], &["-l", "rust"]), some("rust", "axum"));
+    // The default template belongs to the default language
+    assert_eq!(resolve(&[&root], &["-l", "python"]), (Some("python".to_string()), None));
+    // Picking by index doesn't get a default template
+    assert_eq!(
+      resolve(&[&root], &["-l", "rust", "--template-index", "2"]),
+      (Some("rust".to_string()), None)
+    );
+  }
+
+  #[test]
+  fn later_root_configs_override_earlier_ones() {
+    let base = templates_root("defaultLanguage: rust\ndefaultTemplate: axum\n");
+    let template_only = templates_root("defaultTemplate: actix\n");
+    let language_only = templates_root("defaultLanguage: python\n");
+    let missing = tempfile::tempdir().unwrap();
+
+    assert_eq!(resolve(&[&base, &template_only], &[]), some("rust", "actix"));
+    // A new default language drops the earlier language's default template
+    assert_eq!(resolve(&[&base, &language_only], &[]), (Some("python".to_string()), None));
+    assert_eq!(resolve(&[&base, &missing], &[]), some("rust", "axum"));
+    assert_eq!(resolve(&[&missing], &[]), (None, None));
+  }
+
+  #[test]
+  fn root_config_trust_applies_unless_the_cli_sets_it() {
+    let untrusted = templates_root("trusted: false\n");
+    let trusted = templates_root("trusted: true\n");
+    let missing = tempfile::tempdir().unwrap();
+    let trust = |roots: &[&tempfile::TempDir], flags: &[&str]| applied(roots, flags).trust_hooks;
+
+    assert_eq!(trust(&[&untrusted], &[]), Some(false));
+    assert_eq!(trust(&[&untrusted], &["--trust-hooks"]), Some(true));
+    assert_eq!(trust(&[&trusted], &["--trust-hooks=false"]), Some(false));
+    assert_eq!(trust(&[&untrusted, &trusted], &[]), Some(true));
+    assert_eq!(trust(&[&untrusted, &missing], &[]), Some(false));
+    assert_eq!(trust(&[&missing], &[]), None);
+  }
+
+  #[test]
+  fn later_root_configs_override_the_registry_url() {
+    let team = templates_root("registryUrl: https://example.com/team-templates.git\n");
+    let project = templates_root("registryUrl: https://example.com/project-templates.git\n");
+    let missing = tempfile::tempdir().unwrap();
+
+    assert_eq!(
+      load_all(&[&team, &missing]).registry_url.as_deref(),
+      Some("https://example.com/team-templates.git")
+    );
+    assert_eq!(
+      load_all(&[&team, &project]).registry_url.as_deref(),
+      Some("https://example.com/project-templates.git")
+    );
+    assert_eq!(load_all(&[&missing]).registry_url, None);
+  }
+}
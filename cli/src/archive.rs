@@ -0,0 +1,102 @@
+// src/archive.rs
+//! Templates shipped as a single archive (`--templates-archive`): `.tar.gz`/`.tgz`, `.tar` or
+//! `.zip`. The archive is extracted into the user cache directory, keyed by a hash of its
+//! path, and re-extracted only when its modification time or size changes.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use directories::ProjectDirs;
+use flate2::read::GzDecoder;
+use log::{debug, info};
+
+use crate::error::SpawnError;
+use crate::registry::MANIFEST_FILE_NAME;
+use crate::state::sha256_hex;
+
+/// Written into the extracted tree; holds the archive fingerprint it was extracted from.
+const EXTRACTED_MARKER: &str = ".spawnpoint-extracted";
+
+/// Returns the templates directory inside the extracted `archive`, extracting it first when
+/// there is no extraction yet or the archive changed since.
+pub fn extract_templates_archive(archive: &Path) -> Result<PathBuf, SpawnError> {
+  let archive_error = |reason: String| SpawnError::TemplatesArchive {
+    path: archive.to_path_buf(),
+    reason,
+  };
+  let archive = fs::canonicalize(archive).map_err(|e| archive_error(e.to_string()))?;
+  let metadata = fs::metadata(&archive).map_err(|e| archive_error(e.to_string()))?;
+  let modified = metadata
+    .modified()
+    .ok()
+    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+    .map_or(0, |since_epoch| since_epoch.as_nanos());
+  let fingerprint = format!("{}:{}", modified, metadata.len());
+
+  let cache_root = ProjectDirs::from("com", "excsn", "spawnpoint")
+    .map(|p| p.cache_dir().to_path_buf())
+    .unwrap_or_else(|| std::env::temp_dir().join("spawnpoint"));
+  let archives_dir = cache_root.join("template-archives");
+  let target = archives_dir.join(&sha256_hex(archive.to_string_lossy().as_bytes())[..16]);
+
+  let marker = target.join(EXTRACTED_MARKER);
+  if fs::read_to_string(&marker).is_ok_and(|recorded| recorded == fingerprint) {
+    debug!("Reusing extracted templates archive at {}", target.display());
+    return Ok(templates_root(&target));
+  }
+
+  fs::create_dir_all(&archives_dir).map_err(|e| SpawnError::OutputDirCreation {
+    path: archives_dir.clone(),
+    source: e,
+  })?;
+  // Extract next to the final location so an interrupted extraction never looks complete
+  let staging = tempfile::Builder::new()
+    .prefix("extract-")
+    .tempdir_in(&archives_dir)?;
+  info!("Extracting templates archive {}...", archive.display());
+  unpack(&archive, staging.path()).map_err(archive_error)?;
+  fs::write(staging.path().join(EXTRACTED_MARKER), &fingerprint)?;
+  if target.exists() {
+    fs::remove_dir_all(&target)?;
+  }
+  fs::rename(staging.keep(), &target)?;
+  info!("Extracted templates archive to {}", target.display());
+  Ok(templates_root(&target))
+}
+
+/// Unpacks `archive` into `dest` according to its extension. Entries that would land outside
+/// `dest` are skipped by both the tar and zip readers.
+fn unpack(archive: &Path, dest: &Path) -> Result<(), String> {
+  let file_name = archive
+    .file_name()
+    .map(|name| name.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  let file = File::open(archive).map_err(|e| e.to_string())?;
+  if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+    tar::Archive::new(GzDecoder::new(file)).unpack(dest).map_err(|e| e.to_string())
+  } else if file_name.ends_with(".tar") {
+    tar::Archive::new(file).unpack(dest).map_err(|e| e.to_string())
+  } else if file_name.ends_with(".zip") {
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    zip.extract(dest).map_err(|e| e.to_string())
+  } else {
+    Err("unsupported archive type (expected .tar.gz, .tgz, .tar or .zip)".to_string())
+  }
+}
+
+/// Archives often wrap the templates in one top-level directory (e.g. `templates/`); use it
+/// as the root when it is the only entry and is not a template itself.
+fn templates_root(extracted: &Path) -> PathBuf {
+  let entries: Vec<PathBuf> = match fs::read_dir(extracted) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok().map(|e| e.path()))
+      .filter(|path| path.file_name().is_some_and(|name| name != EXTRACTED_MARKER))
+      .collect(),
+    Err(_) => return extracted.to_path_buf(),
+  };
+  match entries.as_slice() {
+    [only] if only.is_dir() && !only.join(MANIFEST_FILE_NAME).is_file() => only.clone(),
+    _ => extracted.to_path_buf(),
+  }
+}
@@ -1,7 +1,11 @@
 // src/cli.rs
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 use std::path::PathBuf;
 
+use crate::config::ProgressUnit;
+use crate::utils;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "spawnpoint", // Command name users type
@@ -17,20 +21,178 @@ pub struct Cli {
   /// Increase verbosity level (e.g., -v, -vv)
   #[arg(short, long, action = clap::ArgAction::Count)]
   pub verbose: u8,
+
+  /// Only log warnings and errors, and hide the progress bar (an explicit -v wins)
+  #[arg(short, long, global = true)]
+  pub quiet: bool,
+
+  /// Colored prompts, progress bar and logs: `auto` (stderr is a terminal and `NO_COLOR`
+  /// is unset), `always` or `never`
+  #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+  pub color: ColorChoice,
   
   #[arg(long)] // Configures the --templates-dir command-line flag
   #[clap(env = "SPAWNPOINT_TEMPLATES_DIR")] // Configures the environment variable fallback
   pub templates_dir: Option<PathBuf>,
+
+  /// Never fall back to the templates built into the binary; only use external directories
+  #[arg(long, global = true)]
+  pub no_builtin: bool,
+
+  /// Use templates from this git repository (shallow-cloned into the cache; overrides --templates-dir)
+  #[arg(long, global = true, value_name = "URL")]
+  #[clap(env = "SPAWNPOINT_TEMPLATE_REPO")]
+  pub template_repo: Option<String>,
+
+  /// Use templates from this .tar.gz/.tgz, .tar or .zip archive (extracted into the cache;
+  /// overrides --templates-dir)
+  #[arg(long, global = true, value_name = "PATH", conflicts_with = "template_repo")]
+  #[clap(env = "SPAWNPOINT_TEMPLATES_ARCHIVE")]
+  pub templates_archive: Option<PathBuf>,
+
+  /// Pull the latest commit of --template-repo (or of a root config's `registryUrl`) instead of
+  /// using the cached checkout
+  #[arg(long, global = true)]
+  pub refresh: bool,
+
+  /// Log how every placeholder value is computed (variable, input, case, result)
+  #[arg(long, global = true)]
+  pub trace_transforms: bool,
+
+  /// Stderr policy for hook/validation steps: `respect` each step's `checkStderr`,
+  /// fail on any stderr (`always`), or ignore stderr (`never`)
+  #[arg(long, global = true, value_enum, default_value_t = StderrPolicy::Respect)]
+  pub check_stderr: StderrPolicy,
+
+  /// Timeout in seconds for pre/post-generate hooks that don't set `timeoutSecs`
+  #[arg(long, global = true, value_name = "SECS")]
+  pub hook_timeout: Option<u64>,
+
+  /// How many trailing lines of a failed step's stderr (or stdout, if stderr is empty) to
+  /// show in warnings and errors; `-vv` shows all of it
+  #[arg(long, global = true, value_name = "N", default_value_t = utils::DEFAULT_OUTPUT_TAIL_LINES)]
+  pub output_tail_lines: usize,
+
+  /// Treat recoverable manifest/generation warnings as errors (useful for CI)
+  #[arg(long, global = true, alias = "manifest-warnings-as-errors")]
+  pub strict_warnings: bool,
+}
+
+/// A `--var NAME=VALUE` argument. `Debug` prints the name only: whether the value is a
+/// secret isn't known until the manifest is read, and the parsed arguments are logged first.
+#[derive(Clone)]
+pub struct VarAssignment {
+  pub name: String,
+  pub value: String,
+}
+
+impl std::str::FromStr for VarAssignment {
+  type Err = String;
+
+  fn from_str(assignment: &str) -> Result<Self, Self::Err> {
+    match assignment.split_once('=') {
+      Some((name, value)) if !name.trim().is_empty() => Ok(VarAssignment {
+        name: name.trim().to_string(),
+        value: value.to_string(),
+      }),
+      _ => Err("expected name=value".to_string()),
+    }
+  }
+}
+
+impl std::fmt::Debug for VarAssignment {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}=****", self.name)
+  }
+}
+
+/// When to use colors in prompts, the progress bar and logs.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+  /// Color when stderr is a terminal and `NO_COLOR` is not set
+  #[default]
+  Auto,
+  /// Always color, even when piped or with `NO_COLOR`
+  Always,
+  /// Never color
+  Never,
+}
+
+/// Global override for the per-step `checkStderr` setting.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StderrPolicy {
+  /// Never fail a step because of stderr output
+  Never,
+  /// Fail any step that writes to stderr (unless it has `ignoreErrors`)
+  Always,
+  /// Use each step's own `checkStderr` flag
+  Respect,
+}
+
+impl StderrPolicy {
+  /// The `checkStderr` value forced onto every step, or `None` to keep each step's own.
+  pub fn forced(self) -> Option<bool> {
+    match self {
+      StderrPolicy::Never => Some(false),
+      StderrPolicy::Always => Some(true),
+      StderrPolicy::Respect => None,
+    }
+  }
+}
+
+/// How `generate`/`describe` report their result on stdout.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+  /// Human-readable output
+  #[default]
+  Text,
+  /// Machine-readable JSON on stdout
+  Json,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
   /// List available templates
-  List,
+  List(ListArgs),
   /// Generate a new project scaffold
   Generate(GenerateArgs),
   /// Validate a specific template within the scaffolder
   Validate(ValidateArgs),
+  /// Update a generated project from its template using the recorded state file
+  Update(UpdateArgs),
+  /// Work with a template's generation hooks
+  Hooks(HooksArgs),
+  /// Show how two templates differ (variables, files, manifest fields)
+  Compare(CompareArgs),
+  /// Show a template's variables, conditional paths and hooks without generating anything
+  Describe(DescribeArgs),
+  /// Print where a template lives: its absolute directory and scaffold.yaml path
+  Which(WhichArgs),
+  /// Check a template's scaffold.yaml (by path) for structural problems without generating anything
+  ValidateManifest(ValidateManifestArgs),
+  /// Create a starter template (scaffold.yaml and a sample file) in the templates directory
+  NewTemplate(NewTemplateArgs),
+  /// Print a shell completion script (e.g. `spawnpoint completions bash > ~/.local/share/bash-completion/completions/spawnpoint`)
+  Completions(CompletionsArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+  /// Show the on-disk directory of each template
+  #[arg(long)]
+  pub show_path: bool,
+
+  /// Only list templates with this tag (repeatable; a template must have every given tag)
+  #[arg(long = "tag", value_name = "TAG")]
+  pub tags: Vec<String>,
+
+  /// Only list templates for this language (e.g., rust)
+  #[arg(short, long)]
+  pub language: Option<String>,
+
+  /// Print the templates as a JSON array instead of a table
+  #[arg(long)]
+  pub json: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -43,19 +205,332 @@ pub struct GenerateArgs {
   #[arg(short, long)]
   pub template: Option<String>,
 
-  /// Directory to generate the project into
+  /// Pick the nth (0-based) template of `--language` without prompting, in `list` order
+  #[arg(long, value_name = "N", requires = "language", conflicts_with = "template")]
+  pub template_index: Option<usize>,
+
+  /// Template version: exact (`2.1.0`) or a semver requirement (`^2`, `~2.1`); defaults to
+  /// the highest version
+  #[arg(long, value_name = "VERSION")]
+  pub template_version: Option<String>,
+
+  /// Directory to generate the project into; may use `{{varName}}` (e.g. `./{{projectName}}`)
   #[arg(short, long, default_value = ".")]
   pub output_dir: PathBuf,
-  // TODO: Add non-interactive variable flags if needed:
-  // #[arg(long)]
-  // pub var: Vec<String>, // e.g., --var name=value
+
+  /// Overwrite files that already exist in the output directory (by default generation fails)
+  #[arg(long)]
+  pub force: bool,
+
+  /// If generation, a post-generate hook or the build check fails, remove every file and
+  /// directory this run created (pre-existing files are kept)
+  #[arg(long)]
+  pub rollback_on_failure: bool,
+
+  /// After generating, print a unified diff of each file that already existed against the
+  /// content it was replaced with (binary files: changed or unchanged); use --dry-run to preview
+  #[arg(long, conflicts_with = "stdout_tar")]
+  pub diff: bool,
+
+  /// Render the project without writing files or running hooks (pair with --diff to preview)
+  #[arg(long, conflicts_with = "stdout_tar")]
+  pub dry_run: bool,
+
+  /// What the progress bar counts: `files` (default) or `bytes`, with throughput and ETA
+  /// (overrides the manifest's `progress`)
+  #[arg(long, value_enum, value_name = "UNIT")]
+  pub progress: Option<ProgressArg>,
+
+  /// Override the manifest's filename placeholder prefix (default: "__VAR_")
+  #[arg(long)]
+  pub placeholder_prefix: Option<String>,
+
+  /// Override the manifest's filename placeholder suffix (default: "__")
+  #[arg(long)]
+  pub placeholder_suffix: Option<String>,
+
+  /// Run the template's `buildCheck` step in the output directory after generation
+  #[arg(long)]
+  pub build_check: bool,
+
+  /// Binary file (relative to the template root) to substitute placeholders in byte-wise (repeatable)
+  #[arg(long, value_name = "PATH")]
+  pub replace_in_binary: Vec<PathBuf>,
+
+  /// Only generate template paths matching this pattern (`exclude` syntax, repeatable);
+  /// a matching directory includes everything in it
+  #[arg(long, value_name = "GLOB")]
+  pub only: Vec<String>,
+
+  /// Also leave out template paths matching this pattern (`exclude` syntax, repeatable)
+  #[arg(long, value_name = "GLOB")]
+  pub skip: Vec<String>,
+
+  /// Fail if generated text files still contain placeholder-like tokens (`__VAR_x__`, `--x--`)
+  #[arg(long)]
+  pub strict_placeholders: bool,
+
+  /// Keep generating when a single file fails; report all failures and exit non-zero at the end
+  #[arg(long)]
+  pub continue_on_file_error: bool,
+
+  /// Write `.spawnpoint-state.yaml` (file hashes and variables) into the output directory
+  #[arg(long)]
+  pub write_state: bool,
+
+  /// Write `.spawnpoint.lock` (template and non-sensitive variables) for `--answers` reuse
+  #[arg(long)]
+  pub write_lock: bool,
+
+  /// Abort when an interactive prompt gets no answer within this many seconds (0 = wait forever)
+  #[arg(long, value_name = "SECS")]
+  pub prompt_timeout_secs: Option<u64>,
+
+  /// Don't ask to confirm the variable summary before generating
+  #[arg(long)]
+  pub no_confirm: bool,
+
+  /// Run template hooks without asking even if the root config sets `trusted: false`
+  /// (`--trust-hooks=false` asks even if it doesn't)
+  #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+  pub trust_hooks: Option<bool>,
+
+  /// Override a manifest value in memory, e.g. `validation.steps.0.timeoutSecs=5` (repeatable)
+  #[arg(long = "set", value_name = "DOTPATH=VALUE")]
+  pub set: Vec<String>,
+
+  /// Keep UTF-8 byte order marks in generated text files (overrides the manifest's `bom: strip`)
+  #[arg(long)]
+  pub preserve_bom: bool,
+
+  /// Save each executed step's stdout/stderr to `<step>.stdout`/`<step>.stderr` in this directory
+  #[arg(long, value_name = "DIR", visible_alias = "log-dir")]
+  pub step_log_dir: Option<PathBuf>,
+
+  /// Stream the generated project to stdout as a tar archive instead of writing it to `--output-dir`
+  #[arg(long)]
+  pub stdout_tar: bool,
+
+  /// Print a summary of the result to stdout (`json`: template, variables, files, hook results)
+  #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "stdout_tar")]
+  pub output_format: OutputFormat,
+
+  /// Provide a variable non-interactively, e.g. `--var projectName=my-app` (repeatable)
+  #[arg(long, value_name = "NAME=VALUE")]
+  pub var: Vec<VarAssignment>,
+
+  /// YAML/JSON map of `name: value` answers; those variables are not prompted for (`--var` wins)
+  #[arg(long, value_name = "FILE")]
+  pub answers: Option<PathBuf>,
+
+  /// File of `name=value` lines with `${ENV}` expanded from the environment, e.g. for CI
+  /// secrets (repeatable; overrides `--answers`, `--var` wins)
+  #[arg(long, value_name = "FILE")]
+  pub var_file: Vec<PathBuf>,
+
+  /// Seed for `generator` variables, so their values are reproducible (random when omitted)
+  #[arg(long, value_name = "N")]
+  pub seed: Option<u64>,
 }
 
 #[derive(Parser, Debug)]
 pub struct ValidateArgs {
   /// Language/Framework of the template to validate
-  pub language: String,
+  #[arg(required_unless_present = "all")]
+  pub language: Option<String>,
 
   /// Specific template name to validate
+  #[arg(required_unless_present = "all")]
+  pub template: Option<String>,
+
+  /// Template version: exact (`2.1.0`) or a semver requirement (`^2`); defaults to the highest
+  #[arg(long, value_name = "VERSION", conflicts_with = "all")]
+  pub template_version: Option<String>,
+
+  /// Only check manifests offline (parsing, placeholders, conditional paths, variables); runs no commands
+  #[arg(long)]
+  pub only_manifest_validation: bool,
+
+  /// Check every template (requires --only-manifest-validation)
+  #[arg(long, requires = "only_manifest_validation", conflicts_with_all = ["language", "template"])]
+  pub all: bool,
+
+  /// Override the manifest's filename placeholder prefix (default: "__VAR_")
+  #[arg(long)]
+  pub placeholder_prefix: Option<String>,
+
+  /// Override the manifest's filename placeholder suffix (default: "__")
+  #[arg(long)]
+  pub placeholder_suffix: Option<String>,
+
+  /// Save each executed step's stdout/stderr to `<step>.stdout`/`<step>.stderr` in this directory
+  #[arg(long, value_name = "DIR", visible_alias = "log-dir")]
+  pub step_log_dir: Option<PathBuf>,
+
+  /// Override a manifest value in memory, e.g. `validation.steps.0.timeoutSecs=5` (repeatable)
+  #[arg(long = "set", value_name = "DOTPATH=VALUE")]
+  pub set: Vec<String>,
+
+  /// Only run validation steps with at least one of these tags (comma-separated or repeated)
+  #[arg(long, value_delimiter = ',')]
+  pub tags: Vec<String>,
+
+  /// Skip steps (including setup/teardown) with any of these tags
+  #[arg(long, value_delimiter = ',')]
+  pub skip_tags: Vec<String>,
+
+  /// Keep the temporary project after validation instead of deleting it
+  #[arg(long)]
+  pub keep: bool,
+
+  /// Generate the test project into this (new or empty) directory and keep it
+  #[arg(short, long, value_name = "DIR", conflicts_with = "keep")]
+  pub output_dir: Option<PathBuf>,
+
+  /// Seed for `generator` variables missing from `testVariables` (default 0, so runs repeat)
+  #[arg(long, value_name = "N", default_value_t = 0)]
+  pub seed: u64,
+}
+
+#[derive(Parser, Debug)]
+pub struct UpdateArgs {
+  /// Project directory containing `.spawnpoint-state.yaml`
+  #[arg(default_value = ".")]
+  pub project_dir: PathBuf,
+
+  /// Report what would change without modifying any files
+  #[arg(long)]
+  pub dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct HooksArgs {
+  #[command(subcommand)]
+  pub command: HooksCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HooksCommands {
+  /// Re-run a hook phase against an existing project without regenerating files
+  Run(HooksRunArgs),
+}
+
+/// `--progress` values (see `config::ProgressUnit`).
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressArg {
+  /// Files written (smooth for templates of many similar files)
+  Files,
+  /// Bytes of template files processed, with throughput and ETA (smooth for a few huge files)
+  Bytes,
+}
+
+impl From<ProgressArg> for ProgressUnit {
+  fn from(arg: ProgressArg) -> Self {
+    match arg {
+      ProgressArg::Files => ProgressUnit::Files,
+      ProgressArg::Bytes => ProgressUnit::Bytes,
+    }
+  }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookPhase {
+  /// preGenerate hooks
+  Pre,
+  /// postGenerate hooks
+  Post,
+}
+
+#[derive(Parser, Debug)]
+pub struct HooksRunArgs {
+  /// Hook phase to run
+  #[arg(long, value_enum, default_value = "post")]
+  pub phase: HookPhase,
+
+  /// Existing project directory to run the hooks in
+  #[arg(short, long, default_value = ".")]
+  pub dir: PathBuf,
+
+  /// Language/Framework of the template (defaults to the project's state file)
+  #[arg(short, long)]
+  pub language: Option<String>,
+
+  /// Specific template name (defaults to the project's state file)
+  #[arg(short, long)]
+  pub template: Option<String>,
+
+  /// Save each executed step's stdout/stderr to `<step>.stdout`/`<step>.stderr` in this directory
+  #[arg(long, value_name = "DIR", visible_alias = "log-dir")]
+  pub step_log_dir: Option<PathBuf>,
+
+  /// Don't ask for confirmation before running pre-generate hooks in a non-empty directory
+  #[arg(short, long)]
+  pub yes: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct DescribeArgs {
+  /// Language/Framework of the template
+  pub language: String,
+
+  /// Specific template name
+  pub template: String,
+
+  /// `json` prints the description as JSON (e.g. for editors building forms)
+  #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+  pub output_format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct WhichArgs {
+  /// Language/Framework of the template
+  pub language: String,
+
+  /// Specific template name
   pub template: String,
+
+  /// Template version: exact (`2.1.0`) or a semver requirement (`^2`); defaults to the highest
+  #[arg(long, value_name = "VERSION")]
+  pub template_version: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ValidateManifestArgs {
+  /// Template directory, or its scaffold.yaml
+  #[arg(default_value = ".")]
+  pub path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct NewTemplateArgs {
+  /// Template name as shown by `list`; the directory is its snake_case form
+  pub name: String,
+
+  /// Language/Framework the template is listed under
+  #[arg(short, long, default_value = "generic")]
+  pub language: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct CompletionsArgs {
+  /// Shell to generate completions for
+  #[arg(value_enum)]
+  pub shell: Shell,
+}
+
+#[derive(Parser, Debug)]
+pub struct CompareArgs {
+  /// First template (manifest name or directory name), e.g. the old version
+  pub left: String,
+
+  /// Second template (manifest name or directory name), e.g. the new version
+  pub right: String,
+
+  /// Only consider templates of this language when resolving the names
+  #[arg(short, long)]
+  pub language: Option<String>,
+
+  /// Print the comparison as JSON
+  #[arg(long)]
+  pub json: bool,
 }
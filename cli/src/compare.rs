@@ -0,0 +1,189 @@
+// src/compare.rs
+//! `compare`: structural diff between two templates (variables, files, manifest fields).
+//! Meant for maintainers moving a template from `_v1` to `_v2`; nothing is generated.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::cli::CompareArgs;
+use crate::config::ScaffoldManifest;
+use crate::error::SpawnError;
+use crate::registry::{TemplateRegistry, MANIFEST_FILE_NAME};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateComparison {
+  left: String,
+  right: String,
+  variables: SetDiff,
+  files: SetDiff,
+  /// Top-level manifest fields (other than `variables`) whose values differ.
+  changed_manifest_fields: Vec<String>,
+}
+
+/// Names only in the left template, only in the right, and present in both but different.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SetDiff {
+  removed: Vec<String>,
+  added: Vec<String>,
+  changed: Vec<String>,
+}
+
+impl SetDiff {
+  fn is_empty(&self) -> bool {
+    self.removed.is_empty() && self.added.is_empty() && self.changed.is_empty()
+  }
+
+  /// Diffs two keyed maps; values are compared for equality to detect changes.
+  fn between<V: PartialEq>(left: &BTreeMap<String, V>, right: &BTreeMap<String, V>) -> Self {
+    let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+    let mut diff = SetDiff::default();
+    for key in keys {
+      match (left.get(key), right.get(key)) {
+        (Some(_), None) => diff.removed.push(key.clone()),
+        (None, Some(_)) => diff.added.push(key.clone()),
+        (Some(l), Some(r)) if l != r => diff.changed.push(key.clone()),
+        _ => {}
+      }
+    }
+    diff
+  }
+}
+
+pub fn run_compare(args: CompareArgs, registry: &TemplateRegistry) -> Result<(), SpawnError> {
+  let templates = registry.templates();
+  let (left_path, left) = find_template(templates, &args.left, args.language.as_deref())?;
+  let (right_path, right) = find_template(templates, &args.right, args.language.as_deref())?;
+  // Compare what each generates from, including anything inherited through `extends`
+  let left_resolved = registry.resolve(left_path, left)?;
+  let right_resolved = registry.resolve(right_path, right)?;
+  let (left_path, left) = (left_resolved.path.as_path(), &left_resolved.manifest);
+  let (right_path, right) = (right_resolved.path.as_path(), &right_resolved.manifest);
+
+  let comparison = TemplateComparison {
+    left: left.name.clone(),
+    right: right.name.clone(),
+    variables: SetDiff::between(&variable_map(left)?, &variable_map(right)?),
+    files: SetDiff::between(&file_map(left_path)?, &file_map(right_path)?),
+    changed_manifest_fields: changed_fields(left, right)?,
+  };
+
+  if args.json {
+    println!("{}", serde_json::to_string_pretty(&comparison)?);
+  } else {
+    print_comparison(&comparison);
+  }
+  Ok(())
+}
+
+/// Finds a template by manifest name or directory name, optionally restricted to a language.
+fn find_template<'a>(
+  templates: &'a [(String, PathBuf, ScaffoldManifest)],
+  wanted: &str,
+  language: Option<&str>,
+) -> Result<(&'a Path, &'a ScaffoldManifest), SpawnError> {
+  let matches: Vec<&(String, PathBuf, ScaffoldManifest)> = templates
+    .iter()
+    .filter(|(dir_name, _, m)| m.name == wanted || dir_name == wanted)
+    .filter(|(_, _, m)| language.is_none_or(|lang| m.language == lang))
+    .collect();
+  match matches.as_slice() {
+    [(_, path, manifest)] => Ok((path.as_path(), manifest)),
+    [] => Err(SpawnError::TemplateNotFound(format!(
+      "Template '{}' not found.",
+      wanted
+    ))),
+    _ => Err(SpawnError::GenerationError(format!(
+      "Template name '{}' is ambiguous; pass --language to pick one.",
+      wanted
+    ))),
+  }
+}
+
+/// Variable definitions keyed by name, as YAML values so any field change counts.
+fn variable_map(manifest: &ScaffoldManifest) -> Result<BTreeMap<String, serde_yaml::Value>, SpawnError> {
+  manifest
+    .variables
+    .iter()
+    .map(|vd| Ok((vd.name.clone(), serde_yaml::to_value(vd)?)))
+    .collect()
+}
+
+/// Template files (excluding the manifest) keyed by '/'-separated relative path, with their contents.
+fn file_map(template_path: &Path) -> Result<BTreeMap<String, Vec<u8>>, SpawnError> {
+  let mut files = BTreeMap::new();
+  for entry in WalkDir::new(template_path) {
+    let entry = entry.map_err(|e| SpawnError::WalkDirError {
+      path: template_path.to_path_buf(),
+      source: e,
+    })?;
+    if !entry.file_type().is_file() {
+      continue;
+    }
+    let Ok(relative) = entry.path().strip_prefix(template_path) else {
+      continue;
+    };
+    if relative == Path::new(MANIFEST_FILE_NAME) {
+      continue;
+    }
+    let key = relative.to_string_lossy().replace('\\', "/");
+    files.insert(key, fs::read(entry.path())?);
+  }
+  Ok(files)
+}
+
+/// Top-level manifest fields (except identity fields and `variables`) that differ.
+fn changed_fields(left: &ScaffoldManifest, right: &ScaffoldManifest) -> Result<Vec<String>, SpawnError> {
+  let to_map = |m: &ScaffoldManifest| -> Result<serde_yaml::Mapping, SpawnError> {
+    match serde_yaml::to_value(m)? {
+      serde_yaml::Value::Mapping(map) => Ok(map),
+      _ => Ok(serde_yaml::Mapping::new()),
+    }
+  };
+  let (left_map, right_map) = (to_map(left)?, to_map(right)?);
+  let mut keys: BTreeSet<String> = BTreeSet::new();
+  for key in left_map.keys().chain(right_map.keys()) {
+    if let Some(name) = key.as_str() {
+      if !matches!(name, "name" | "variables") && left_map.get(key) != right_map.get(key) {
+        keys.insert(name.to_string());
+      }
+    }
+  }
+  Ok(keys.into_iter().collect())
+}
+
+fn print_comparison(comparison: &TemplateComparison) {
+  println!("Comparing '{}' -> '{}'", comparison.left, comparison.right);
+  print_set_diff("Variables", &comparison.variables);
+  print_set_diff("Files", &comparison.files);
+  if comparison.changed_manifest_fields.is_empty() {
+    println!("\nManifest fields: no changes");
+  } else {
+    println!("\nManifest fields:");
+    for field in &comparison.changed_manifest_fields {
+      println!("  ~ {}", field);
+    }
+  }
+}
+
+fn print_set_diff(title: &str, diff: &SetDiff) {
+  if diff.is_empty() {
+    println!("\n{}: no changes", title);
+    return;
+  }
+  println!("\n{}:", title);
+  for name in &diff.removed {
+    println!("  - {}", name);
+  }
+  for name in &diff.added {
+    println!("  + {}", name);
+  }
+  for name in &diff.changed {
+    println!("  ~ {}", name);
+  }
+}
@@ -1,20 +1,105 @@
 // src/config.rs
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::error::SpawnError;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct Condition {
-    pub variable: String, // Name of the boolean variable
+    pub variable: String, // Name of the variable to test
     #[serde(default = "default_condition_value")]
-    pub value: String, // Expected value (usually "true" or "false")
+    pub value: String, // Expected value (usually "true" or "false"); a regex for `matches`
+    #[serde(default)]
+    pub operator: ConditionOperator,
+    #[serde(default)]
+    pub values: Vec<String>, // Candidates for `in`/`notIn` (falls back to `value` when empty)
 }
 
 // Default condition expects the variable to be "true"
 fn default_condition_value() -> String { "true".to_string() }
 
+impl Condition {
+    /// Candidate values for `in`/`notIn`.
+    pub fn candidates(&self) -> Vec<&str> {
+        if self.values.is_empty() {
+            vec![self.value.as_str()]
+        } else {
+            self.values.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operator {
+            ConditionOperator::Equals => write!(f, "{} == '{}'", self.variable, self.value),
+            ConditionOperator::NotEquals => write!(f, "{} != '{}'", self.variable, self.value),
+            ConditionOperator::In => write!(f, "{} in [{}]", self.variable, self.candidates().join(", ")),
+            ConditionOperator::NotIn => {
+                write!(f, "{} not in [{}]", self.variable, self.candidates().join(", "))
+            }
+            ConditionOperator::Matches => write!(f, "{} matches /{}/", self.variable, self.value),
+        }
+    }
+}
+
+/// A condition or a nested `all`/`any` group of conditions (used by `conditionalPaths`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ConditionExpr {
+    /// Met when every nested condition is met
+    All {
+        all: Vec<ConditionExpr>,
+    },
+    /// Met when at least one nested condition is met
+    Any {
+        any: Vec<ConditionExpr>,
+    },
+    Single(Condition),
+}
+
+impl ConditionExpr {
+    /// Every leaf condition, depth-first.
+    pub fn leaves(&self) -> Vec<&Condition> {
+        match self {
+            ConditionExpr::All { all: group } | ConditionExpr::Any { any: group } => {
+                group.iter().flat_map(ConditionExpr::leaves).collect()
+            }
+            ConditionExpr::Single(condition) => vec![condition],
+        }
+    }
+}
+
+impl std::fmt::Display for ConditionExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (group, joiner) = match self {
+            ConditionExpr::All { all } => (all, " and "),
+            ConditionExpr::Any { any } => (any, " or "),
+            ConditionExpr::Single(condition) => return write!(f, "{}", condition),
+        };
+        let parts: Vec<String> = group.iter().map(ToString::to_string).collect();
+        write!(f, "({})", parts.join(joiner))
+    }
+}
+
+/// How a `Condition` compares its variable. Comparisons other than `matches` ignore case.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionOperator {
+    #[default]
+    Equals,
+    NotEquals,
+    In,
+    NotIn,
+    /// `value` is a regex (requires the `regex` feature)
+    Matches,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)] // Good practice to catch typos in yaml
 #[serde(rename_all = "camelCase")]
@@ -22,28 +107,226 @@ pub struct ScaffoldManifest {
   pub name: String,
   pub description: String,
   pub language: String,
+  /// Semver version (e.g. "2.1.0"). Templates sharing a name and language are versions of one
+  /// template; the highest is used unless `--template-version` picks another.
+  #[serde(default)]
+  pub version: Option<semver::Version>,
+  /// Free-form labels (e.g. "backend", "cli") for `list --tag`.
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /// Programs that must be on `PATH` before `generate` starts (e.g. "node", "git").
+  #[serde(default)]
+  pub required_tools: Vec<String>,
+  /// Oldest spawnpoint release that understands this manifest (e.g. "1.2.0"); checked
+  /// before the rest of the manifest is parsed.
+  #[serde(default)]
+  pub min_spawnpoint_version: Option<String>,
+  /// Name of a template (same language) whose files and manifest this one is layered on.
+  #[serde(default)]
+  pub extends: Option<String>,
+  #[serde(default)] // May be empty when inherited through `extends`
   pub variables: Vec<VariableDefinition>,
+  /// Values computed from other variables once they are known (never prompted).
+  #[serde(default)]
+  pub derived: Vec<DerivedVariable>,
   #[serde(default)]
   pub placeholder_filenames: Option<PlaceholderFilenames>,
   #[serde(default)]
   pub binary_extensions: Vec<String>,
   #[serde(default)]
   pub binary_files: Vec<PathBuf>, // Relative to template root
+  /// Treat unlisted files whose first bytes contain NUL or invalid UTF-8 as binary.
+  #[serde(default = "default_true")]
+  pub autodetect_binary: bool,
+  /// Binary files (relative to template root) that get byte-level placeholder replacement.
+  #[serde(default)]
+  pub replace_in_binary: Vec<PathBuf>,
   // --- Conditional Paths ---
   /// Map from relative template path (String) to the condition for inclusion.
   #[serde(default)]
-  pub conditional_paths: HashMap<String, Condition>,
+  pub conditional_paths: HashMap<String, ConditionExpr>,
   #[serde(default)]
   pub exclude: Vec<String>,
+  /// What to do with a UTF-8 byte order mark at the start of text template files.
+  #[serde(default)]
+  pub bom: BomHandling,
+  /// Only substitute placeholders wrapped in these delimiters in file contents (e.g. `{{name}}`).
+  /// Without it, every literal occurrence of a placeholder is replaced (legacy behavior).
+  #[serde(default)]
+  pub content_delimiters: Option<ContentDelimiters>,
+  /// Expand `${ENV_VAR}` in text files from the environment, after placeholder substitution.
+  #[serde(default)]
+  pub expand_env_in_content: bool,
+  /// What to do with `${ENV_VAR}` references to unset variables.
+  #[serde(default)]
+  pub undefined_env: UndefinedEnv,
+  /// Text files larger than this are substituted line by line while streaming instead of
+  /// being read into memory whole (default: `DEFAULT_MAX_SUBSTITUTION_BYTES`). Placeholders
+  /// and delimited tokens spanning a newline are not replaced in such files.
+  #[serde(default)]
+  pub max_substitution_bytes: Option<u64>,
+  /// Progress bar unit for this template, e.g. `bytes` for a few large files
+  /// (`--progress` overrides it).
+  #[serde(default)]
+  pub progress: Option<ProgressUnit>,
   // --- Hooks ---
   #[serde(default)]
   pub pre_generate: Vec<ValidationStep>, // Runs before generation
   #[serde(default)]
   pub post_generate: Vec<ValidationStep>, // Runs after generation
+  /// Shown to the user once `generate` is done (e.g. "cd {{projectName}} && npm install").
+  /// Supports `{{varName}}` like hook commands.
+  #[serde(default)]
+  pub post_message: Option<String>,
+  /// Canonical "does it build" check, run in the output dir with `generate --build-check`.
+  #[serde(default)]
+  pub build_check: Option<ValidationStep>,
   #[serde(default)]
   pub validation: Option<ValidationConfig>,
 }
 
+impl ScaffoldManifest {
+  /// How `placeholder` is written in file contents: wrapped in `contentDelimiters` if set.
+  pub fn content_token(&self, placeholder: &str) -> String {
+    match &self.content_delimiters {
+      Some(delimiters) => format!("{}{}{}", delimiters.open, placeholder, delimiters.close),
+      None => placeholder.to_string(),
+    }
+  }
+
+  /// Sets `checkStderr` on every hook, build-check and validation step (the global
+  /// `--check-stderr always|never`); `None` keeps each step's own setting.
+  pub fn force_check_stderr(&mut self, check_stderr: Option<bool>) {
+    let Some(forced) = check_stderr else {
+      return;
+    };
+    let validation_steps = self
+      .validation
+      .iter_mut()
+      .flat_map(|v| v.setup.iter_mut().chain(v.steps.iter_mut()).chain(v.teardown.iter_mut()));
+    for step in self
+      .pre_generate
+      .iter_mut()
+      .chain(self.post_generate.iter_mut())
+      .chain(self.build_check.iter_mut())
+      .chain(validation_steps)
+    {
+      step.check_stderr = forced;
+    }
+  }
+
+  /// Applies the global `--hook-timeout` to pre/post-generate hooks without `timeoutSecs`.
+  pub fn apply_hook_timeout(&mut self, timeout_secs: Option<u64>) {
+    let Some(timeout_secs) = timeout_secs else {
+      return;
+    };
+    for step in self.pre_generate.iter_mut().chain(self.post_generate.iter_mut()) {
+      step.timeout_secs.get_or_insert(timeout_secs);
+    }
+  }
+}
+
+/// Default for `maxSubstitutionBytes` (16 MiB).
+pub const DEFAULT_MAX_SUBSTITUTION_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum UndefinedEnv {
+    /// Leave `${NAME}` as-is
+    #[default]
+    Keep,
+    /// Fail generation of the file
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum BomHandling {
+    /// Drop the BOM from generated text files (most compilers/tools don't expect one)
+    #[default]
+    Strip,
+    /// Copy the BOM through unchanged
+    Preserve,
+}
+
+/// What the file-copy progress bar counts.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressUnit {
+    /// Files written (smooth for templates of many similar files)
+    #[default]
+    Files,
+    /// Bytes of template files processed, with throughput and ETA (smooth for a few huge files)
+    Bytes,
+}
+
+/// Fills a variable with a generated value instead of prompting, e.g. `generator: uuid` or
+/// `generator: { randomHex: 32 }`. `--seed` makes the values reproducible.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(from = "GeneratorSpec", into = "GeneratorSpec")]
+pub enum VariableGenerator {
+    /// Lowercase hex string with this many characters
+    RandomHex(usize),
+    /// Random (version 4) UUID
+    Uuid,
+    /// Port number in the dynamic range (49152-65535)
+    RandomPort,
+}
+
+/// YAML spelling of a `VariableGenerator`: a bare name, or a one-key map for sized generators
+/// (serde_yaml would otherwise expect `!randomHex 32`).
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, expecting = "`uuid`, `randomPort` or `{ randomHex: <length> }`")]
+enum GeneratorSpec {
+    Named(NamedGenerator),
+    Sized(SizedGenerator),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum NamedGenerator {
+    Uuid,
+    RandomPort,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SizedGenerator {
+    random_hex: usize,
+}
+
+impl From<GeneratorSpec> for VariableGenerator {
+    fn from(spec: GeneratorSpec) -> Self {
+        match spec {
+            GeneratorSpec::Named(NamedGenerator::Uuid) => VariableGenerator::Uuid,
+            GeneratorSpec::Named(NamedGenerator::RandomPort) => VariableGenerator::RandomPort,
+            GeneratorSpec::Sized(sized) => VariableGenerator::RandomHex(sized.random_hex),
+        }
+    }
+}
+
+impl From<VariableGenerator> for GeneratorSpec {
+    fn from(generator: VariableGenerator) -> Self {
+        match generator {
+            VariableGenerator::RandomHex(random_hex) => {
+                GeneratorSpec::Sized(SizedGenerator { random_hex })
+            }
+            VariableGenerator::Uuid => GeneratorSpec::Named(NamedGenerator::Uuid),
+            VariableGenerator::RandomPort => GeneratorSpec::Named(NamedGenerator::RandomPort),
+        }
+    }
+}
+
+impl std::fmt::Display for VariableGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariableGenerator::RandomHex(len) => write!(f, "randomHex({})", len),
+            VariableGenerator::Uuid => write!(f, "uuid"),
+            VariableGenerator::RandomPort => write!(f, "randomPort"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum CaseTransformation {
@@ -52,19 +335,44 @@ pub enum CaseTransformation {
     SnakeCase,      // my_variable
     KebabCase,      // my-variable
     ShoutySnakeCase, // MY_VARIABLE
-    PackageName, 
+    TitleCase,      // My Variable
+    TrainCase,      // My-Variable
+    PackageName,    // myvariable (drops every non-alphanumeric)
+    NpmPackageName, // my-variable (keeps `-._~`)
+    CargoPackageName, // my-variable (keeps `-_`)
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+impl CaseTransformation {
+    /// Looks up a transformation by its manifest spelling (e.g. "kebabCase").
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_yaml::from_value(serde_yaml::Value::String(name.to_string())).ok()
+    }
+
+    /// The manifest spelling of this transformation (e.g. "kebabCase").
+    pub fn name(&self) -> String {
+        serde_yaml::to_value(self)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("{:?}", self))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum VariableType {
+    #[default]
     String,
     Boolean,
-    // Could add Integer, etc. later
-}
-
-impl Default for VariableType {
-    fn default() -> Self { VariableType::String }
+    /// Whole number (`i64`), optionally bounded by `min`/`max`
+    Integer,
+    /// Decimal number (`f64`), optionally bounded by `min`/`max`
+    Float,
+    /// SPDX id chosen from the bundled licenses; `placeholderValue` expands to the full license text.
+    License,
+    /// One of the strings in `choices`, picked from a list
+    Choice,
+    /// Any subset of `choices`, stored joined with `separator`
+    MultiSelect,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -78,6 +386,9 @@ pub struct VariableDefinition {
     pub var_type: VariableType, // Added type hint
     #[serde(default)]
     pub sensitive: bool,
+    /// For sensitive variables: key to look up in the OS keychain (requires `keyring` feature).
+    #[serde(default)]
+    pub keychain_key: Option<String>,
     #[serde(default)]
     pub default: Option<String>,
     /// Defines transformations and the placeholders to use for them.
@@ -85,6 +396,131 @@ pub struct VariableDefinition {
     pub transformations: HashMap<CaseTransformation, String>, // e.g., { PascalCase: "__PASCAL_VAR__" }
     #[serde(default)]
     pub validation_regex: Option<String>,
+    /// Only prompt for this variable when the condition holds (checked against earlier variables).
+    /// A skipped variable is left out of substitution entirely, so its placeholders stay intact.
+    #[serde(default)]
+    pub when: Option<Condition>,
+    /// For numeric variables: smallest accepted value (inclusive).
+    #[serde(default)]
+    pub min: Option<NumericBound>,
+    /// For numeric variables: largest accepted value (inclusive).
+    #[serde(default)]
+    pub max: Option<NumericBound>,
+    /// For `varType: license`: name of the variable holding the copyright holder.
+    #[serde(default)]
+    pub license_holder: Option<String>,
+    /// For `varType: choice`/`multiSelect`: the values offered, in display order.
+    #[serde(default)]
+    pub choices: Vec<String>,
+    /// For `varType: multiSelect`: joins the selected choices (default `,`).
+    #[serde(default)]
+    pub separator: Option<String>,
+    /// Generates the value without prompting unless it is given with `--var`.
+    #[serde(default)]
+    pub generator: Option<VariableGenerator>,
+}
+
+/// A `min`/`max` bound as written. Whole numbers stay exact, so `integer` variables are
+/// compared without a lossy trip through `f64` (which can't tell apart integers above 2^53).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum NumericBound {
+    Integer(i64),
+    Float(f64),
+}
+
+impl NumericBound {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            NumericBound::Integer(bound) => bound as f64,
+            NumericBound::Float(bound) => bound,
+        }
+    }
+
+    /// Exact ordering of an integer `value` relative to this bound.
+    pub fn cmp_integer(self, value: i64) -> Ordering {
+        // 2^63, the first f64 above every i64
+        const I64_END: f64 = 9_223_372_036_854_775_808.0;
+        match self {
+            NumericBound::Integer(bound) => value.cmp(&bound),
+            NumericBound::Float(bound) if bound.is_nan() => Ordering::Equal,
+            NumericBound::Float(bound) if bound >= I64_END => Ordering::Less,
+            NumericBound::Float(bound) if bound < -I64_END => Ordering::Greater,
+            NumericBound::Float(bound) => {
+                // floor <= bound < floor + 1, and floor fits an i64 exactly
+                let floor = bound.floor();
+                match value.cmp(&(floor as i64)) {
+                    Ordering::Equal if bound > floor => Ordering::Less,
+                    ordering => ordering,
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for NumericBound {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for NumericBound {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (NumericBound::Integer(a), NumericBound::Integer(b)) => Some(a.cmp(b)),
+            (NumericBound::Integer(a), NumericBound::Float(_)) => {
+                Some(other.cmp_integer(*a))
+            }
+            (NumericBound::Float(_), NumericBound::Integer(b)) => {
+                Some(self.cmp_integer(*b).reverse())
+            }
+            (NumericBound::Float(a), NumericBound::Float(b)) => a.partial_cmp(b),
+        }
+    }
+}
+
+impl std::fmt::Display for NumericBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumericBound::Integer(bound) => write!(f, "{}", bound),
+            NumericBound::Float(bound) => write!(f, "{}", bound),
+        }
+    }
+}
+
+impl VariableDefinition {
+    /// Separator of a `multiSelect` value.
+    pub fn separator(&self) -> &str {
+        self.separator.as_deref().unwrap_or(",")
+    }
+}
+
+/// A placeholder filled from other variables, e.g. `template: "{{orgScope}}/{{projectName.kebabCase}}"`.
+/// Entries are evaluated in order; several entries may share a name, and the first one whose
+/// `when` holds provides the value.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedVariable {
+    pub name: String,
+    pub placeholder_value: String,
+    /// References `{{varName}}` (a variable or an earlier derived value) or
+    /// `{{varName.<transformation>}}`, e.g. `{{projectName.snakeCase}}`.
+    pub template: String,
+    #[serde(default)]
+    pub when: Option<ConditionExpr>,
+}
+
+/// Delimiters marking placeholders in file contents: `{{name}}` with `open: "{{"`, `close: "}}"`.
+/// Whitespace just inside the delimiters is ignored (`{{ name }}`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentDelimiters {
+  #[serde(default = "default_content_open")]
+  pub open: String,
+  #[serde(default = "default_content_close")]
+  pub close: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -96,6 +532,23 @@ pub struct PlaceholderFilenames {
   #[serde(default = "default_var_suffix")]
   pub suffix: String,
 }
+impl Default for PlaceholderFilenames {
+  fn default() -> Self {
+    PlaceholderFilenames {
+      prefix: default_var_prefix(),
+      suffix: default_var_suffix(),
+    }
+  }
+}
+fn default_true() -> bool {
+  true
+}
+fn default_content_open() -> String {
+  "{{".to_string()
+}
+fn default_content_close() -> String {
+  "}}".to_string()
+}
 fn default_var_prefix() -> String {
   "__VAR_".to_string()
 }
@@ -111,6 +564,9 @@ pub struct ValidationConfig {
   #[serde(default)]
   pub setup: Vec<ValidationStep>,
   pub steps: Vec<ValidationStep>,
+  /// Run `steps` concurrently (setup and teardown always run in order).
+  #[serde(default)]
+  pub parallel: bool,
   #[serde(default)]
   pub teardown: Vec<ValidationStep>,
 }
@@ -120,17 +576,257 @@ pub struct ValidationConfig {
 #[serde(rename_all = "camelCase")]
 pub struct ValidationStep {
   pub name: String,
+  /// Command line run through `shell`. Ignored when `args` is set.
+  #[serde(default)]
   pub command: String,
+  /// Program and arguments run directly, without a shell (`{{varName}}` is filled in each).
+  #[serde(default)]
+  pub args: Vec<String>,
+  /// Shell for `command` (e.g. "bash"); defaults to `sh`, or `cmd` on Windows.
+  #[serde(default)]
+  pub shell: Option<String>,
   #[serde(default)]
   pub working_dir: Option<PathBuf>, // Relative to generated dir root
+  /// Extra environment variables; values may use `{{varName}}` like the command.
   #[serde(default)]
   pub env: HashMap<String, String>,
+  /// Start from an empty environment (keeping only `utils::CLEAN_ENV_PRESERVED`) plus `env`,
+  /// instead of inheriting spawnpoint's.
+  #[serde(default)]
+  pub clean_env: bool,
   #[serde(default)]
   pub timeout_secs: Option<u64>,
   #[serde(default)]
   pub ignore_errors: bool, // Don't fail validation if this step errors
+  /// Failure classes to tolerate without `ignoreErrors` ignoring everything, e.g. `[timeout]`.
+  #[serde(default)]
+  pub ignore: Vec<StepFailure>,
   #[serde(default)]
   pub always_run: bool, // Primarily for teardown
   #[serde(default)]
   pub check_stderr: bool, // Fail if stderr is not empty
+  #[serde(default)]
+  pub tags: Vec<String>, // For selective execution via `validate --tags/--skip-tags`
+  /// Hooks only: consecutive steps with the same group run concurrently; a step without
+  /// one waits for everything before it and runs alone.
+  #[serde(default)]
+  pub group: Option<String>,
+}
+
+/// A way a step can fail, for a step's `ignore` list.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StepFailure {
+  /// Non-zero exit status (including "command not found")
+  Status,
+  /// Output on stderr with `checkStderr`
+  Stderr,
+  /// Killed after `timeoutSecs`
+  Timeout,
+}
+
+impl ValidationStep {
+  /// Whether a failure of this class is tolerated (`ignoreErrors` tolerates all of them).
+  pub fn ignores(&self, failure: StepFailure) -> bool {
+    self.ignore_errors || self.ignore.contains(&failure)
+  }
+
+  /// Whether an execution error from `utils::run_command` is tolerated. Only timeouts can be
+  /// ignored selectively; other errors (e.g. the program could not be started) need `ignoreErrors`.
+  pub fn ignores_error(&self, error: &SpawnError) -> bool {
+    match error {
+      SpawnError::CommandTimeout { .. } => self.ignores(StepFailure::Timeout),
+      _ => self.ignore_errors,
+    }
+  }
+}
+
+/// Optional `.spawnpoint.yaml` at the root of a templates directory, shipping team defaults.
+/// Unknown keys are ignored so newer templates repos keep working with older binaries.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RootConfig {
+  /// Language used by `generate` when `--language` is not given.
+  #[serde(default)]
+  pub default_language: Option<String>,
+  /// Template name used by `generate` when `--template` is not given (within the default language).
+  #[serde(default)]
+  pub default_template: Option<String>,
+  /// Git repository whose templates are layered on top of these directories, unless
+  /// `--template-repo` or `--templates-archive` picks the templates explicitly.
+  #[serde(default)]
+  pub registry_url: Option<String>,
+  /// With `false`, `generate` asks before running template hooks (and skips them without a
+  /// terminal) unless `--trust-hooks` is given.
+  #[serde(default)]
+  pub trusted: Option<bool>,
+}
+
+pub const ROOT_CONFIG_FILE_NAME: &str = ".spawnpoint.yaml";
+
+impl RootConfig {
+  /// Loads `.spawnpoint.yaml` from the templates root; a missing file yields the defaults.
+  pub fn load(templates_dir: &Path) -> Result<Self, SpawnError> {
+    let path = templates_dir.join(ROOT_CONFIG_FILE_NAME);
+    if !path.is_file() {
+      return Ok(RootConfig::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| SpawnError::ManifestReadError {
+      manifest_path: path.clone(),
+      source: e,
+    })?;
+    serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+      manifest_path: path,
+      source: e,
+    })
+  }
+
+  /// Loads `.spawnpoint.yaml` from each templates root in order; values set in a later root
+  /// override earlier ones. A later `defaultLanguage` also drops an earlier `defaultTemplate`,
+  /// which belongs to the earlier language.
+  pub fn load_all(templates_dirs: &[PathBuf]) -> Result<Self, SpawnError> {
+    let mut merged = RootConfig::default();
+    for templates_dir in templates_dirs {
+      let config = RootConfig::load(templates_dir)?;
+      if config.default_language.is_some() {
+        merged.default_language = config.default_language;
+        merged.default_template = None;
+      }
+      if config.default_template.is_some() {
+        merged.default_template = config.default_template;
+      }
+      if config.registry_url.is_some() {
+        merged.registry_url = config.registry_url;
+      }
+      if config.trusted.is_some() {
+        merged.trusted = config.trusted;
+      }
+    }
+    Ok(merged)
+  }
+}
+
+/// Applies `--set <dotpath>=<value>` overrides to a raw manifest document before typed
+/// deserialization. Path segments are manifest keys (camelCase, as written in YAML) or
+/// 0-based sequence indices; the value is parsed as YAML (`5`, `true`, `foo`, `[a, b]`).
+/// Every segment but the last must already exist; the last may add a new mapping key.
+pub fn apply_manifest_overrides(
+  doc: &mut serde_yaml::Value,
+  overrides: &[String],
+) -> Result<(), SpawnError> {
+  for expr in overrides {
+    let invalid = |reason: String| SpawnError::ManifestOverride {
+      expr: expr.clone(),
+      reason,
+    };
+    let (path, raw_value) = expr
+      .split_once('=')
+      .ok_or_else(|| invalid("expected <dotpath>=<value>".to_string()))?;
+    let segments: Vec<&str> = path.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+      return Err(invalid("empty path segment".to_string()));
+    }
+    let new_value: serde_yaml::Value =
+      serde_yaml::from_str(raw_value).map_err(|e| invalid(format!("invalid value: {}", e)))?;
+
+    let (last, parents) = segments.split_last().expect("split always yields a segment");
+    let mut node = &mut *doc;
+    for segment in parents {
+      node = match node {
+        serde_yaml::Value::Mapping(map) => map
+          .get_mut(*segment)
+          .ok_or_else(|| invalid(format!("'{}' not found", segment)))?,
+        serde_yaml::Value::Sequence(seq) => {
+          let index: usize = segment
+            .parse()
+            .map_err(|_| invalid(format!("'{}' is not a list index", segment)))?;
+          let len = seq.len();
+          seq
+            .get_mut(index)
+            .ok_or_else(|| invalid(format!("index {} out of range (length {})", index, len)))?
+        }
+        _ => return Err(invalid(format!("cannot descend into '{}'", segment))),
+      };
+    }
+    match node {
+      serde_yaml::Value::Mapping(map) => {
+        map.insert(serde_yaml::Value::String(last.to_string()), new_value);
+      }
+      serde_yaml::Value::Sequence(seq) => {
+        let index: usize = last
+          .parse()
+          .map_err(|_| invalid(format!("'{}' is not a list index", last)))?;
+        let len = seq.len();
+        *seq
+          .get_mut(index)
+          .ok_or_else(|| invalid(format!("index {} out of range (length {})", index, len)))? = new_value;
+      }
+      _ => return Err(invalid(format!("cannot set '{}' on a scalar", last))),
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::StderrPolicy;
+    use crate::utils;
+    use std::collections::HashSet;
+
+    const STDERR_MANIFEST: &str = r#"
+name: Sample
+description: Test template
+language: test
+variables: []
+postGenerate:
+  - { name: quiet, command: "echo note >&2", checkStderr: false }
+  - { name: strict, command: "echo note >&2", checkStderr: true }
+  - { name: tolerant, command: "echo note >&2", checkStderr: true, ignoreErrors: true }
+buildCheck: { name: build, command: "true", checkStderr: true }
+validation:
+  testVariables: {}
+  steps:
+    - { name: test, command: "true" }
+"#;
+
+    /// Which post-generate steps succeed under `policy`, in manifest order.
+    fn post_generate_outcomes(policy: StderrPolicy) -> Vec<bool> {
+        let mut manifest: ScaffoldManifest = serde_yaml::from_str(STDERR_MANIFEST).unwrap();
+        manifest.force_check_stderr(policy.forced());
+        let dir = tempfile::tempdir().unwrap();
+        let (values, sensitive) = (HashMap::new(), HashSet::new());
+        let variables = utils::StepVariables::new(&values, &sensitive);
+        manifest
+            .post_generate
+            .iter()
+            .map(|step| utils::run_command(step, dir.path(), variables, None).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn respect_keeps_each_steps_check_stderr() {
+        assert_eq!(post_generate_outcomes(StderrPolicy::Respect), [true, false, true]);
+    }
+
+    #[test]
+    fn always_fails_any_step_writing_to_stderr_unless_errors_are_ignored() {
+        assert_eq!(post_generate_outcomes(StderrPolicy::Always), [false, false, true]);
+    }
+
+    #[test]
+    fn never_ignores_stderr() {
+        assert_eq!(post_generate_outcomes(StderrPolicy::Never), [true, true, true]);
+    }
+
+    #[test]
+    fn forced_check_stderr_reaches_every_phase() {
+        for (policy, expected) in [(StderrPolicy::Always, true), (StderrPolicy::Never, false)] {
+            let mut manifest: ScaffoldManifest = serde_yaml::from_str(STDERR_MANIFEST).unwrap();
+            manifest.force_check_stderr(policy.forced());
+            let validation = manifest.validation.as_ref().unwrap();
+            assert_eq!(manifest.build_check.as_ref().unwrap().check_stderr, expected);
+            assert_eq!(validation.steps[0].check_stderr, expected);
+        }
+    }
 }
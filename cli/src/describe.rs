@@ -0,0 +1,199 @@
+// src/describe.rs
+//! `describe`: shows what a template asks for (variables, conditional paths, hooks)
+//! without generating anything. `--output-format json` is meant for editors building forms.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::{DescribeArgs, OutputFormat};
+use crate::config::{
+  Condition, ConditionExpr, NumericBound, ScaffoldManifest, ValidationStep, VariableDefinition,
+  VariableGenerator, VariableType,
+};
+use crate::error::SpawnError;
+use crate::registry::TemplateRegistry;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateDescription<'a> {
+  name: &'a str,
+  language: &'a str,
+  description: &'a str,
+  path: &'a Path,
+  required_tools: &'a [String],
+  variables: Vec<VariableDescription<'a>>,
+  conditional_paths: BTreeMap<&'a str, &'a ConditionExpr>,
+  pre_generate: Vec<HookDescription<'a>>,
+  post_generate: Vec<HookDescription<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VariableDescription<'a> {
+  name: &'a str,
+  /// `None` for variables that are never asked for (e.g. a license with a fixed default)
+  prompt: Option<&'a str>,
+  var_type: &'a VariableType,
+  default: Option<&'a str>,
+  sensitive: bool,
+  validation_regex: Option<&'a str>,
+  choices: &'a [String],
+  min: Option<NumericBound>,
+  max: Option<NumericBound>,
+  when: Option<&'a Condition>,
+  /// Set for variables filled without prompting
+  generator: Option<&'a VariableGenerator>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HookDescription<'a> {
+  name: &'a str,
+  command: &'a str,
+  /// Argument vector for steps that run without a shell
+  #[serde(skip_serializing_if = "<[String]>::is_empty")]
+  args: &'a [String],
+  #[serde(skip_serializing_if = "Option::is_none")]
+  shell: Option<&'a str>,
+}
+
+pub fn run_describe(args: DescribeArgs, registry: &TemplateRegistry) -> Result<(), SpawnError> {
+  let Some((_, template_path, manifest)) = registry.find(&args.language, &args.template) else {
+    return Err(SpawnError::TemplateNotFound(format!(
+      "Template '{}' for language '{}' not found.",
+      args.template, args.language
+    )));
+  };
+
+  // Describe what generating it would use, including anything inherited through `extends`
+  let resolved = registry.resolve(template_path, manifest)?;
+  let description = describe(&resolved.manifest, template_path);
+  match args.output_format {
+    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&description)?),
+    OutputFormat::Text => print_description(&description),
+  }
+  Ok(())
+}
+
+fn describe<'a>(manifest: &'a ScaffoldManifest, path: &'a Path) -> TemplateDescription<'a> {
+  let hooks = |steps: &'a [ValidationStep]| {
+    steps
+      .iter()
+      .map(|s| HookDescription {
+        name: &s.name,
+        command: &s.command,
+        args: &s.args,
+        shell: s.shell.as_deref(),
+      })
+      .collect()
+  };
+  TemplateDescription {
+    name: &manifest.name,
+    language: &manifest.language,
+    description: &manifest.description,
+    path,
+    required_tools: &manifest.required_tools,
+    variables: manifest.variables.iter().map(describe_variable).collect(),
+    conditional_paths: manifest
+      .conditional_paths
+      .iter()
+      .map(|(path, condition)| (path.as_str(), condition))
+      .collect(),
+    pre_generate: hooks(&manifest.pre_generate),
+    post_generate: hooks(&manifest.post_generate),
+  }
+}
+
+fn describe_variable(var_def: &VariableDefinition) -> VariableDescription<'_> {
+  VariableDescription {
+    name: &var_def.name,
+    prompt: var_def.prompt.as_deref(),
+    var_type: &var_def.var_type,
+    default: var_def.default.as_deref(),
+    sensitive: var_def.sensitive,
+    validation_regex: var_def.validation_regex.as_deref(),
+    choices: &var_def.choices,
+    min: var_def.min,
+    max: var_def.max,
+    when: var_def.when.as_ref(),
+    generator: var_def.generator.as_ref(),
+  }
+}
+
+fn print_description(description: &TemplateDescription) {
+  println!("{} ({})", description.name, description.language);
+  println!("  {}", description.description);
+  println!("  Path: {}", description.path.display());
+  if !description.required_tools.is_empty() {
+    println!("  Requires: {}", description.required_tools.join(", "));
+  }
+
+  println!("\nVariables:");
+  if description.variables.is_empty() {
+    println!("  (none)");
+  }
+  for var in &description.variables {
+    // Same spelling as `varType` in scaffold.yaml
+    let var_type = serde_json::to_value(var.var_type)
+      .ok()
+      .and_then(|v| v.as_str().map(str::to_string))
+      .unwrap_or_default();
+    let mut details = vec![var_type];
+    if let Some(default) = var.default {
+      details.push(format!("default: {}", default));
+    }
+    if var.sensitive {
+      details.push("sensitive".to_string());
+    }
+    if let Some(regex) = var.validation_regex {
+      details.push(format!("regex: {}", regex));
+    }
+    if !var.choices.is_empty() {
+      details.push(format!("choices: {}", var.choices.join(", ")));
+    }
+    if let Some(min) = var.min {
+      details.push(format!("min: {}", min));
+    }
+    if let Some(max) = var.max {
+      details.push(format!("max: {}", max));
+    }
+    if let Some(when) = var.when {
+      details.push(format!("when: {}", when));
+    }
+    if let Some(generator) = var.generator {
+      details.push(format!("generated: {}", generator));
+    }
+    println!("  {} [{}]", var.name, details.join("; "));
+    match var.prompt {
+      Some(prompt) if var.generator.is_none() => println!("      \"{}\"", prompt),
+      _ => println!("      (not prompted)"),
+    }
+  }
+
+  if !description.conditional_paths.is_empty() {
+    println!("\nConditional paths:");
+    for (path, condition) in &description.conditional_paths {
+      println!("  {} if {}", path, condition);
+    }
+  }
+  for (title, hooks) in [
+    ("Pre-generate hooks", &description.pre_generate),
+    ("Post-generate hooks", &description.post_generate),
+  ] {
+    if !hooks.is_empty() {
+      println!("\n{}:", title);
+      for hook in hooks {
+        if hook.args.is_empty() {
+          match hook.shell {
+            Some(shell) => println!("  {}: {} (via {})", hook.name, hook.command, shell),
+            None => println!("  {}: {}", hook.name, hook.command),
+          }
+        } else {
+          println!("  {}: {:?}", hook.name, hook.args);
+        }
+      }
+    }
+  }
+}
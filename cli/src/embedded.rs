@@ -0,0 +1,90 @@
+// src/embedded.rs
+//! Templates bundled into the binary at build time (`embedded-templates` feature).
+//! When no on-disk templates directory resolves, the embedded set is extracted to a
+//! cache directory so listing, generation and validation work unchanged.
+//!
+//! Extraction (rather than reading from memory) is required because templates are used as
+//! real directories: hooks and validation steps run commands against the template's files,
+//! `extends` layers template directories on disk, and `which` prints their paths.
+
+use std::path::PathBuf;
+
+use crate::error::SpawnError;
+
+#[cfg(feature = "embedded-templates")]
+static EMBEDDED_TEMPLATES: include_dir::Dir<'static> =
+  include_dir::include_dir!("$CARGO_MANIFEST_DIR/templates");
+
+/// Extracts the embedded templates (if compiled in) and returns the directory holding them.
+/// Extraction is keyed by the binary version and a content hash, so unchanged templates are
+/// only written once; extractions left behind by other binaries are removed.
+#[cfg(feature = "embedded-templates")]
+pub fn extract_embedded_templates() -> Result<Option<PathBuf>, SpawnError> {
+  use std::fs;
+
+  use directories::ProjectDirs;
+  use include_dir::{Dir, DirEntry};
+  use log::{debug, info};
+  use sha2::{Digest, Sha256};
+
+  fn hash_dir(dir: &Dir, hasher: &mut Sha256) {
+    for entry in dir.entries() {
+      match entry {
+        DirEntry::Dir(d) => hash_dir(d, hasher),
+        DirEntry::File(f) => {
+          let path = f.path().to_string_lossy();
+          // Length-prefixed, so a path/content boundary can't shift between files
+          for part in [path.as_bytes(), f.contents()] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+          }
+        }
+      }
+    }
+  }
+
+  if EMBEDDED_TEMPLATES.entries().is_empty() {
+    debug!("No embedded templates compiled into this binary.");
+    return Ok(None);
+  }
+
+  let mut hasher = Sha256::new();
+  hash_dir(&EMBEDDED_TEMPLATES, &mut hasher);
+  let digest: String = hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+  let extraction_key = format!("{}-{}", env!("CARGO_PKG_VERSION"), digest);
+
+  let cache_root = ProjectDirs::from("com", "excsn", "spawnpoint")
+    .map(|p| p.cache_dir().to_path_buf())
+    .unwrap_or_else(|| std::env::temp_dir().join("spawnpoint"));
+  let extractions_dir = cache_root.join("embedded-templates");
+  let target = extractions_dir.join(&extraction_key);
+  let marker = target.join(".spawnpoint-extracted");
+
+  // Best effort: a stale extraction only wastes space
+  if let Ok(entries) = fs::read_dir(&extractions_dir) {
+    for entry in entries.flatten().filter(|entry| entry.file_name() != extraction_key.as_str()) {
+      debug!("Removing stale embedded templates at {}", entry.path().display());
+      let _ = fs::remove_dir_all(entry.path());
+    }
+  }
+
+  if marker.is_file() {
+    debug!("Reusing extracted embedded templates at {}", target.display());
+    return Ok(Some(target));
+  }
+
+  fs::create_dir_all(&target).map_err(|e| SpawnError::OutputDirCreation {
+    path: target.clone(),
+    source: e,
+  })?;
+  EMBEDDED_TEMPLATES.extract(&target)?;
+  fs::write(&marker, env!("CARGO_PKG_VERSION"))?;
+  info!("Extracted embedded templates to {}", target.display());
+  Ok(Some(target))
+}
+
+/// Embedded templates not compiled in.
+#[cfg(not(feature = "embedded-templates"))]
+pub fn extract_embedded_templates() -> Result<Option<PathBuf>, SpawnError> {
+  Ok(None)
+}
@@ -10,11 +10,11 @@ pub enum SpawnError {
   #[error("YAML Parsing Error: {0}")]
   YamlParse(#[from] serde_yaml::Error),
 
-  #[error("Template directory not found at path: {0}")]
-  TemplateDirNotFound(PathBuf),
+  #[error("JSON Error: {0}")]
+  Json(#[from] serde_json::Error),
 
-  #[error("Invalid template path (not a directory): {0}")]
-  InvalidTemplatePath(PathBuf),
+  #[error("{0}")]
+  TemplateNotFound(String),
 
   #[error("Could not read scaffold manifest '{manifest_path}': {source}")]
   ManifestReadError {
@@ -56,14 +56,17 @@ pub enum SpawnError {
     #[source]
     source: Box<dyn std::error::Error + Send + Sync>, // Box to handle different error types
   },
-  #[error("Command for step '{step_name}' failed with status {status}. Stderr: {stderr}")]
+  #[error("Step '{step_name}' timed out after {secs} seconds")]
+  CommandTimeout { step_name: String, secs: u64 },
+
+  #[error("Command for step '{step_name}' failed with status {status}.{}", crate::utils::output_tail(.stdout, .stderr))]
   CommandFailedStatus {
     step_name: String,
     status: ExitStatus, // Store the actual status
     stdout: String,
     stderr: String,
   },
-  #[error("Command for step '{step_name}' produced stderr (check_stderr=true). Stderr: {stderr}")]
+  #[error("Command for step '{step_name}' produced stderr (check_stderr=true).{}", crate::utils::output_tail("", .stderr))]
   CommandStderrNotEmpty {
     step_name: String,
     stdout: String,
@@ -75,17 +78,128 @@ pub enum SpawnError {
 
   #[error("Could not determine templates directory")]
   CannotDetermineTemplatesDir,
+
+  #[error("{count} file(s) failed to generate:\n{details}")]
+  FileGenerationFailed { count: usize, details: String },
+
+  #[error("{count} unsubstituted placeholder(s) left in generated files (--strict-placeholders):\n{details}")]
+  LeftoverPlaceholders { count: usize, details: String },
+
+  #[error("Invalid value for variable '{name}': {reason}")]
+  VariableError { name: String, reason: String },
+
+  #[error("Invalid manifest override '{expr}': {reason}")]
+  ManifestOverride { expr: String, reason: String },
+
+  #[error("Unsupported license '{id}' (bundled: {supported})")]
+  UnsupportedLicense { id: String, supported: String },
+
+  #[error("Prompt '{prompt}' timed out after {secs} seconds")]
+  PromptTimeout { prompt: String, secs: u64 },
+
+  #[error("Build check '{step_name}' failed: {reason}")]
+  BuildCheckFailed { step_name: String, reason: String },
+
+  #[error("Warning promoted to error (--strict-warnings): {0}")]
+  StrictWarning(String),
+
+  #[error("Keychain Error for key '{key}': {reason}")]
+  KeychainError { key: String, reason: String },
+
+  #[error("Invalid answers file '{path}': {reason}")]
+  AnswersFile { path: PathBuf, reason: String },
+
+  #[error("Invalid var file '{path}': {reason}")]
+  VarFile { path: PathBuf, reason: String },
+
+  #[error("Cannot create template '{path}': {reason}")]
+  NewTemplate { path: PathBuf, reason: String },
+
+  #[error("Template '{template}' extends '{base}': {reason}")]
+  TemplateExtends { template: String, base: String, reason: String },
+
+  #[error("Template repository '{url}': {reason}")]
+  TemplateRepo { url: String, reason: String },
+
+  #[error("Templates archive '{path}': {reason}")]
+  TemplatesArchive { path: PathBuf, reason: String },
+
+  #[error("Template manifest '{manifest_path}' requires spawnpoint {required} or newer, but this is {current}. Please upgrade spawnpoint.")]
+  SpawnpointTooOld {
+    manifest_path: PathBuf,
+    required: String,
+    current: String,
+  },
+
+  #[error("Duplicate placeholders in '{manifest_path}': {conflicts}")]
+  DuplicatePlaceholders {
+    manifest_path: PathBuf,
+    conflicts: String,
+  },
+
+  #[error("Invalid minSpawnpointVersion '{value}' in '{manifest_path}': {reason}")]
+  InvalidMinSpawnpointVersion {
+    manifest_path: PathBuf,
+    value: String,
+    reason: String,
+  },
 }
 
-// Helper to convert generic command errors
+// --- Exit Codes ---
+// 2 is left to clap, which exits with it on invalid command-line usage.
+
+/// Any error without a more specific code.
+pub const EXIT_FAILURE: i32 = 1;
+/// No template (or templates directory) matched the selection.
+pub const EXIT_TEMPLATE_NOT_FOUND: i32 = 3;
+/// A check failed: validation steps, `--build-check`, `--strict-placeholders` or manifest lint.
+pub const EXIT_VALIDATION_FAILED: i32 = 4;
+/// A hook or step command failed, timed out or could not be started.
+pub const EXIT_COMMAND_FAILED: i32 = 5;
+/// A template manifest could not be read, parsed or resolved.
+pub const EXIT_INVALID_MANIFEST: i32 = 6;
+/// A variable value, answers/var file or `--set` override was rejected.
+pub const EXIT_INVALID_INPUT: i32 = 7;
+/// Reading or writing files failed.
+pub const EXIT_IO: i32 = 8;
+
 impl SpawnError {
-  fn command_exec_error<E>(step_name: &str, error: E) -> Self
-  where
-    E: std::error::Error + Send + Sync + 'static,
-  {
-    SpawnError::CommandExecError {
-      step_name: step_name.to_string(),
-      source: Box::new(error),
+  /// Process exit code for this error, so scripts can tell failure categories apart.
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      SpawnError::TemplateNotFound(_) | SpawnError::CannotDetermineTemplatesDir => EXIT_TEMPLATE_NOT_FOUND,
+      SpawnError::ValidationError { .. }
+      | SpawnError::BuildCheckFailed { .. }
+      | SpawnError::LeftoverPlaceholders { .. } => EXIT_VALIDATION_FAILED,
+      SpawnError::CommandExecError { .. }
+      | SpawnError::CommandTimeout { .. }
+      | SpawnError::CommandFailedStatus { .. }
+      | SpawnError::CommandStderrNotEmpty { .. } => EXIT_COMMAND_FAILED,
+      SpawnError::ManifestReadError { .. }
+      | SpawnError::ManifestParseError { .. }
+      | SpawnError::TemplateExtends { .. }
+      | SpawnError::SpawnpointTooOld { .. }
+      | SpawnError::DuplicatePlaceholders { .. }
+      | SpawnError::InvalidMinSpawnpointVersion { .. } => EXIT_INVALID_MANIFEST,
+      SpawnError::VariableError { .. }
+      | SpawnError::ManifestOverride { .. }
+      | SpawnError::UnsupportedLicense { .. }
+      | SpawnError::PromptTimeout { .. }
+      | SpawnError::AnswersFile { .. }
+      | SpawnError::VarFile { .. } => EXIT_INVALID_INPUT,
+      SpawnError::Io(_)
+      | SpawnError::OutputDirCreation { .. }
+      | SpawnError::WalkDirError { .. }
+      | SpawnError::FileGenerationFailed { .. } => EXIT_IO,
+      SpawnError::YamlParse(_)
+      | SpawnError::Json(_)
+      | SpawnError::GenerationError(_)
+      | SpawnError::DialoguerError(_)
+      | SpawnError::StrictWarning(_)
+      | SpawnError::KeychainError { .. }
+      | SpawnError::NewTemplate { .. }
+      | SpawnError::TemplateRepo { .. }
+      | SpawnError::TemplatesArchive { .. } => EXIT_FAILURE,
     }
   }
 }
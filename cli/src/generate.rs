@@ -1,65 +1,193 @@
 // src/generate.rs
-use crate::cli::GenerateArgs;
-use crate::config::{ScaffoldManifest, ValidationStep, VariableType};
+use crate::cli::{GenerateArgs, OutputFormat, StderrPolicy, VarAssignment};
+use crate::config::{
+  ScaffoldManifest, StepFailure, UndefinedEnv, ValidationStep, VariableDefinition, VariableType,
+};
 use crate::error::SpawnError;
-use crate::list::read_and_parse_manifest;
+use crate::generator::ValueGenerator;
+use crate::keychain;
+use crate::license;
+use crate::list::read_manifest_with_overrides;
+use crate::prompt::{prompt_with_timeout, theme, DialoguerProvider, VariableProvider};
+use crate::registry::{self, TemplateRegistry};
+use crate::state;
 use crate::utils;
-use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
+use dialoguer::{Confirm, Select};
+use semver::VersionReq;
 use log::{debug, error, info, warn};
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 use std::{env, fs};
+use tempfile::{Builder, TempDir};
 
 #[cfg(feature = "regex")] // Conditionally compile regex logic
 use regex::Regex;
 
-pub fn run_generate(args: GenerateArgs, templates_dir: &Path) -> Result<(), SpawnError> {
+pub fn run_generate(
+  args: GenerateArgs,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+  stderr_policy: StderrPolicy,
+  hook_timeout: Option<u64>,
+) -> Result<(), SpawnError> {
   info!("Running generate command...");
   debug!(
-    "Args: {:?}, Templates Dir: {}",
+    "Args: {:?}, Templates Dirs: {:?}",
     args,
-    templates_dir.display()
+    registry.roots()
   );
 
+  // Zero or unset means prompts wait forever
+  let prompt_timeout = args
+    .prompt_timeout_secs
+    .filter(|secs| *secs > 0)
+    .map(Duration::from_secs);
+
   // --- 1. Select Template ---
-  let (template_name, template_path, manifest) =
-    select_template(args.language, args.template, templates_dir)?;
+  let template_version = args
+    .template_version
+    .as_deref()
+    .map(registry::parse_version_selector)
+    .transpose()?;
+  let (template_name, template_path, mut manifest) =
+    select_template(
+      args.language,
+      args.template,
+      template_version.as_ref(),
+      registry,
+      args.template_index,
+      prompt_timeout,
+    )?;
   info!(
     "Selected template: '{}' from {}",
     template_name,
     template_path.display()
   );
+  // Lays base templates (`extends`) underneath; a composed directory lives as long as `resolved`
+  let resolved = registry.resolve(&template_path, &manifest)?;
+  let template_path = resolved.path.clone();
+  manifest = resolved.manifest.clone();
+  if !args.set.is_empty() {
+    manifest = read_manifest_with_overrides(&template_path.join("scaffold.yaml"), &args.set)?;
+    info!("Applied {} manifest override(s): {:?}", args.set.len(), args.set);
+  }
+  manifest.force_check_stderr(stderr_policy.forced());
+  manifest.apply_hook_timeout(hook_timeout);
   debug!("Manifest loaded: {:?}", manifest);
 
+  let copy_options = utils::CopyOptions {
+    strict_warnings,
+    placeholder_prefix: args.placeholder_prefix.clone(),
+    placeholder_suffix: args.placeholder_suffix.clone(),
+    replace_in_binary: args.replace_in_binary.clone(),
+    continue_on_file_error: args.continue_on_file_error,
+    preserve_bom: args.preserve_bom,
+    overwrite_existing: args.force,
+    strict_placeholders: args.strict_placeholders,
+    only: args.only.clone(),
+    skip: args.skip.clone(),
+    diff: args.diff,
+    dry_run: args.dry_run,
+    progress: args.progress.map(Into::into),
+  };
+  // Fail fast on invalid placeholder overrides before prompting
+  utils::resolve_placeholder_filenames(&manifest.placeholder_filenames, &copy_options)?;
+  check_required_tools(&manifest)?;
+
   // --- 2. Gather Variables ---
-  let base_variables = gather_variables(&manifest)?;
-  debug!("Gathered base variables: {:?}", base_variables);
+  let mut provided_variables = match &args.answers {
+    Some(path) => read_answers_file(path, &manifest)?,
+    None => HashMap::new(),
+  };
+  for path in &args.var_file {
+    provided_variables.extend(read_var_file(path, &manifest)?);
+  }
+  provided_variables.extend(parse_var_assignments(&args.var));
+  // One transform cache for the whole run: defaults, substitutions and `--output-dir`
+  let mut transform_cache = utils::TransformCache::default();
+  let base_variables = gather_variables(
+    &manifest,
+    &provided_variables,
+    strict_warnings,
+    prompt_timeout,
+    args.seed,
+    &mut transform_cache,
+  )?;
 
   // --- 2b. Compute All Variables (Base + Transformed) ---
-  let all_substitutions =
-    utils::compute_transformed_variables(&base_variables, &manifest.variables);
+  let all_substitutions = utils::compute_transformed_variables_cached(
+    &base_variables,
+    &manifest.variables,
+    &manifest.derived,
+    strict_warnings,
+    &mut transform_cache,
+  )?;
+  let sensitive = utils::sensitive_names(&manifest.variables, &manifest.derived);
+  debug!("Gathered base variables: {:?}", utils::redacted_variables(&base_variables, &sensitive));
   debug!(
     "Computed all substitutions (keyed by placeholder): {:?}",
-    all_substitutions
+    utils::redacted_variables(&all_substitutions, &sensitive)
   );
+  if !args.no_confirm && io::stdin().is_terminal() && io::stderr().is_terminal() {
+    confirm_variables(&manifest, &base_variables, prompt_timeout)?;
+  }
+  let output_dir = resolve_output_dir(&args.output_dir, &base_variables, &sensitive, &mut transform_cache)?;
 
   // --- 3. Run Pre-Generate Hooks ---
+  // Without --step-log-dir, step output goes to `.spawnpoint/logs` in the output directory
+  // (nowhere with --stdout-tar, which would otherwise archive it)
+  let step_log = match args.step_log_dir.as_deref() {
+    None if args.stdout_tar => None,
+    step_log_dir => Some(utils::StepLogDir::for_run(step_log_dir, &output_dir)?),
+  };
   let original_cwd = env::current_dir().map_err(SpawnError::Io)?;
+  let step_variables = utils::StepVariables::new(&base_variables, &sensitive);
+  // Hooks can have side effects, so a dry run skips them (as does declining untrusted ones)
+  let run_template_hooks =
+    !args.dry_run && hooks_allowed(&manifest, args.trust_hooks, strict_warnings, prompt_timeout)?;
+  let hooks_unless_skipped = |hooks| if run_template_hooks { hooks } else { &[][..] };
   info!("Checking for pre-generate hooks...");
-  run_hooks(
+  let pre_hooks = run_hooks(
     "Pre-Generate",
-    &manifest.pre_generate,
-    &base_variables, // Pass base vars for {{varName}} substitution in commands
+    hooks_unless_skipped(&manifest.pre_generate),
+    step_variables,  // Base vars for {{varName}} substitution in commands
     &original_cwd,   // Hooks run relative to original CWD by default
+    step_log.as_ref(),
   )?;
   info!("Pre-generate hooks finished.");
 
   // --- 4. Prepare Output Directory ---
-  let output_path = &args.output_dir;
-  if !output_path.exists() {
-    fs::create_dir_all(output_path).map_err(|e| SpawnError::OutputDirCreation {
-      path: output_path.to_path_buf(),
-      source: e,
+  // With --stdout-tar the project is built in a scratch dir and streamed out at the end
+  let scratch_dir: Option<TempDir> = if args.stdout_tar {
+    Some(
+      Builder::new()
+        .prefix(&format!("spawnpoint_tar_{}_", template_name))
+        .tempdir()
+        .map_err(SpawnError::Io)?,
+    )
+  } else {
+    None
+  };
+  let output_path: &Path = scratch_dir
+    .as_ref()
+    .map_or(output_dir.as_path(), |dir| dir.path());
+  // What this run created in the output directory, for --rollback-on-failure
+  let mut created_paths: Vec<PathBuf> = Vec::new();
+  if scratch_dir.is_some() {
+    debug!("Generating into scratch dir for tar output: {}", output_path.display());
+  } else if args.dry_run {
+    info!("Dry run: nothing will be written to '{}'.", output_path.display());
+  } else if !output_path.exists() {
+    utils::create_dir_all_tracked(output_path, &mut created_paths).map_err(|e| {
+      SpawnError::OutputDirCreation {
+        path: output_path.to_path_buf(),
+        source: e,
+      }
     })?;
     info!("Created output directory: {}", output_path.display());
   } else if !output_path.is_dir() {
@@ -68,163 +196,634 @@ pub fn run_generate(args: GenerateArgs, templates_dir: &Path) -> Result<(), Spaw
       output_path.display()
     )));
   } else {
-    // Optional: Check if directory is empty and warn/prompt?
-    // For now, we'll overwrite/add files.
-    warn!(
-      "Output directory '{}' already exists. Files may be overwritten.",
-      output_path.display()
-    );
+    // Existing files are only overwritten with --force (checked before anything is written)
+    if args.force {
+      warn!(
+        "Output directory '{}' already exists. Existing files will be overwritten (--force).",
+        output_path.display()
+      );
+    } else {
+      debug!(
+        "Output directory '{}' already exists; new files will be added next to existing ones.",
+        output_path.display()
+      );
+    }
   }
 
-  // --- 5. Generate Project ---
-  info!("Generating project files...");
-  utils::copy_template_dir(
-    &template_path,
-    output_path,
-    &base_variables,
-    &all_substitutions,
-    &manifest,
-  )?;
+  // --- 5-7. Generate, run post-generate hooks and the build check ---
+  // With --rollback-on-failure, a failure in any of them removes what this run created
+  let generated = (|| {
+    // --- 5. Generate Project ---
+    info!("Generating project files...");
+    let copy_report = utils::copy_template_dir_tracked(
+      &template_path,
+      output_path,
+      &base_variables,
+      &all_substitutions,
+      &manifest,
+      &copy_options,
+      &mut created_paths,
+    )?;
+    if args.diff {
+      print_diffs(&copy_report.diffs, args.output_format == OutputFormat::Json);
+    }
 
-  info!(
-    "Successfully generated project in '{}'!",
-    output_path.display()
-  );
+    if !copy_report.failed_files.is_empty() {
+      let details = copy_report
+        .failed_files
+        .iter()
+        .map(|(path, reason)| format!("  - {}: {}", path.display(), reason))
+        .collect::<Vec<_>>()
+        .join("\n");
+      error!(
+        "Generation finished with {} failed file(s) in '{}'. Skipping post-generate hooks.",
+        copy_report.failed_files.len(),
+        output_path.display()
+      );
+      return Err(SpawnError::FileGenerationFailed {
+        count: copy_report.failed_files.len(),
+        details,
+      });
+    }
 
-  // --- 6. Run Post-Generate Hooks ---
-  info!("Checking for post-generate hooks...");
-  run_hooks(
-    "Post-Generate",
-    &manifest.post_generate,
-    &base_variables, // Pass base vars for {{varName}} substitution in commands
-    output_path,     // Hooks run relative to the generated output path by default
-  )?;
-  info!("Post-generate hooks finished.");
+    if !copy_report.leftover_placeholders.is_empty() {
+      let details = copy_report
+        .leftover_placeholders
+        .iter()
+        .map(|l| format!("  - {}:{}: {}", l.file.display(), l.line, l.token))
+        .collect::<Vec<_>>()
+        .join("\n");
+      error!(
+        "Generated files in '{}' still contain placeholders (usually a typo in the template or manifest). Skipping post-generate hooks.",
+        output_path.display()
+      );
+      return Err(SpawnError::LeftoverPlaceholders {
+        count: copy_report.leftover_placeholders.len(),
+        details,
+      });
+    }
+
+    if args.dry_run {
+      info!(
+        "Dry run: {} file(s) rendered; nothing was written to '{}'.",
+        copy_report.files.len(),
+        output_path.display()
+      );
+    } else {
+      utils::report_skipped_placeholders(
+        output_path,
+        &copy_report,
+        &manifest,
+        &base_variables,
+        &copy_options,
+      )?;
+
+      if args.write_state {
+        track_new(&mut created_paths, output_path.join(state::STATE_FILE_NAME));
+        state::write_state(output_path, &manifest, &base_variables, &copy_report, &copy_options)?;
+      }
+      if args.write_lock {
+        track_new(&mut created_paths, output_path.join(state::LOCK_FILE_NAME));
+        state::write_lock(output_path, &manifest, &base_variables)?;
+      }
+
+      info!(
+        "Successfully generated project in '{}'!",
+        output_path.display()
+      );
+    }
+
+    // --- 6. Run Post-Generate Hooks ---
+    info!("Checking for post-generate hooks...");
+    let post_hooks = run_hooks(
+      "Post-Generate",
+      hooks_unless_skipped(&manifest.post_generate),
+      step_variables,  // Base vars for {{varName}} substitution in commands
+      output_path,     // Hooks run relative to the generated output path by default
+      step_log.as_ref(),
+    )?;
+    info!("Post-generate hooks finished.");
+
+    // --- 7. Optional Build Check ---
+    let build_check_passed = if args.build_check && !args.dry_run {
+      run_build_check(&manifest, step_variables, output_path, step_log.as_ref())?.then_some(true)
+    } else {
+      None
+    };
+    Ok((copy_report, post_hooks, build_check_passed))
+  })();
+  let (copy_report, post_hooks, build_check_passed) = match generated {
+    Ok(generated) => generated,
+    Err(e) => {
+      if args.rollback_on_failure {
+        roll_back(&created_paths);
+      }
+      return Err(e);
+    }
+  };
+
+  // --- 7b. Next Steps From The Template ---
+  if let Some(post_message) = manifest.post_message.as_ref().filter(|_| !args.dry_run) {
+    let message = utils::substitute_command_for_validation(post_message, &base_variables);
+    // Keep stdout clean when it carries the tar archive or the JSON summary
+    if args.stdout_tar || args.output_format == OutputFormat::Json {
+      eprintln!("\n{}", message.trim_end());
+    } else {
+      println!("\n{}", message.trim_end());
+    }
+  }
+
+  // --- 8. Stream as tar (--stdout-tar) ---
+  if args.stdout_tar {
+    write_tar_to_stdout(output_path)?;
+    info!("Wrote project archive to stdout.");
+  }
+
+  // --- 9. Machine-readable summary (--output-format json) ---
+  if args.output_format == OutputFormat::Json {
+    let mut hooks = pre_hooks;
+    hooks.extend(post_hooks);
+    let summary = GenerationSummary {
+      template: manifest.name.clone(),
+      language: manifest.language.clone(),
+      output_dir: output_path.to_path_buf(),
+      variables: summary_variables(&manifest, &base_variables),
+      files: copy_report.files,
+      hooks,
+      build_check_passed,
+    };
+    println!("{}", serde_json::to_string_pretty(&summary)?);
+  }
 
   Ok(())
 }
 
+/// Records `path` in `created` if nothing exists there yet (it is about to be written).
+fn track_new(created: &mut Vec<PathBuf>, path: PathBuf) {
+  if path.symlink_metadata().is_err() {
+    created.push(path);
+  }
+}
+
+/// Removes what a failed run created (`--rollback-on-failure`), newest first. Created
+/// directories held nothing before the run, so they are removed with whatever hooks put in
+/// them; files that existed before are left alone.
+fn roll_back(created: &[PathBuf]) {
+  let mut removed = 0;
+  for path in created.iter().rev() {
+    let result = match path.symlink_metadata() {
+      Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+      Ok(_) => fs::remove_file(path),
+      Err(_) => continue, // Already gone with a removed directory
+    };
+    match result {
+      Ok(()) => removed += 1,
+      Err(e) => warn!("Rollback could not remove '{}': {}", path.display(), e),
+    }
+  }
+  warn!("Rolled back: removed {} path(s) created by this run (--rollback-on-failure).", removed);
+}
+
+/// Prints `--diff` results: a unified diff per changed text file, one line per other change.
+/// Goes to stderr when stdout carries the JSON summary.
+fn print_diffs(diffs: &[utils::FileDiff], to_stderr: bool) {
+  let print = |text: &str| {
+    if to_stderr {
+      eprint!("{}", text);
+    } else {
+      print!("{}", text);
+    }
+  };
+  for diff in diffs {
+    match &diff.change {
+      utils::FileChange::Unchanged => debug!("unchanged: {}", diff.file.display()),
+      utils::FileChange::Added => print(&format!("new file: {}\n", diff.file.display())),
+      utils::FileChange::Modified(unified) => print(unified),
+      utils::FileChange::BinaryModified { existing, generated } => print(&format!(
+        "binary file changed: {} (sha256 {} -> {})\n",
+        diff.file.display(),
+        existing,
+        generated
+      )),
+    }
+  }
+  let changed = diffs
+    .iter()
+    .filter(|d| d.change != utils::FileChange::Unchanged)
+    .count();
+  info!("{} of {} file(s) differ from the output directory.", changed, diffs.len());
+}
+
+/// Result of `generate`, printed as JSON with `--output-format json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationSummary {
+  template: String,
+  language: String,
+  output_dir: PathBuf,
+  /// Values by variable name; sensitive values are replaced by `utils::REDACTED`.
+  variables: BTreeMap<String, String>,
+  files: Vec<utils::GeneratedFile>,
+  hooks: Vec<HookResult>,
+  /// `None` unless `--build-check` ran a `buildCheck` step (a failed check is an error).
+  build_check_passed: Option<bool>,
+}
+
+/// Outcome of a single hook step.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HookResult {
+  phase: String,
+  name: String,
+  outcome: HookOutcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum HookOutcome {
+  Succeeded,
+  /// Failed, but the step has `ignoreErrors: true`
+  FailureIgnored,
+}
+
+fn summary_variables(
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+) -> BTreeMap<String, String> {
+  manifest
+    .variables
+    .iter()
+    .filter_map(|vd| {
+      let value = base_variables.get(&vd.name)?;
+      let shown = if vd.sensitive { utils::REDACTED } else { value.as_str() };
+      Some((vd.name.clone(), shown.to_string()))
+    })
+    .collect()
+}
+
 // --- Helper Functions ---
 
-// Helper to execute a list of hook steps.
-fn run_hooks(
+/// Shows the gathered values (sensitive ones masked) and asks whether to go on.
+/// Runs before pre-generate hooks, so declining leaves nothing behind.
+fn confirm_variables(
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+  prompt_timeout: Option<Duration>,
+) -> Result<(), SpawnError> {
+  let rows: Vec<(&str, &str)> = manifest
+    .variables
+    .iter()
+    .filter_map(|vd| {
+      let value = base_variables.get(&vd.name)?;
+      Some((vd.name.as_str(), if vd.sensitive { utils::REDACTED } else { value.as_str() }))
+    })
+    .collect();
+  if rows.is_empty() {
+    return Ok(());
+  }
+  let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+  eprintln!("\nVariables for '{}':", manifest.name);
+  for (name, value) in rows {
+    eprintln!("  {:<width$} = {}", name, value, width = width);
+  }
+  let label = "Proceed?";
+  let proceed = prompt_with_timeout(prompt_timeout, label, move || {
+    Confirm::with_theme(theme())
+      .with_prompt(label)
+      .default(true)
+      .interact()
+  })?;
+  if !proceed {
+    return Err(SpawnError::GenerationError(
+      "Aborted: variables not confirmed; nothing was generated.".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// Whether the template's hooks may run. With `trusted: false` in the root config (and no
+/// `--trust-hooks`), they need a yes in a terminal and are skipped otherwise.
+fn hooks_allowed(
+  manifest: &ScaffoldManifest,
+  trust_hooks: Option<bool>,
+  strict_warnings: bool,
+  prompt_timeout: Option<Duration>,
+) -> Result<bool, SpawnError> {
+  let hook_count = manifest.pre_generate.len() + manifest.post_generate.len();
+  if trust_hooks != Some(false) || hook_count == 0 {
+    return Ok(true);
+  }
+  if io::stdin().is_terminal() && io::stderr().is_terminal() {
+    let label = format!(
+      "Template '{}' has {} hook(s) from an untrusted templates directory. Run them?",
+      manifest.name, hook_count
+    );
+    let prompt_label = label.clone();
+    let confirmed = prompt_with_timeout(prompt_timeout, &label, move || {
+      Confirm::with_theme(theme())
+        .with_prompt(prompt_label)
+        .default(false)
+        .interact()
+    })?;
+    if confirmed {
+      return Ok(true);
+    }
+  }
+  utils::strict_warn(
+    strict_warnings,
+    format!(
+      "Skipping the {} hook(s) of template '{}': its templates directory is not trusted (pass --trust-hooks to run them).",
+      hook_count, manifest.name
+    ),
+  )?;
+  Ok(false)
+}
+
+/// Fails before any prompt or file write if a `requiredTools` entry is not on `PATH`.
+fn check_required_tools(manifest: &ScaffoldManifest) -> Result<(), SpawnError> {
+  let missing: Vec<&str> = manifest
+    .required_tools
+    .iter()
+    .map(String::as_str)
+    .filter(|tool| {
+      let found = utils::find_executable(tool);
+      debug!("Required tool '{}': {:?}", tool, found);
+      found.is_none()
+    })
+    .collect();
+  if missing.is_empty() {
+    return Ok(());
+  }
+  Err(SpawnError::GenerationError(format!(
+    "Template '{}' requires tools that were not found on PATH: {}. Install them and try again.",
+    manifest.name,
+    missing.join(", ")
+  )))
+}
+
+/// Writes the contents of `project_dir` to stdout as an uncompressed tar archive.
+/// Paths in the archive are relative to `project_dir`; everything else logs to stderr.
+fn write_tar_to_stdout(project_dir: &Path) -> Result<(), SpawnError> {
+  let stdout = io::stdout();
+  let mut archive = tar::Builder::new(stdout.lock());
+  archive.follow_symlinks(false);
+  archive.append_dir_all(".", project_dir)?;
+  let mut out = archive.into_inner()?;
+  out.flush()?;
+  Ok(())
+}
+
+// Helper to execute a list of hook steps, in order except for consecutive steps sharing a `group`.
+pub(crate) fn run_hooks(
   phase_name: &str, // "Pre-Generate" or "Post-Generate"
   hooks: &[ValidationStep],
-  variables: &HashMap<String, String>, // Base variables for {{varName}} command substitution
+  variables: utils::StepVariables<'_>, // Base variables for {{varName}} command substitution
   default_base_dir: &Path,             // Default directory to run hook in
-) -> Result<(), SpawnError> {
+  step_log: Option<&utils::StepLogDir>, // Per-step output files (--step-log-dir)
+) -> Result<Vec<HookResult>, SpawnError> {
+  let mut results = Vec::with_capacity(hooks.len());
   if hooks.is_empty() {
-    return Ok(());
+    return Ok(results);
   }
 
   info!("--- Running {} phase ---", phase_name);
-  for (i, step) in hooks.iter().enumerate() {
-    let step_num = i + 1;
-    let total_steps = hooks.len();
-
-    // Determine working directory: use step's if specified (relative to default), else use default
-    let run_path = step
-      .working_dir
-      .as_ref()
-      .map_or(default_base_dir.to_path_buf(), |wd| {
-        default_base_dir.join(wd).to_path_buf()
+  let total_steps = hooks.len();
+  let mut start = 0;
+  while start < total_steps {
+    // A batch is one ungrouped step, or consecutive steps sharing a group
+    let end = match &hooks[start].group {
+      Some(group) => {
+        start
+          + hooks[start..]
+            .iter()
+            .take_while(|step| step.group.as_ref() == Some(group))
+            .count()
+      }
+      None => start + 1,
+    };
+    let batch = &hooks[start..end];
+    let run_step = |step: &ValidationStep, step_num: usize| {
+      run_hook_step(phase_name, step, step_num, total_steps, variables, default_base_dir, step_log)
+    };
+    let outcomes: Vec<Result<HookOutcome, SpawnError>> = if batch.len() == 1 {
+      vec![run_step(&batch[0], start + 1)]
+    } else {
+      info!(
+        "Running {} steps of group '{}' in parallel...",
+        batch.len(),
+        batch[0].group.as_deref().unwrap_or_default()
+      );
+      thread::scope(|scope| {
+        let handles: Vec<_> = batch
+          .iter()
+          .enumerate()
+          .map(|(i, step)| {
+            let run_step = &run_step;
+            scope.spawn(move || run_step(step, start + i + 1))
+          })
+          .collect();
+        // A panicking step fails that hook instead of aborting the run
+        handles
+          .into_iter()
+          .zip(batch)
+          .map(|(handle, step)| {
+            handle.join().unwrap_or_else(|panic| {
+              Err(SpawnError::CommandExecError {
+                step_name: format!("{} Hook: {}", phase_name, step.name),
+                source: format!("step thread panicked: {}", utils::panic_message(&*panic)).into(),
+              })
+            })
+          })
+          .collect()
+      })
+    };
+    // Every step of a group runs to completion; the first failure in manifest order is returned
+    for (step, outcome) in batch.iter().zip(outcomes) {
+      results.push(HookResult {
+        phase: phase_name.to_string(),
+        name: step.name.clone(),
+        outcome: outcome?,
       });
-    // Need to handle potential non-existence of default_base_dir.join(wd) if needed,
-    // but run_command should handle CWD errors. Using owned path now.
+    }
+    start = end;
+  }
+  info!("--- Finished {} phase ---", phase_name);
+  Ok(results)
+}
 
-    info!(
-      "[{}/{}] Running step: '{}'...",
-      step_num, total_steps, step.name
-    );
+/// Runs one hook step and logs its outcome. Returns Err only for failures the step doesn't ignore.
+fn run_hook_step(
+  phase_name: &str,
+  step: &ValidationStep,
+  step_num: usize,
+  total_steps: usize,
+  variables: utils::StepVariables<'_>,
+  default_base_dir: &Path,
+  step_log: Option<&utils::StepLogDir>,
+) -> Result<HookOutcome, SpawnError> {
+  let mut outcome = HookOutcome::Succeeded;
 
-    // Execute the command using the *base* variables map for substitution
-    match utils::run_command(step, &run_path, variables) {
-      Ok(output) => {
-        // Check status AFTER command runs
-        if !output.status.success() {
-          let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
-          let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
-          error!(
-            "{} hook step '{}' failed (status: {:?}).\nStderr:\n{}\nStdout:\n{}",
-            phase_name, step.name, output.status, stderr_string, stdout_string
-          );
-          if !step.ignore_errors {
-            // Return specific error for hook failure
-            return Err(SpawnError::CommandFailedStatus {
-              step_name: format!("{} Hook: {}", phase_name, step.name), // Add phase context
-              status: output.status,
-              stdout: stdout_string,
-              stderr: stderr_string,
-            });
-          } else {
-            warn!(
-              "Ignoring failed status in {} hook step '{}' (ignore_errors=true).",
-              phase_name, step.name
-            );
-          }
-        } else if step.check_stderr && !output.stderr.is_empty() {
-          let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
-          let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
-          error!(
-            "{} hook step '{}' check_stderr failed.\nStderr:\n{}\nStdout:\n{}",
-            phase_name, step.name, stderr_string, stdout_string
-          );
-          if !step.ignore_errors {
-            return Err(SpawnError::CommandStderrNotEmpty {
-              step_name: format!("{} Hook: {}", phase_name, step.name),
-              stdout: stdout_string,
-              stderr: stderr_string,
-            });
-          } else {
-            warn!(
-              "Ignoring non-empty stderr in {} hook step '{}' (ignore_errors=true).",
-              phase_name, step.name
-            );
-          }
+  // Determine working directory: use step's if specified (relative to default), else use default
+  let run_path = step
+    .working_dir
+    .as_ref()
+    .map_or(default_base_dir.to_path_buf(), |wd| {
+      default_base_dir.join(wd).to_path_buf()
+    });
+  // Need to handle potential non-existence of default_base_dir.join(wd) if needed,
+  // but run_command should handle CWD errors. Using owned path now.
+
+  info!(
+    "[{}/{}] Running step: '{}'...",
+    step_num, total_steps, step.name
+  );
+
+  // Execute the command using the *base* variables map for substitution
+  match utils::run_command(step, &run_path, variables, step_log) {
+    Ok(output) => {
+      // Check status AFTER command runs
+      if !output.status.success() {
+        let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
+        error!(
+          "{} hook step '{}' failed (status: {:?}).\nStderr:\n{}\nStdout:\n{}",
+          phase_name, step.name, output.status, stderr_string, stdout_string
+        );
+        if !step.ignores(StepFailure::Status) {
+          // Return specific error for hook failure
+          return Err(SpawnError::CommandFailedStatus {
+            step_name: format!("{} Hook: {}", phase_name, step.name), // Add phase context
+            status: output.status,
+            stdout: stdout_string,
+            stderr: stderr_string,
+          });
         } else {
-          info!(
-            "[{}/{}] Step '{}' successful.",
-            step_num, total_steps, step.name
+          warn!(
+            "Ignoring failed status in {} hook step '{}' (ignored failure: status).",
+            phase_name, step.name
           );
+          outcome = HookOutcome::FailureIgnored;
         }
-      }
-      Err(e) => {
-        // Execution errors (spawn, timeout, wait)
+      } else if step.check_stderr && !output.stderr.is_empty() {
+        let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
         error!(
-          "{} hook step '{}' execution error: {}",
-          phase_name, step.name, e
+          "{} hook step '{}' check_stderr failed.\nStderr:\n{}\nStdout:\n{}",
+          phase_name, step.name, stderr_string, stdout_string
         );
-        if !step.ignore_errors {
-          // Wrap the original error if possible, or create a new one
-          // Reusing CommandExecError might require adjusting its structure or creating a new HookExecError variant
-          return Err(SpawnError::CommandExecError {
+        if !step.ignores(StepFailure::Stderr) {
+          return Err(SpawnError::CommandStderrNotEmpty {
             step_name: format!("{} Hook: {}", phase_name, step.name),
-            source: format!("Execution failed: {}", e).into(), // Simple wrapping for now
+            stdout: stdout_string,
+            stderr: stderr_string,
           });
         } else {
           warn!(
-            "Ignoring execution error in {} hook step '{}' (ignore_errors=true).",
+            "Ignoring non-empty stderr in {} hook step '{}' (ignored failure: stderr).",
             phase_name, step.name
           );
+          outcome = HookOutcome::FailureIgnored;
         }
+      } else {
+        info!(
+          "[{}/{}] Step '{}' successful.",
+          step_num, total_steps, step.name
+        );
+      }
+    }
+    Err(e) => {
+      // Execution errors (spawn, timeout, wait)
+      error!(
+        "{} hook step '{}' execution error: {}",
+        phase_name, step.name, e
+      );
+      if !step.ignores_error(&e) {
+        // Wrap the original error if possible, or create a new one
+        // Reusing CommandExecError might require adjusting its structure or creating a new HookExecError variant
+        return Err(SpawnError::CommandExecError {
+          step_name: format!("{} Hook: {}", phase_name, step.name),
+          source: format!("Execution failed: {}", e).into(), // Simple wrapping for now
+        });
+      } else {
+        warn!(
+          "Ignoring execution error in {} hook step '{}'.",
+          phase_name, step.name
+        );
+        outcome = HookOutcome::FailureIgnored;
       }
     }
   }
-  info!("--- Finished {} phase ---", phase_name);
-  Ok(())
+  Ok(outcome)
 }
 
-fn select_template(
+/// Runs the manifest's `buildCheck` step in the generated project, reporting it separately from hooks.
+fn run_build_check(
+  manifest: &ScaffoldManifest,
+  variables: utils::StepVariables<'_>,
+  output_path: &Path,
+  step_log: Option<&utils::StepLogDir>,
+) -> Result<bool, SpawnError> {
+  let Some(step) = &manifest.build_check else {
+    warn!(
+      "--build-check requested but template '{}' defines no buildCheck. Skipping.",
+      manifest.name
+    );
+    return Ok(false);
+  };
+
+  let run_path = step
+    .working_dir
+    .as_ref()
+    .map_or(output_path.to_path_buf(), |wd| output_path.join(wd));
+  info!("--- Running build check: '{}' ---", step.name);
+
+  let failure_reason = match utils::run_command(step, &run_path, variables, step_log) {
+    Ok(output) if output.status.success() => None,
+    Ok(output) => Some(format!(
+      "exited with status {:?}. Stderr: {}",
+      output.status.code(),
+      String::from_utf8_lossy(&output.stderr)
+    )),
+    Err(e) => Some(e.to_string()),
+  };
+
+  match failure_reason {
+    None => {
+      info!("✅ Build check '{}' passed.", step.name);
+      Ok(true)
+    }
+    Some(reason) => {
+      error!("❌ Build check '{}' failed: {}", step.name, reason);
+      Err(SpawnError::BuildCheckFailed {
+        step_name: step.name.clone(),
+        reason,
+      })
+    }
+  }
+}
+
+/// Picks the template to use from the CLI arguments, prompting for whatever is missing.
+/// With a `version` selector only matching versions are considered; otherwise a template
+/// name means its highest version.
+pub(crate) fn select_template(
   lang_opt: Option<String>,
   template_opt: Option<String>,
-  templates_dir: &Path,
+  version: Option<&VersionReq>,
+  registry: &TemplateRegistry,
+  template_index: Option<usize>,
+  prompt_timeout: Option<Duration>,
 ) -> Result<(String, PathBuf, ScaffoldManifest), SpawnError> {
-  let available_templates = find_available_templates(templates_dir)?;
+  let available_templates: Vec<&(String, PathBuf, ScaffoldManifest)> = registry
+    .templates()
+    .iter()
+    .filter(|(_, _, manifest)| registry::version_matches(manifest, version))
+    .collect();
 
   if available_templates.is_empty() {
-    return Err(SpawnError::GenerationError(
-      "No templates found.".to_string(),
-    ));
+    return Err(SpawnError::TemplateNotFound(match version {
+      Some(version) => format!("No templates found with version {}.", version),
+      None => "No templates found.".to_string(),
+    }));
   }
 
   match (lang_opt, template_opt) {
@@ -244,53 +843,57 @@ fn select_template(
           .collect::<Vec<_>>()
       ); // Log manifest name
 
-      available_templates
-        .into_iter()
-        .find(|(_dir_name, _path, manifest)| {
-          // Ignore dir_name from tuple here
-          let lang_match = manifest.language == lang;
-          // Compare against the manifest's name field!
-          let name_match = manifest.name == template_name;
-          log::trace!(
-            "Checking manifest '{}': lang_match={}, name_match={}",
-            manifest.name,
-            lang_match,
-            name_match
-          );
-          lang_match && name_match
-        })
+      // Compares against the manifest's name field, not the directory name
+      registry
+        .find_version(&lang, &template_name, version)
+        .cloned()
         .ok_or_else(|| {
-          SpawnError::GenerationError(format!(
-            "Template '{}' for language '{}' not found.",
-            template_name, lang
+          let version_note = version.map(|v| format!(" with version {}", v)).unwrap_or_default();
+          SpawnError::TemplateNotFound(format!(
+            "Template '{}' for language '{}'{} not found.",
+            template_name, lang, version_note
           ))
         })
     }
     // Only language provided: Select template from language
     (Some(lang), None) => {
       let lang_templates: Vec<_> = available_templates
-        .into_iter()
+        .iter()
         .filter(|(_, _, manifest)| manifest.language == lang)
+        .map(|&template| template.clone())
         .collect();
 
       if lang_templates.is_empty() {
-        return Err(SpawnError::GenerationError(format!(
+        return Err(SpawnError::TemplateNotFound(format!(
           "No templates found for language '{}'.",
           lang
         )));
       }
+      if let Some(index) = template_index {
+        // Same (path-sorted) order as the interactive list
+        let count = lang_templates.len();
+        return lang_templates.into_iter().nth(index).ok_or_else(|| {
+          SpawnError::GenerationError(format!(
+            "Template index {} is out of range for language '{}' ({} template(s), valid indices 0-{}).",
+            index,
+            lang,
+            count,
+            count - 1
+          ))
+        });
+      }
       if lang_templates.len() == 1 {
         Ok(lang_templates.into_iter().next().unwrap())
       } else {
-        let names: Vec<&str> = lang_templates
+        let names: Vec<String> = lang_templates
           .iter()
-          .map(|(_, _, manifest)| manifest.name.as_str())
+          .map(|(_, _, manifest)| registry::display_name(manifest))
           .collect();
-        let selection = Select::with_theme(&ColorfulTheme::default())
-          .with_prompt(format!("Select a template for language '{}'", lang))
-          .items(&names)
-          .default(0)
-          .interact()?;
+        let selection = select_with_timeout(
+          prompt_timeout,
+          format!("Select a template for language '{}'", lang),
+          names,
+        )?;
         Ok(lang_templates.into_iter().nth(selection).unwrap()) // Should always succeed
       }
     }
@@ -300,15 +903,19 @@ fn select_template(
         "Attempting to find template by name only: template_name='{}'",
         template_name
       );
-      let matches: Vec<_> = available_templates
-        .into_iter()
+      let mut matches: Vec<_> = available_templates
+        .iter()
         // Compare against manifest.name here too
         .filter(|(_dir_name, _path, manifest)| manifest.name == template_name)
+        .map(|&template| template.clone())
         .collect();
+      // Versions come highest first; keep one per language
+      let mut seen_languages = HashSet::new();
+      matches.retain(|(_, _, manifest)| seen_languages.insert(manifest.language.clone()));
       if matches.len() == 1 {
         Ok(matches.into_iter().next().unwrap())
       } else if matches.is_empty() {
-        Err(SpawnError::GenerationError(format!(
+        Err(SpawnError::TemplateNotFound(format!(
           "Template '{}' not found.",
           template_name
         )))
@@ -328,158 +935,988 @@ fn select_template(
       languages.dedup();
 
       if languages.is_empty() {
-        return Err(SpawnError::GenerationError(
+        return Err(SpawnError::TemplateNotFound(
           "No templates found.".to_string(),
         ));
       }
 
-      let lang_selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select the language/framework")
-        .items(&languages)
-        .default(0)
-        .interact()?;
+      let lang_selection = select_with_timeout(
+        prompt_timeout,
+        "Select the language/framework".to_string(),
+        languages.clone(),
+      )?;
       let selected_lang = &languages[lang_selection];
 
       // Now select template within that language
       let lang_templates: Vec<_> = available_templates
-        .into_iter()
+        .iter()
         .filter(|(_, _, manifest)| &manifest.language == selected_lang)
+        .map(|&template| template.clone())
         .collect();
 
       if lang_templates.len() == 1 {
         Ok(lang_templates.into_iter().next().unwrap())
       } else {
-        let names: Vec<&str> = lang_templates
+        let names: Vec<String> = lang_templates
           .iter()
-          .map(|(name, _, _)| name.as_str())
+          .map(|(name, _, _)| name.clone())
           .collect();
-        let selection = Select::with_theme(&ColorfulTheme::default())
-          .with_prompt(format!(
-            "Select a template for language '{}'",
-            selected_lang
-          ))
-          .items(&names)
-          .default(0)
-          .interact()?;
+        let selection = select_with_timeout(
+          prompt_timeout,
+          format!("Select a template for language '{}'", selected_lang),
+          names,
+        )?;
         Ok(lang_templates.into_iter().nth(selection).unwrap())
       }
     }
   }
 }
 
-pub(crate) fn find_available_templates(
+/// Every template under `templates_dir` as (directory name, path, manifest), sorted by
+/// directory name with the versions of one template grouped highest first.
+/// See [`TemplateRegistry::scan`].
+pub fn find_available_templates(
   templates_dir: &Path,
+  strict_warnings: bool,
 ) -> Result<Vec<(String, PathBuf, ScaffoldManifest)>, SpawnError> {
-  let mut templates = Vec::new();
-  if !templates_dir.is_dir() {
-    warn!(
-      "Templates directory not found or is not a directory: {}",
-      templates_dir.display()
-    );
-    return Ok(templates); // Return empty vec if dir doesn't exist
-  }
+  Ok(TemplateRegistry::scan(templates_dir, strict_warnings)?.into_templates())
+}
 
-  for entry_result in fs::read_dir(templates_dir)? {
-    let entry = match entry_result {
-      Ok(e) => e,
-      Err(e) => {
-        warn!("Failed to read entry in templates directory: {}", e);
-        continue;
-      }
+/// Result of [`generate_project`] and [`generate_project_with`].
+#[derive(Debug, Clone)]
+pub struct GenerationReport {
+  /// Resolved value of every variable that was used, keyed by variable name.
+  pub variables: HashMap<String, String>,
+  /// Files written, in template order.
+  pub files: Vec<utils::GeneratedFile>,
+}
+
+/// Library entry point: generates the template at `template_path` into `output_path`
+/// (created if needed) without prompting. `vars` supplies values by variable name; the rest
+/// fall back to their manifest default, and a variable with neither is an error. Values
+/// are validated like `--var`. Hooks are not run. A manifest that `extends` another template
+/// is resolved against the templates next to `template_path`; resolve it with
+/// [`TemplateRegistry::resolve`] first to use another registry.
+pub fn generate_project(
+  manifest: &ScaffoldManifest,
+  template_path: &Path,
+  output_path: &Path,
+  vars: &HashMap<String, String>,
+) -> Result<GenerationReport, SpawnError> {
+  generate_with_provider(manifest, template_path, output_path, vars, None)
+}
+
+/// Like [`generate_project`], but variables missing from `vars` are asked of `provider`
+/// instead of falling back to their default.
+pub fn generate_project_with(
+  manifest: &ScaffoldManifest,
+  template_path: &Path,
+  output_path: &Path,
+  vars: &HashMap<String, String>,
+  provider: &mut dyn VariableProvider,
+) -> Result<GenerationReport, SpawnError> {
+  generate_with_provider(manifest, template_path, output_path, vars, Some(provider))
+}
+
+fn generate_with_provider(
+  manifest: &ScaffoldManifest,
+  template_path: &Path,
+  output_path: &Path,
+  vars: &HashMap<String, String>,
+  provider: Option<&mut dyn VariableProvider>,
+) -> Result<GenerationReport, SpawnError> {
+  // Kept alive until the copy is done: a layered template lives in a temp dir
+  let resolved = match manifest.extends {
+    Some(_) => Some(TemplateRegistry::scan(&template_path.join(".."), false)?.resolve(template_path, manifest)?),
+    None => None,
+  };
+  let (manifest, template_path) = match &resolved {
+    Some(resolved) => (&resolved.manifest, resolved.path.as_path()),
+    None => (manifest, template_path),
+  };
+  let mut transform_cache = utils::TransformCache::default();
+  let base_variables = collect_variables(manifest, vars, false, None, &mut transform_cache, provider)?;
+  let all_substitutions = utils::compute_transformed_variables_cached(
+    &base_variables,
+    &manifest.variables,
+    &manifest.derived,
+    false,
+    &mut transform_cache,
+  )?;
+  fs::create_dir_all(output_path).map_err(|e| SpawnError::OutputDirCreation {
+    path: output_path.to_path_buf(),
+    source: e,
+  })?;
+  let copy_report = utils::copy_template_dir(
+    template_path,
+    output_path,
+    &base_variables,
+    &all_substitutions,
+    manifest,
+    &utils::CopyOptions::default(),
+  )?;
+  Ok(GenerationReport {
+    variables: base_variables,
+    files: copy_report.files,
+  })
+}
+
+/// Collects repeated `--var name=value` flags. Later flags win over earlier ones.
+pub(crate) fn parse_var_assignments(vars: &[VarAssignment]) -> HashMap<String, String> {
+  vars
+    .iter()
+    .map(|assignment| (assignment.name.clone(), assignment.value.clone()))
+    .collect()
+}
+
+/// Reads a `--var-file`: `name=value` lines as for `--var`, with `${ENV}` in values replaced
+/// from the process environment (`$${ENV}` for a literal) so secrets stay off the command
+/// line. Whitespace around names and values is trimmed, blank lines and `#` comments are
+/// skipped, and names that aren't variables of
+/// `manifest` are warned about and dropped. Only names are logged, never values.
+pub(crate) fn read_var_file(
+  path: &Path,
+  manifest: &ScaffoldManifest,
+) -> Result<HashMap<String, String>, SpawnError> {
+  let var_file_error = |reason: String| SpawnError::VarFile {
+    path: path.to_path_buf(),
+    reason,
+  };
+  let content = fs::read_to_string(path).map_err(|e| var_file_error(e.to_string()))?;
+  let mut values = HashMap::new();
+  for (index, line) in content.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let Some((name, raw)) = line.split_once('=').filter(|(name, _)| !name.trim().is_empty()) else {
+      return Err(var_file_error(format!("line {}: expected name=value", index + 1)));
     };
+    let name = name.trim();
+    if !manifest.variables.iter().any(|vd| vd.name == name) {
+      warn!(
+        "Var file '{}': '{}' does not match any variable of template '{}'; ignoring it.",
+        path.display(),
+        name,
+        manifest.name
+      );
+      continue;
+    }
+    let value = utils::expand_env_vars(raw.trim(), UndefinedEnv::Error, |name| env::var(name).ok())
+      .map_err(|reason| var_file_error(format!("line {}: {}", index + 1, reason)))?;
+    values.insert(name.to_string(), value);
+  }
+  debug!(
+    "Var file '{}' sets: {}",
+    path.display(),
+    values.keys().cloned().collect::<Vec<_>>().join(", ")
+  );
+  Ok(values)
+}
 
-    let path = entry.path();
-    if path.is_dir() {
-      let manifest_path = path.join("scaffold.yaml");
-      let template_name = path
-        .file_name()
-        .map_or_else(|| ".".into(), |n| n.to_string_lossy().to_string());
-
-      if manifest_path.is_file() {
-        match read_and_parse_manifest(&manifest_path) {
-          Ok(manifest) => {
-            templates.push((template_name, path.clone(), manifest));
-          }
-          Err(e) => {
-            warn!(
-              "Skipping directory '{}': Could not read or parse scaffold.yaml: {}",
-              template_name, e
-            );
-          }
-        }
-      } else {
-        debug!(
-          "Directory {} does not contain scaffold.yaml.",
-          path.display()
+/// Reads an `--answers` file: a YAML (or JSON) map of variable name to value, or a
+/// `.spawnpoint.lock` (its `variables` are used).
+/// Scalars are used as written; a list is joined with the variable's separator (`multiSelect`).
+/// Keys that aren't variables of `manifest` are warned about and dropped.
+pub(crate) fn read_answers_file(
+  path: &Path,
+  manifest: &ScaffoldManifest,
+) -> Result<HashMap<String, String>, SpawnError> {
+  let answers_error = |reason: String| SpawnError::AnswersFile {
+    path: path.to_path_buf(),
+    reason,
+  };
+  let content = fs::read_to_string(path).map_err(|e| answers_error(e.to_string()))?;
+  let doc: serde_yaml::Value =
+    serde_yaml::from_str(&content).map_err(|e| answers_error(e.to_string()))?;
+  let map = match doc {
+    serde_yaml::Value::Mapping(map) if map.contains_key("lockVersion") => {
+      let lock: state::LockFile = serde_yaml::from_value(serde_yaml::Value::Mapping(map))
+        .map_err(|e| answers_error(e.to_string()))?;
+      if lock.template_name != manifest.name {
+        warn!(
+          "Lockfile '{}' was written for template '{}', not '{}'.",
+          path.display(),
+          lock.template_name,
+          manifest.name
+        );
+      } else if lock.template_version.is_some() && lock.template_version != manifest.version {
+        warn!(
+          "Lockfile '{}' was written for version {} of template '{}'; using {}.",
+          path.display(),
+          lock.template_version.as_ref().map_or(String::new(), |v| v.to_string()),
+          manifest.name,
+          manifest.version.as_ref().map_or("an unversioned copy".to_string(), |v| v.to_string())
+        );
+      }
+      if !lock.redacted_variables.is_empty() {
+        info!(
+          "Lockfile '{}' does not record sensitive variable(s): {}.",
+          path.display(),
+          lock.redacted_variables.join(", ")
         );
       }
+      lock
+        .variables
+        .into_iter()
+        .map(|(name, value)| (serde_yaml::Value::String(name), serde_yaml::Value::String(value)))
+        .collect()
     }
+    serde_yaml::Value::Mapping(map) => map,
+    serde_yaml::Value::Null => serde_yaml::Mapping::new(), // Empty file
+    _ => return Err(answers_error("expected a map of variable names to values".to_string())),
+  };
+
+  let mut answers = HashMap::new();
+  for (key, value) in map {
+    let Some(name) = key.as_str() else {
+      return Err(answers_error(format!("variable names must be strings, found {:?}", key)));
+    };
+    let Some(var_def) = manifest.variables.iter().find(|vd| vd.name == name) else {
+      warn!(
+        "Answers file '{}': '{}' does not match any variable of template '{}'; ignoring it.",
+        path.display(),
+        name,
+        manifest.name
+      );
+      continue;
+    };
+    let scalar = |value: &serde_yaml::Value| match value {
+      serde_yaml::Value::String(s) => Some(s.clone()),
+      serde_yaml::Value::Bool(b) => Some(b.to_string()),
+      serde_yaml::Value::Number(n) => Some(n.to_string()),
+      _ => None,
+    };
+    let raw = match &value {
+      serde_yaml::Value::Null => continue, // `name:` with no value leaves the variable unanswered
+      serde_yaml::Value::Sequence(items) => items
+        .iter()
+        .map(scalar)
+        .collect::<Option<Vec<_>>>()
+        .map(|items| items.join(var_def.separator())),
+      other => scalar(other),
+    };
+    let raw = raw.ok_or_else(|| {
+      answers_error(format!("value of '{}' must be a scalar or a list of scalars", name))
+    })?;
+    answers.insert(name.to_string(), raw);
+  }
+  debug!("Loaded {} answer(s) from '{}'.", answers.len(), path.display());
+  Ok(answers)
+}
+
+/// Collects variable values: `--var` values first, then generated values, then interactive
+/// prompts. Without a terminal, unprovided variables fall back to their default (or the
+/// keychain) and fail with an error naming the variable instead of blocking on stdin.
+pub(crate) fn gather_variables(
+  manifest: &ScaffoldManifest,
+  provided: &HashMap<String, String>,
+  strict_warnings: bool,
+  prompt_timeout: Option<Duration>,
+  seed: Option<u64>,
+  cache: &mut utils::TransformCache,
+) -> Result<HashMap<String, String>, SpawnError> {
+  let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+  if !interactive {
+    return collect_variables(manifest, provided, strict_warnings, seed, cache, None);
+  }
+  let needs_prompt = manifest.variables.iter().any(|vd| {
+    vd.prompt.is_some() && vd.generator.is_none() && !provided.contains_key(&vd.name)
+  });
+  if needs_prompt {
+    // stderr, so `--stdout-tar` output stays a clean archive
+    eprintln!("Please provide values for the following variables:");
   }
-  Ok(templates)
+  let mut provider = DialoguerProvider::new(prompt_timeout);
+  collect_variables(manifest, provided, strict_warnings, seed, cache, Some(&mut provider))
 }
 
-fn gather_variables(manifest: &ScaffoldManifest) -> Result<HashMap<String, String>, SpawnError> {
+/// Resolves every variable: `provided` values first, then its `generator` (seeded with
+/// `seed`), then `provider` for the rest.
+/// Without a provider, unprovided variables use the keychain or their default, or fail.
+/// `cache` is the run's transform cache, used for `{{var.<transformation>}}` in defaults.
+pub(crate) fn collect_variables(
+  manifest: &ScaffoldManifest,
+  provided: &HashMap<String, String>,
+  strict_warnings: bool,
+  seed: Option<u64>,
+  cache: &mut utils::TransformCache,
+  mut provider: Option<&mut dyn VariableProvider>,
+) -> Result<HashMap<String, String>, SpawnError> {
   let mut variables = HashMap::new();
-  println!("Please provide values for the following variables:");
+  let value_generator = ValueGenerator::new(seed);
+
+  for name in provided.keys() {
+    if !manifest.variables.iter().any(|vd| &vd.name == name) {
+      utils::strict_warn(
+        strict_warnings,
+        format!("--var '{}' does not match any variable of template '{}'.", name, manifest.name),
+      )?;
+    }
+  }
 
   for var_def in &manifest.variables {
+    if let Some(condition) = &var_def.when {
+      if !utils::evaluate_condition(condition, &variables, strict_warnings)? {
+        debug!(
+          "Skipping variable '{}' (condition `{}` not met).",
+          var_def.name, condition
+        );
+        continue;
+      }
+    }
+    if let Some(raw) = provided.get(&var_def.name) {
+      let value = parse_provided_value(var_def, raw, strict_warnings)?;
+      debug!("Using --var value for '{}'.", var_def.name);
+      utils::insert_multi_select_members(var_def, &value, &mut variables);
+      variables.insert(var_def.name.clone(), value);
+      continue;
+    }
+    if let Some(generator) = &var_def.generator {
+      let value = value_generator.generate(&var_def.name, generator);
+      debug!("Generated value for '{}'.", var_def.name);
+      variables.insert(var_def.name.clone(), value);
+      continue;
+    }
     let Some(prompt) = &var_def.prompt else {
       continue;
     };
-    let default_val_str = var_def.default.as_deref();
-
-    let theme = ColorfulTheme::default();
-    let value = match var_def.var_type {
-      VariableType::Boolean => {
-        let default_bool = default_val_str.map_or(false, |s| s.eq_ignore_ascii_case("true"));
-        Confirm::with_theme(&theme)
-          .with_prompt(prompt)
-          .default(default_bool)
-          .interact()?
-          .to_string() // Store as "true" or "false"
+    let resolved_def;
+    let var_def = match resolve_default_references(var_def, manifest, &variables, cache)? {
+      Some(default) => {
+        resolved_def = VariableDefinition {
+          default: Some(default),
+          ..var_def.clone()
+        };
+        &resolved_def
       }
-      VariableType::String => {
-        if var_def.sensitive {
-          let input = Password::with_theme(&theme).with_prompt(prompt);
-          // Password doesn't support default display, maybe confirm?
-          // For now, no default for password.
-          input.interact()?
-        } else {
-          let mut input = Input::with_theme(&theme).with_prompt(prompt);
-          if let Some(default_val) = default_val_str {
-            input = input.default(default_val.to_string());
-          }
+      None => var_def,
+    };
+    let value = match provider.as_deref_mut() {
+      Some(provider) => prompt_variable(var_def, prompt, provider, strict_warnings)?,
+      None => non_interactive_value(var_def, strict_warnings)?,
+    };
+    utils::insert_multi_select_members(var_def, &value, &mut variables);
+    variables.insert(var_def.name.clone(), value);
+  }
+  Ok(variables)
+}
 
-          // --- Add Validation ---
-          #[cfg(feature = "regex")] // Only include if regex feature is enabled
-          if let Some(regex_str) = &var_def.validation_regex {
-            match Regex::new(regex_str) {
-              Ok(regex) => {
-                let regex_err_msg = format!("Input must match regex: {}", regex_str);
-                input = input.validate_with(move |input: &String| -> Result<(), String> {
-                  if regex.is_match(input) {
-                    Ok(())
-                  } else {
-                    Err(regex_err_msg.clone())
-                  }
-                });
-              }
-              Err(e) => {
-                // Log error if regex is invalid in the manifest, but don't block generation
-                warn!(
-                  "Invalid validation_regex for variable '{}': {} - Skipping validation.",
-                  var_def.name, e
-                );
-              }
-            }
+/// The variable's `default` if it passes the same checks as a `--var` value. An invalid one
+/// is reported (an error with `--strict-warnings`) and the prompt is shown without it.
+fn valid_default(
+  var_def: &VariableDefinition,
+  strict_warnings: bool,
+) -> Result<Option<&str>, SpawnError> {
+  let Some(default) = var_def.default.as_deref() else {
+    return Ok(None);
+  };
+  match parse_provided_value(var_def, default, false) {
+    Ok(_) => Ok(Some(default)),
+    Err(e) => {
+      utils::strict_warn(
+        strict_warnings,
+        format!("Ignoring the invalid default: {}", e),
+      )?;
+      Ok(None)
+    }
+  }
+}
+
+/// Asks `provider` for one variable and validates the answer like a `--var` value.
+fn prompt_variable(
+  var_def: &VariableDefinition,
+  prompt: &str,
+  provider: &mut dyn VariableProvider,
+  strict_warnings: bool,
+) -> Result<String, SpawnError> {
+  let default_val_str = valid_default(var_def, strict_warnings)?;
+  let pick = |choices: &[String], index: usize| {
+    choices.get(index).cloned().ok_or_else(|| SpawnError::VariableError {
+      name: var_def.name.clone(),
+      reason: format!("selection {} is out of range ({} choices)", index, choices.len()),
+    })
+  };
+
+  match var_def.var_type {
+    VariableType::Boolean => {
+      let default_bool = default_val_str.and_then(parse_bool_value).unwrap_or(false);
+      // Store as "true" or "false"
+      Ok(provider.prompt_bool(var_def, prompt, default_bool)?.to_string())
+    }
+    VariableType::License => {
+      // Stores the SPDX id; the license text itself is rendered during substitution
+      let ids: Vec<String> = license::supported_ids().into_iter().map(str::to_string).collect();
+      let default_idx = default_val_str
+        .and_then(|d| ids.iter().position(|id| id.eq_ignore_ascii_case(d)))
+        .unwrap_or(0);
+      pick(&ids, provider.prompt_choice(var_def, prompt, &ids, default_idx)?)
+    }
+    VariableType::Choice => {
+      require_choices(var_def)?;
+      let default_idx = default_val_str
+        .and_then(|d| var_def.choices.iter().position(|c| c == d))
+        .unwrap_or(0);
+      pick(
+        &var_def.choices,
+        provider.prompt_choice(var_def, prompt, &var_def.choices, default_idx)?,
+      )
+    }
+    VariableType::MultiSelect => {
+      require_choices(var_def)?;
+      let defaults: Vec<bool> = match default_val_str {
+        Some(default) => {
+          let selected = parse_multi_select_value(var_def, default)?;
+          let selected: Vec<&str> = selected.split(var_def.separator()).collect();
+          var_def.choices.iter().map(|c| selected.contains(&c.as_str())).collect()
+        }
+        None => vec![false; var_def.choices.len()],
+      };
+      let mut selection = provider.prompt_multi_choice(var_def, prompt, &var_def.choices, &defaults)?;
+      // Stored in `choices` order, like `--var` values
+      selection.sort_unstable();
+      selection.dedup();
+      let selected = selection
+        .into_iter()
+        .map(|i| pick(&var_def.choices, i))
+        .collect::<Result<Vec<_>, _>>()?;
+      Ok(selected.join(var_def.separator()))
+    }
+    VariableType::String if var_def.sensitive => {
+      // Prefer the OS keychain if the variable declares a key
+      if let Some(key) = &var_def.keychain_key {
+        if let Some(secret) = keychain::read_secret(key)? {
+          info!("Using value for '{}' from the OS keychain.", var_def.name);
+          return Ok(secret.into_inner());
+        }
+      }
+      let secret = provider.prompt_secret(var_def, prompt)?;
+      if let Some(key) = var_def.keychain_key.as_ref().filter(|_| keychain::is_available()) {
+        let save_prompt = format!("Save '{}' to the OS keychain?", var_def.name);
+        if provider.prompt_bool(var_def, &save_prompt, false)? {
+          keychain::store_secret(key, &secret)?;
+          info!("Saved '{}' to the OS keychain.", var_def.name);
+        }
+      }
+      Ok(secret.into_inner())
+    }
+    VariableType::String | VariableType::Integer | VariableType::Float => {
+      let raw = provider.prompt_string(var_def, prompt, default_val_str)?;
+      parse_provided_value(var_def, &raw, strict_warnings)
+    }
+  }
+}
+
+/// Fills `{{varName}}` (or `{{varName.<transformation>}}`) references in `--output-dir`.
+/// A value that is empty, absolute or contains `..` is rejected, so answers can't move the
+/// project outside the directory the user named.
+fn resolve_output_dir(
+  output_dir: &Path,
+  variables: &HashMap<String, String>,
+  sensitive: &HashSet<String>,
+  cache: &mut utils::TransformCache,
+) -> Result<PathBuf, SpawnError> {
+  let Some(template) = output_dir.to_str().filter(|dir| dir.contains("{{")) else {
+    return Ok(output_dir.to_path_buf());
+  };
+  let invalid = |reason: String| {
+    SpawnError::GenerationError(format!("Invalid --output-dir '{}': {}", template, reason))
+  };
+  let mut uses_sensitive = false;
+  let rendered = utils::render_references(template, |reference| {
+    let masked = utils::is_sensitive_reference(reference, sensitive);
+    uses_sensitive |= masked;
+    let value = utils::resolve_derived_reference(reference, variables, cache)
+      .map_err(&invalid)?
+      .ok_or_else(|| invalid(format!("'{}' has no value", reference)))?;
+    let escapes = Path::new(&value).components().any(|component| {
+      !matches!(component, Component::Normal(_) | Component::CurDir)
+    });
+    if value.trim().is_empty() || escapes {
+      return Err(invalid(format!(
+        "'{}' resolved to '{}'; values must be non-empty relative paths without '..'",
+        reference,
+        utils::shown(&value, masked)
+      )));
+    }
+    Ok(Some(value))
+  })?;
+  info!("Output directory resolved to '{}'.", utils::shown(&rendered, uses_sensitive));
+  Ok(PathBuf::from(rendered))
+}
+
+/// Fills `{{varName}}` (or `{{varName.<transformation>}}`) references in a variable's `default`
+/// from the variables gathered so far. `None` when the default has no references.
+/// Referenced variables must be defined earlier in the manifest and have a value.
+fn resolve_default_references(
+  var_def: &VariableDefinition,
+  manifest: &ScaffoldManifest,
+  gathered: &HashMap<String, String>,
+  cache: &mut utils::TransformCache,
+) -> Result<Option<String>, SpawnError> {
+  let Some(default) = var_def.default.as_deref().filter(|d| d.contains("{{")) else {
+    return Ok(None);
+  };
+  let invalid = |reason: String| SpawnError::VariableError {
+    name: var_def.name.clone(),
+    reason,
+  };
+  let position = |name: &str| manifest.variables.iter().position(|vd| vd.name == name);
+  let own_position = position(&var_def.name);
+  let sensitive = utils::sensitive_names(&manifest.variables, &[]);
+  let mut uses_sensitive = var_def.sensitive;
+  let rendered = utils::render_references(default, |reference| {
+    match utils::resolve_derived_reference(reference, gathered, cache).map_err(&invalid)? {
+      Some(value) => {
+        uses_sensitive |= utils::is_sensitive_reference(reference, &sensitive);
+        Ok(Some(value))
+      }
+      None => {
+        // The variable itself, without a `.transformation` or multiSelect `.choice` suffix
+        let name = reference.split('.').next().unwrap_or(reference);
+        let reason = match position(name).map(|p| (name, p)) {
+          None => format!("default references undefined variable '{}'", reference),
+          Some((_, p)) if Some(p) == own_position => {
+            "default references the variable itself".to_string()
           }
-          // --- End Validation ---
+          Some((name, p)) if Some(p) > own_position => format!(
+            "default references '{}', which is defined after '{}'; move it earlier in `variables`",
+            name, var_def.name
+          ),
+          Some((name, _)) => format!(
+            "default references '{}', which has no value (its `when` condition was not met)",
+            name
+          ),
+        };
+        Err(invalid(reason))
+      }
+    }
+  })?;
+  debug!(
+    "Default of '{}' resolved to '{}'.",
+    var_def.name,
+    utils::shown(&rendered, uses_sensitive)
+  );
+  Ok(Some(rendered))
+}
 
-          input.interact_text()?
+/// Value for a prompted variable when there is no terminal: keychain (sensitive), then default.
+fn non_interactive_value(var_def: &VariableDefinition, strict_warnings: bool) -> Result<String, SpawnError> {
+  if var_def.sensitive {
+    if let Some(key) = &var_def.keychain_key {
+      if let Some(secret) = keychain::read_secret(key)? {
+        info!("Using value for '{}' from the OS keychain.", var_def.name);
+        return Ok(secret.into_inner());
+      }
+    }
+  }
+  match &var_def.default {
+    Some(default) => {
+      debug!("No terminal: using default for '{}'.", var_def.name);
+      parse_provided_value(var_def, default, strict_warnings)
+    }
+    None => Err(SpawnError::VariableError {
+      name: var_def.name.clone(),
+      reason: "no value given and no default; pass it with --var name=value (not prompting without a terminal)".to_string(),
+    }),
+  }
+}
+
+/// Case-insensitive `true/false`, `yes/no`, `y/n` or `1/0`.
+pub(crate) fn parse_bool_value(raw: &str) -> Option<bool> {
+  match raw.trim().to_ascii_lowercase().as_str() {
+    "true" | "yes" | "y" | "1" => Some(true),
+    "false" | "no" | "n" | "0" => Some(false),
+    _ => None,
+  }
+}
+
+/// Validates a non-prompted value (`--var` or a default) against the variable's type and
+/// `validationRegex`, returning the value as it would have been stored by the prompt.
+pub(crate) fn parse_provided_value(
+  var_def: &VariableDefinition,
+  raw: &str,
+  strict_warnings: bool,
+) -> Result<String, SpawnError> {
+  let invalid = |reason: String| SpawnError::VariableError {
+    name: var_def.name.clone(),
+    reason,
+  };
+  match var_def.var_type {
+    VariableType::Boolean => parse_bool_value(raw).map(|b| b.to_string()).ok_or_else(|| {
+      invalid(format!(
+        "'{}' is not a boolean (expected true/false, yes/no, y/n or 1/0)",
+        raw
+      ))
+    }),
+    VariableType::Integer => parse_integer_value(var_def, raw),
+    VariableType::MultiSelect => parse_multi_select_value(var_def, raw),
+    VariableType::Choice => {
+      // Exact match first; otherwise accept a case-insensitive match if it is unambiguous
+      let mut matches = var_def.choices.iter().filter(|c| c.eq_ignore_ascii_case(raw.trim()));
+      if let Some(exact) = var_def.choices.iter().find(|c| *c == raw) {
+        Ok(exact.clone())
+      } else if let (Some(choice), None) = (matches.next(), matches.next()) {
+        Ok(choice.clone())
+      } else {
+        Err(invalid(format!(
+          "'{}' is not one of the choices ({})",
+          raw,
+          var_def.choices.join(", ")
+        )))
+      }
+    }
+    VariableType::Float => parse_float_value(var_def, raw),
+    VariableType::License => license::supported_ids()
+      .into_iter()
+      .find(|id| id.eq_ignore_ascii_case(raw.trim()))
+      .map(str::to_string)
+      .ok_or_else(|| {
+        invalid(format!(
+          "unsupported license '{}' (bundled: {})",
+          raw,
+          license::supported_ids().join(", ")
+        ))
+      }),
+    VariableType::String => {
+      #[cfg(feature = "regex")]
+      if let Some(regex_str) = &var_def.validation_regex {
+        match Regex::new(regex_str) {
+          Ok(regex) if !regex.is_match(raw) => {
+            return Err(invalid(format!("'{}' does not match regex: {}", raw, regex_str)))
+          }
+          Ok(_) => {}
+          Err(e) => utils::strict_warn(
+            strict_warnings,
+            format!(
+              "Invalid validation_regex for variable '{}': {} - Skipping validation.",
+              var_def.name, e
+            ),
+          )?,
         }
-      } // Add other types later if needed
+      }
+      #[cfg(not(feature = "regex"))]
+      let _ = strict_warnings;
+      Ok(raw.to_string())
+    }
+  }
+}
+
+fn require_choices(var_def: &VariableDefinition) -> Result<(), SpawnError> {
+  if var_def.choices.is_empty() {
+    return Err(SpawnError::VariableError {
+      name: var_def.name.clone(),
+      reason: "this varType requires a non-empty 'choices' list".to_string(),
+    });
+  }
+  Ok(())
+}
+
+/// Parses a `multiSelect` value given as a list joined with the variable's `separator` or
+/// with commas (e.g. `--var features=auth,metrics`). Returns the selection in `choices`
+/// order, joined with the separator; an empty string selects nothing.
+pub(crate) fn parse_multi_select_value(
+  var_def: &VariableDefinition,
+  raw: &str,
+) -> Result<String, SpawnError> {
+  let separator = var_def.separator();
+  let parts: Vec<&str> = if raw.contains(separator) {
+    raw.split(separator).collect()
+  } else {
+    raw.split(',').collect()
+  };
+  let mut selected = HashSet::new();
+  for part in parts.into_iter().map(str::trim).filter(|p| !p.is_empty()) {
+    if !var_def.choices.iter().any(|c| c == part) {
+      return Err(SpawnError::VariableError {
+        name: var_def.name.clone(),
+        reason: format!("'{}' is not one of the choices ({})", part, var_def.choices.join(", ")),
+      });
+    }
+    selected.insert(part);
+  }
+  Ok(
+    var_def
+      .choices
+      .iter()
+      .filter(|c| selected.contains(c.as_str()))
+      .map(String::as_str)
+      .collect::<Vec<_>>()
+      .join(separator),
+  )
+}
+
+/// Parses an `integer` variable's input, enforcing `min`/`max`, and returns its canonical
+/// decimal form (e.g. `+042` -> `42`).
+pub(crate) fn parse_integer_value(
+  var_def: &VariableDefinition,
+  input: &str,
+) -> Result<String, SpawnError> {
+  let value: i64 = input.trim().parse().map_err(|_| SpawnError::VariableError {
+    name: var_def.name.clone(),
+    reason: format!("'{}' is not a whole number", input.trim()),
+  })?;
+  check_integer_bounds(var_def, value)?;
+  Ok(value.to_string())
+}
+
+fn check_integer_bounds(var_def: &VariableDefinition, value: i64) -> Result<(), SpawnError> {
+  let invalid = |reason: String| SpawnError::VariableError {
+    name: var_def.name.clone(),
+    reason,
+  };
+  if let Some(min) = var_def.min.filter(|min| min.cmp_integer(value) == Ordering::Less) {
+    return Err(invalid(format!("{} is below the minimum {}", value, min)));
+  }
+  if let Some(max) = var_def.max.filter(|max| max.cmp_integer(value) == Ordering::Greater) {
+    return Err(invalid(format!("{} is above the maximum {}", value, max)));
+  }
+  Ok(())
+}
+
+/// Parses a `float` variable's input, enforcing `min`/`max`, and returns its canonical form
+/// (e.g. `1.50` -> `1.5`, `2.0` -> `2`).
+pub(crate) fn parse_float_value(
+  var_def: &VariableDefinition,
+  input: &str,
+) -> Result<String, SpawnError> {
+  let invalid = |reason: String| SpawnError::VariableError {
+    name: var_def.name.clone(),
+    reason,
+  };
+  let value: f64 = input
+    .trim()
+    .parse()
+    .map_err(|_| invalid(format!("'{}' is not a number", input.trim())))?;
+  if !value.is_finite() {
+    return Err(invalid(format!("'{}' is not a finite number", input.trim())));
+  }
+  if let Some(min) = var_def.min.filter(|min| value < min.as_f64()) {
+    return Err(invalid(format!("{} is below the minimum {}", value, min)));
+  }
+  if let Some(max) = var_def.max.filter(|max| value > max.as_f64()) {
+    return Err(invalid(format!("{} is above the maximum {}", value, max)));
+  }
+  Ok(value.to_string())
+}
+
+/// Shows a `Select` prompt (defaulting to the first item), honoring the prompt timeout.
+fn select_with_timeout(
+  timeout: Option<Duration>,
+  prompt: String,
+  items: Vec<String>,
+) -> Result<usize, SpawnError> {
+  let label = prompt.clone();
+  prompt_with_timeout(timeout, &label, move || {
+    Select::with_theme(theme())
+      .with_prompt(prompt)
+      .items(&items)
+      .default(0)
+      .interact()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn manifest(extra_yaml: &str) -> ScaffoldManifest {
+    serde_yaml::from_str(&format!(
+      "name: Sample\ndescription: Test template\nlanguage: test\nvariables: []\n{}",
+      extra_yaml
+    ))
+    .unwrap()
+  }
+
+  /// Answers the secret prompt and any yes/no follow-up, recording every prompt shown.
+  #[derive(Default)]
+  struct SecretProvider {
+    asked: Vec<String>,
+  }
+
+  impl VariableProvider for SecretProvider {
+    fn prompt_string(
+      &mut self,
+      _var: &VariableDefinition,
+      prompt: &str,
+      _default: Option<&str>,
+    ) -> Result<String, SpawnError> {
+      panic!("unexpected text prompt '{}'", prompt)
+    }
+
+    fn prompt_bool(&mut self, _var: &VariableDefinition, prompt: &str, _default: bool) -> Result<bool, SpawnError> {
+      self.asked.push(prompt.to_string());
+      Ok(true)
+    }
+
+    fn prompt_choice(
+      &mut self,
+      _var: &VariableDefinition,
+      prompt: &str,
+      _choices: &[String],
+      _default: usize,
+    ) -> Result<usize, SpawnError> {
+      panic!("unexpected choice prompt '{}'", prompt)
+    }
+
+    fn prompt_secret(&mut self, _var: &VariableDefinition, prompt: &str) -> Result<utils::SensitiveValue, SpawnError> {
+      self.asked.push(prompt.to_string());
+      Ok(utils::SensitiveValue::new("s3cret"))
+    }
+  }
+
+  #[test]
+  fn missing_keychain_secret_is_prompted_for_and_offered_for_saving() {
+    #[cfg(feature = "keyring")]
+    keychain::tests::use_memory_backend();
+    let manifest: ScaffoldManifest = serde_yaml::from_str(
+      r#"
+name: Sample
+description: Test template
+language: test
+variables:
+  - { name: apiToken, prompt: "Token:", placeholderValue: api_token, sensitive: true,
+      keychainKey: collect-variables-token }
+"#,
+    )
+    .unwrap();
+    let collect = |provider: &mut SecretProvider| {
+      let mut cache = utils::TransformCache::default();
+      collect_variables(&manifest, &HashMap::new(), false, None, &mut cache, Some(provider))
     };
-    variables.insert(var_def.name.clone(), value);
+
+    // Keychain miss: the hidden prompt answers, then saving is offered if keychain support is built in
+    let mut provider = SecretProvider::default();
+    assert_eq!(collect(&mut provider).unwrap()["apiToken"], "s3cret");
+    if !keychain::is_available() {
+      assert_eq!(provider.asked, ["Token:"]);
+      return;
+    }
+    assert_eq!(provider.asked, ["Token:", "Save 'apiToken' to the OS keychain?"]);
+
+    // Saved: the next run reads it from the keychain without asking
+    let mut provider = SecretProvider::default();
+    assert_eq!(collect(&mut provider).unwrap()["apiToken"], "s3cret");
+    assert!(provider.asked.is_empty(), "{:?}", provider.asked);
+  }
+
+  #[test]
+  fn build_check_passes_when_its_command_succeeds() {
+    let project = tempfile::tempdir().unwrap();
+    let manifest = manifest("buildCheck:\n  name: build\n  command: exit 0\n");
+    let (values, sensitive) = (HashMap::new(), HashSet::new());
+    let variables = utils::StepVariables::new(&values, &sensitive);
+    assert!(run_build_check(&manifest, variables, project.path(), None).unwrap());
+  }
+
+  #[test]
+  fn build_check_fails_when_its_command_fails() {
+    let project = tempfile::tempdir().unwrap();
+    let manifest = manifest("buildCheck:\n  name: build\n  command: exit 3\n");
+    let (values, sensitive) = (HashMap::new(), HashSet::new());
+    let variables = utils::StepVariables::new(&values, &sensitive);
+    match run_build_check(&manifest, variables, project.path(), None) {
+      Err(SpawnError::BuildCheckFailed { step_name, reason }) => {
+        assert_eq!(step_name, "build");
+        assert!(reason.contains('3'), "{}", reason);
+      }
+      other => panic!("expected a build check failure, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn build_check_is_skipped_without_a_build_check_step() {
+    let project = tempfile::tempdir().unwrap();
+    let (values, sensitive) = (HashMap::new(), HashSet::new());
+    let variables = utils::StepVariables::new(&values, &sensitive);
+    assert!(!run_build_check(&manifest(""), variables, project.path(), None).unwrap());
+  }
+
+  fn float_variable(bounds_yaml: &str) -> VariableDefinition {
+    serde_yaml::from_str(&format!(
+      "{{ name: ratio, prompt: \"Ratio:\", varType: float, placeholderValue: ratio_value{} }}",
+      bounds_yaml
+    ))
+    .unwrap()
+  }
+
+  fn variable_error(result: Result<String, SpawnError>) -> String {
+    match result {
+      Err(SpawnError::VariableError { name, reason }) => {
+        assert_eq!(name, "ratio");
+        reason
+      }
+      other => panic!("expected a variable error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn float_values_are_stored_in_canonical_form() {
+    let var_def = float_variable("");
+    let cases = [
+      ("1.5", "1.5"),
+      (" 1.50 ", "1.5"),
+      ("2.0", "2"),
+      ("-0.25", "-0.25"),
+      ("1e3", "1000"),
+    ];
+    for (input, canonical) in cases {
+      assert_eq!(parse_float_value(&var_def, input).unwrap(), canonical, "{}", input);
+    }
+  }
+
+  #[test]
+  fn float_values_outside_the_bounds_are_rejected() {
+    let var_def = float_variable(", min: 0.5, max: 2");
+    assert_eq!(parse_float_value(&var_def, "0.5").unwrap(), "0.5");
+    assert_eq!(parse_float_value(&var_def, "2").unwrap(), "2");
+    let reason = variable_error(parse_float_value(&var_def, "0.49"));
+    assert!(reason.contains("below the minimum 0.5"), "{}", reason);
+    let reason = variable_error(parse_float_value(&var_def, "2.01"));
+    assert!(reason.contains("above the maximum 2"), "{}", reason);
+  }
+
+  #[test]
+  fn non_numeric_float_values_are_rejected() {
+    let var_def = float_variable("");
+    for input in ["abc", "", "1.5.2", "1,5"] {
+      let reason = variable_error(parse_float_value(&var_def, input));
+      assert!(reason.contains("is not a number"), "{}: {}", input, reason);
+    }
+    for input in ["inf", "NaN"] {
+      let reason = variable_error(parse_float_value(&var_def, input));
+      assert!(reason.contains("is not a finite number"), "{}: {}", input, reason);
+    }
+  }
+
+  #[test]
+  fn library_generation_lays_down_the_extended_base() {
+    let templates = tempfile::tempdir().unwrap();
+    let base_dir = templates.path().join("base");
+    let child_dir = templates.path().join("child");
+    fs::create_dir_all(&base_dir).unwrap();
+    fs::create_dir_all(&child_dir).unwrap();
+    fs::write(
+      base_dir.join(registry::MANIFEST_FILE_NAME),
+      "name: Base\ndescription: Base\nlanguage: test\nvariables:\n  \
+       - { name: projectName, prompt: Name, placeholderValue: __NAME__ }\n",
+    )
+    .unwrap();
+    fs::write(base_dir.join("base.txt"), "from __NAME__").unwrap();
+    fs::write(
+      child_dir.join(registry::MANIFEST_FILE_NAME),
+      "name: Child\ndescription: Child\nlanguage: test\nextends: Base\nvariables: []\n",
+    )
+    .unwrap();
+    fs::write(child_dir.join("child.txt"), "child __NAME__").unwrap();
+    let child = crate::list::read_and_parse_manifest(&child_dir.join(registry::MANIFEST_FILE_NAME)).unwrap();
+
+    let output = tempfile::tempdir().unwrap();
+    let vars = HashMap::from([("projectName".to_string(), "demo".to_string())]);
+    let report = generate_project(&child, &child_dir, output.path(), &vars).unwrap();
+
+    assert_eq!(report.variables["projectName"], "demo");
+    assert_eq!(fs::read_to_string(output.path().join("base.txt")).unwrap(), "from demo");
+    assert_eq!(fs::read_to_string(output.path().join("child.txt")).unwrap(), "child demo");
   }
-  Ok(variables)
 }
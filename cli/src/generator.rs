@@ -0,0 +1,67 @@
+// src/generator.rs
+//! Values for variables with a `generator` (`randomHex`, `uuid`, `randomPort`).
+//! With `--seed`, each variable draws from its own stream derived from the seed and its name,
+//! so overriding one variable with `--var` doesn't change the values generated for the others.
+//! Without it, every value comes straight from the OS random number generator.
+
+use log::debug;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+
+use crate::config::VariableGenerator;
+
+/// First port of the IANA dynamic (private) range.
+const DYNAMIC_PORT_START: u16 = 49152;
+
+pub struct ValueGenerator {
+  seed: Option<u64>,
+}
+
+impl ValueGenerator {
+  /// Reproducible values with `seed`; full-entropy OS randomness without it.
+  pub fn new(seed: Option<u64>) -> Self {
+    if seed.is_none() {
+      debug!("Generating variable values from the OS random number generator.");
+    }
+    ValueGenerator { seed }
+  }
+
+  /// Generates the value of variable `name`.
+  pub fn generate(&self, name: &str, generator: &VariableGenerator) -> String {
+    match self.seed {
+      Some(seed) => {
+        let mut hasher = Sha256::new();
+        hasher.update(seed.to_le_bytes());
+        hasher.update(name.as_bytes());
+        render(generator, &mut ChaCha20Rng::from_seed(hasher.finalize().into()))
+      }
+      // A u64 seed would cap e.g. a `randomHex: 64` secret at 64 bits of entropy
+      None => render(generator, &mut OsRng),
+    }
+  }
+}
+
+fn render(generator: &VariableGenerator, rng: &mut impl RngCore) -> String {
+  match generator {
+    VariableGenerator::RandomHex(len) => {
+      let mut bytes = vec![0u8; len.div_ceil(2)];
+      rng.fill_bytes(&mut bytes);
+      let mut hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+      hex.truncate(*len);
+      hex
+    }
+    VariableGenerator::Uuid => {
+      let mut bytes = [0u8; 16];
+      rng.fill_bytes(&mut bytes);
+      bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+      bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+      let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+      format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+    VariableGenerator::RandomPort => {
+      let span = u32::from(u16::MAX - DYNAMIC_PORT_START) + 1;
+      (u32::from(DYNAMIC_PORT_START) + rng.next_u32() % span).to_string()
+    }
+  }
+}
@@ -0,0 +1,138 @@
+// src/hooks.rs
+//! `hooks run`: re-runs a template's pre/post-generate hooks against an existing project.
+//! Handy while developing hooks, since nothing is regenerated.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use dialoguer::Confirm;
+use log::{debug, info};
+
+use crate::cli::{HookPhase, HooksArgs, HooksCommands, HooksRunArgs, StderrPolicy};
+use crate::config::ScaffoldManifest;
+use crate::error::SpawnError;
+use crate::generate::{gather_variables, run_hooks, select_template};
+use crate::prompt::theme;
+use crate::registry::TemplateRegistry;
+use crate::state;
+use crate::update::{find_recorded_template, restore_variables};
+use crate::utils::{self, StepLogDir};
+
+pub fn run_hooks_command(
+  args: HooksArgs,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+  stderr_policy: StderrPolicy,
+  hook_timeout: Option<u64>,
+) -> Result<(), SpawnError> {
+  match args.command {
+    HooksCommands::Run(run_args) => {
+      run_hook_phase(run_args, registry, strict_warnings, stderr_policy, hook_timeout)
+    }
+  }
+}
+
+fn run_hook_phase(
+  args: HooksRunArgs,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+  stderr_policy: StderrPolicy,
+  hook_timeout: Option<u64>,
+) -> Result<(), SpawnError> {
+  let project_dir = &args.dir;
+  if !project_dir.is_dir() {
+    return Err(SpawnError::GenerationError(format!(
+      "Project directory '{}' does not exist or is not a directory.",
+      project_dir.display()
+    )));
+  }
+
+  // --- 1. Resolve template and variables ---
+  let (mut manifest, variables) =
+    resolve_template_and_variables(&args, project_dir, registry, strict_warnings)?;
+  manifest.force_check_stderr(stderr_policy.forced());
+  manifest.apply_hook_timeout(hook_timeout);
+
+  let (phase_name, hooks) = match args.phase {
+    HookPhase::Pre => ("Pre-Generate", &manifest.pre_generate),
+    HookPhase::Post => ("Post-Generate", &manifest.post_generate),
+  };
+  if hooks.is_empty() {
+    info!(
+      "Template '{}' has no {} hooks; nothing to run.",
+      manifest.name, phase_name
+    );
+    return Ok(());
+  }
+
+  // --- 2. Guard pre-generate hooks against populated targets ---
+  // They are written to run before any files exist, so they may clobber a real project.
+  if args.phase == HookPhase::Pre && !args.yes && fs::read_dir(project_dir)?.next().is_some() {
+    let confirmed = Confirm::with_theme(theme())
+      .with_prompt(format!(
+        "'{}' is not empty. Run pre-generate hooks in it anyway?",
+        project_dir.display()
+      ))
+      .default(false)
+      .interact()?;
+    if !confirmed {
+      return Err(SpawnError::GenerationError(
+        "Aborted: pre-generate hooks not run against a populated directory.".to_string(),
+      ));
+    }
+  }
+
+  // --- 3. Run ---
+  let step_log = StepLogDir::for_run(args.step_log_dir.as_deref(), project_dir)?;
+  let sensitive = utils::sensitive_names(&manifest.variables, &manifest.derived);
+  let step_variables = utils::StepVariables::new(&variables, &sensitive);
+  run_hooks(phase_name, hooks, step_variables, project_dir, Some(&step_log))?;
+  info!("✅ {} hooks finished in '{}'.", phase_name, project_dir.display());
+  Ok(())
+}
+
+/// Uses the project's state file when no template is given explicitly;
+/// otherwise selects the template as `generate` does and prompts for variables.
+fn resolve_template_and_variables(
+  args: &HooksRunArgs,
+  project_dir: &Path,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+) -> Result<(ScaffoldManifest, HashMap<String, String>), SpawnError> {
+  let state_path = project_dir.join(state::STATE_FILE_NAME);
+  if args.language.is_none() && args.template.is_none() && state_path.is_file() {
+    let recorded = state::read_state(project_dir)?;
+    let (_, template_path, manifest) =
+      find_recorded_template(&recorded, registry)?;
+    debug!(
+      "Using template recorded in {}: {}",
+      state::STATE_FILE_NAME,
+      template_path.display()
+    );
+    // Only the manifest is needed; hooks may come from a base template (`extends`)
+    let manifest = registry.resolve(&template_path, &manifest)?.manifest;
+    let variables = restore_variables(&recorded, &manifest)?;
+    return Ok((manifest, variables));
+  }
+
+  let (_, template_path, manifest) = select_template(
+    args.language.clone(),
+    args.template.clone(),
+    None,
+    registry,
+    None,
+    None,
+  )?;
+  let manifest = registry.resolve(&template_path, &manifest)?.manifest;
+  let mut transform_cache = utils::TransformCache::default();
+  let variables = gather_variables(
+    &manifest,
+    &HashMap::new(),
+    strict_warnings,
+    None,
+    None,
+    &mut transform_cache,
+  )?;
+  Ok((manifest, variables))
+}
@@ -0,0 +1,165 @@
+// src/keychain.rs
+//! Optional OS keychain integration for sensitive variables.
+//! Only active when built with the `keyring` feature; otherwise lookups always miss.
+
+use crate::error::SpawnError;
+use crate::utils::SensitiveValue;
+
+/// Service name under which spawnpoint secrets are stored in the OS keychain.
+#[cfg(feature = "keyring")]
+const KEYCHAIN_SERVICE: &str = "spawnpoint";
+
+/// Reads a secret from the OS keychain.
+/// Returns Ok(None) if no entry exists for the key.
+#[cfg(feature = "keyring")]
+pub fn read_secret(key: &str) -> Result<Option<SensitiveValue>, SpawnError> {
+  let entry = keyring::Entry::new(KEYCHAIN_SERVICE, key).map_err(|e| keychain_error(key, e))?;
+  match entry.get_password() {
+    Ok(secret) => Ok(Some(SensitiveValue::new(secret))),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(keychain_error(key, e)),
+  }
+}
+
+/// Stores a secret in the OS keychain, overwriting any existing entry.
+#[cfg(feature = "keyring")]
+pub fn store_secret(key: &str, secret: &SensitiveValue) -> Result<(), SpawnError> {
+  let entry = keyring::Entry::new(KEYCHAIN_SERVICE, key).map_err(|e| keychain_error(key, e))?;
+  entry
+    .set_password(secret.expose())
+    .map_err(|e| keychain_error(key, e))
+}
+
+#[cfg(feature = "keyring")]
+fn keychain_error(key: &str, error: keyring::Error) -> SpawnError {
+  SpawnError::KeychainError {
+    key: key.to_string(),
+    reason: error.to_string(),
+  }
+}
+
+/// Keychain support not compiled in: lookups always miss.
+#[cfg(not(feature = "keyring"))]
+pub fn read_secret(key: &str) -> Result<Option<SensitiveValue>, SpawnError> {
+  log::debug!(
+    "Keychain lookup for '{}' skipped (built without 'keyring' feature).",
+    key
+  );
+  Ok(None)
+}
+
+/// Keychain support not compiled in: storing is not possible.
+#[cfg(not(feature = "keyring"))]
+pub fn store_secret(key: &str, _secret: &SensitiveValue) -> Result<(), SpawnError> {
+  Err(SpawnError::KeychainError {
+    key: key.to_string(),
+    reason: "spawnpoint was built without the 'keyring' feature".to_string(),
+  })
+}
+
+/// Whether keychain support was compiled in.
+pub fn is_available() -> bool {
+  cfg!(feature = "keyring")
+}
+
+#[cfg(all(test, feature = "keyring"))]
+pub(crate) mod tests {
+  use super::*;
+  use std::any::Any;
+  use std::collections::BTreeMap;
+  use std::sync::{Mutex, Once};
+
+  use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+
+  /// Secrets of the in-memory backend by (service, user). Unlike `keyring::mock`, every entry
+  /// for the same key shares one secret, as in a real keychain.
+  static SECRETS: Mutex<BTreeMap<(String, String), Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+  /// Lookups of this key fail like an unavailable keychain would.
+  const BROKEN_KEY: &str = "broken";
+
+  #[derive(Debug)]
+  struct MemoryCredential {
+    service: String,
+    user: String,
+  }
+
+  impl MemoryCredential {
+    fn id(&self) -> keyring::Result<(String, String)> {
+      if self.user == BROKEN_KEY {
+        return Err(keyring::Error::NoStorageAccess("keychain is locked".into()));
+      }
+      Ok((self.service.clone(), self.user.clone()))
+    }
+  }
+
+  impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+      SECRETS.lock().unwrap().insert(self.id()?, secret.to_vec());
+      Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+      SECRETS.lock().unwrap().get(&self.id()?).cloned().ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+      SECRETS.lock().unwrap().remove(&self.id()?).map(|_| ()).ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+      self
+    }
+  }
+
+  #[derive(Debug)]
+  struct MemoryBackend;
+
+  impl CredentialBuilderApi for MemoryBackend {
+    fn build(&self, _target: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+      Ok(Box::new(MemoryCredential {
+        service: service.to_string(),
+        user: user.to_string(),
+      }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+      self
+    }
+  }
+
+  /// Routes keychain access to an in-memory store, for tests of code that saves secrets.
+  pub(crate) fn use_memory_backend() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| keyring::set_default_credential_builder(Box::new(MemoryBackend)));
+  }
+
+  #[test]
+  fn stored_secret_is_read_back() {
+    use_memory_backend();
+    store_secret("api-token", &SensitiveValue::new("s3cret")).unwrap();
+    assert_eq!(read_secret("api-token").unwrap().map(SensitiveValue::into_inner).as_deref(), Some("s3cret"));
+    assert_eq!(
+      SECRETS.lock().unwrap().get(&(KEYCHAIN_SERVICE.to_string(), "api-token".to_string())),
+      Some(&b"s3cret".to_vec())
+    );
+  }
+
+  #[test]
+  fn missing_secret_is_none() {
+    use_memory_backend();
+    assert_eq!(read_secret("never-stored").unwrap(), None);
+  }
+
+  #[test]
+  fn backend_failure_names_the_key() {
+    use_memory_backend();
+    match read_secret(BROKEN_KEY) {
+      Err(SpawnError::KeychainError { key, reason }) => {
+        assert_eq!(key, BROKEN_KEY);
+        assert!(reason.contains("keychain is locked"), "{}", reason);
+      }
+      other => panic!("expected a keychain error, got {:?}", other),
+    }
+  }
+}
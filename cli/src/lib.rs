@@ -0,0 +1,44 @@
+// src/lib.rs
+//! Spawn Point as a library. The `spawnpoint` binary is a thin wrapper around [`run`]; the
+//! modules behind it are internal, and the embedding API is re-exported here.
+//!
+//! For embedding, [`generate_project`] generates a template without prompting, and
+//! [`generate_project_with`] asks a [`VariableProvider`] for missing values. Load a
+//! manifest with [`read_manifest`] or find templates with [`find_available_templates`].
+
+pub mod cli;
+pub(crate) mod app;
+pub(crate) mod archive;
+pub(crate) mod compare;
+pub(crate) mod config;
+pub(crate) mod describe;
+pub(crate) mod embedded;
+pub(crate) mod error;
+pub(crate) mod generate;
+pub(crate) mod generator;
+pub(crate) mod hooks;
+pub(crate) mod keychain;
+pub(crate) mod license;
+pub(crate) mod lint;
+pub(crate) mod list;
+pub(crate) mod new_template;
+pub(crate) mod prompt;
+pub(crate) mod registry;
+pub(crate) mod remote;
+pub(crate) mod state;
+pub(crate) mod update;
+pub(crate) mod utils;
+pub(crate) mod validate;
+
+pub use app::run;
+pub use config::{
+  BomHandling, CaseTransformation, Condition, ConditionExpr, ConditionOperator, ContentDelimiters,
+  DerivedVariable, NumericBound, PlaceholderFilenames, ProgressUnit, ScaffoldManifest, StepFailure,
+  UndefinedEnv, ValidationConfig, ValidationStep, VariableDefinition, VariableGenerator, VariableType,
+};
+pub use error::SpawnError;
+pub use generate::{find_available_templates, generate_project, generate_project_with, GenerationReport};
+pub use list::read_and_parse_manifest as read_manifest;
+pub use prompt::VariableProvider;
+pub use registry::{ResolvedTemplate, TemplateRegistry};
+pub use utils::{GeneratedFile, SensitiveValue};
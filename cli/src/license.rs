@@ -0,0 +1,123 @@
+// src/license.rs
+//! Bundled SPDX license texts for `varType: license` variables.
+//! Texts use `{{year}}` and `{{holder}}` markers that are filled in at generation time.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::SpawnError;
+
+/// (SPDX id, license text) for every bundled license.
+const LICENSES: &[(&str, &str)] = &[
+  ("MIT", include_str!("licenses/MIT.txt")),
+  ("Apache-2.0", include_str!("licenses/Apache-2.0.txt")),
+  ("BSD-3-Clause", include_str!("licenses/BSD-3-Clause.txt")),
+];
+
+/// SPDX ids of the bundled licenses, in display order.
+pub fn supported_ids() -> Vec<&'static str> {
+  LICENSES.iter().map(|(id, _)| *id).collect()
+}
+
+/// Returns the full text of `spdx_id` (matched case-insensitively) with the year and holder filled in.
+pub fn render(spdx_id: &str, holder: &str, year: i64) -> Result<String, SpawnError> {
+  let (_, text) = LICENSES
+    .iter()
+    .find(|(id, _)| id.eq_ignore_ascii_case(spdx_id))
+    .ok_or_else(|| SpawnError::UnsupportedLicense {
+      id: spdx_id.to_string(),
+      supported: supported_ids().join(", "),
+    })?;
+  Ok(
+    text
+      .replace("{{year}}", &year.to_string())
+      .replace("{{holder}}", holder),
+  )
+}
+
+/// The current calendar year (UTC).
+pub fn current_year() -> i64 {
+  let secs = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs() as i64);
+  year_from_days(secs.div_euclid(86_400))
+}
+
+/// Converts days since 1970-01-01 into a proleptic Gregorian year
+/// (Howard Hinnant's `civil_from_days`, keeping only the year).
+fn year_from_days(days: i64) -> i64 {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z - era * 146_097; // [0, 146096]
+  let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365; // [0, 399]
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+  let mp = (5 * doy + 2) / 153; // [0, 11], March-based month
+  let year = yoe + era * 400;
+  if mp >= 10 {
+    year + 1 // January or February
+  } else {
+    year
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  use crate::config::ScaffoldManifest;
+  use crate::utils;
+
+  #[test]
+  fn mit_is_rendered_with_year_and_holder() {
+    let text = render("mit", "Jane Doe", 2024).unwrap();
+    assert!(text.starts_with("MIT License\n\nCopyright (c) 2024 Jane Doe\n"), "{}", text);
+    assert!(!text.contains("{{"), "{}", text);
+  }
+
+  #[test]
+  fn unknown_license_lists_the_supported_ones() {
+    match render("GPL-3.0", "Jane Doe", 2024) {
+      Err(SpawnError::UnsupportedLicense { id, supported }) => {
+        assert_eq!(id, "GPL-3.0");
+        assert_eq!(supported, "MIT, Apache-2.0, BSD-3-Clause");
+      }
+      other => panic!("expected an unsupported license error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn license_variable_expands_with_the_holder_variable_and_current_year() {
+    let manifest: ScaffoldManifest = serde_yaml::from_str(
+      r#"
+name: Sample
+description: Test template
+language: test
+variables:
+  - { name: author, prompt: "Author:", placeholderValue: author_name }
+  - name: license
+    prompt: "License:"
+    varType: license
+    licenseHolder: author
+    placeholderValue: "--license-text--"
+"#,
+    )
+    .unwrap();
+    let base_variables = HashMap::from([
+      ("author".to_string(), "Jane Doe".to_string()),
+      ("license".to_string(), "MIT".to_string()),
+    ]);
+    let substitutions =
+      utils::compute_transformed_variables(&base_variables, &manifest.variables, &[], true).unwrap();
+    let expected = format!("Copyright (c) {} Jane Doe", current_year());
+    assert!(substitutions["--license-text--"].contains(&expected));
+  }
+
+  #[test]
+  fn year_from_days_handles_year_boundaries() {
+    assert_eq!(year_from_days(0), 1970);
+    assert_eq!(year_from_days(-1), 1969);
+    assert_eq!(year_from_days(19_722), 2023); // 2023-12-31
+    assert_eq!(year_from_days(19_723), 2024); // 2024-01-01
+    assert_eq!(year_from_days(19_782), 2024); // 2024-02-29
+  }
+}
@@ -0,0 +1,584 @@
+// src/lint.rs
+//! Offline manifest checks (`validate --only-manifest-validation`, `validate-manifest`).
+//! Never runs commands or generates files: only parses manifests and inspects the template tree.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::config::{
+  CaseTransformation, Condition, ConditionOperator, ScaffoldManifest, ValidationStep, VariableGenerator,
+  VariableType,
+};
+use crate::error::SpawnError;
+use crate::generate::{parse_bool_value, parse_float_value, parse_integer_value, parse_multi_select_value};
+use crate::license;
+use crate::list::read_and_parse_manifest;
+use crate::registry::{TemplateRegistry, MANIFEST_FILE_NAME};
+use crate::utils;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+/// What linting found in one template. Issues always fail; warnings (e.g. unused
+/// placeholders) only fail with `--strict-warnings`.
+#[derive(Debug, Default)]
+pub struct LintFindings {
+  pub issues: Vec<String>,
+  pub warnings: Vec<String>,
+}
+
+impl From<String> for LintFindings {
+  fn from(issue: String) -> Self {
+    LintFindings {
+      issues: vec![issue],
+      warnings: Vec::new(),
+    }
+  }
+}
+
+/// Lints one template (by language + name) or, with `all`, every template directory.
+/// Prints a per-template summary and fails if any template has problems.
+pub fn run_manifest_lint(
+  registry: &TemplateRegistry,
+  selected: Option<(&str, &str)>,
+  strict_warnings: bool,
+) -> Result<(), SpawnError> {
+  let mut results: Vec<(String, LintFindings)> = Vec::new();
+  for (dir_name, template_dir, manifest) in registry.templates() {
+    if selected.is_some_and(|(lang, name)| manifest.language != lang || manifest.name != name) {
+      continue;
+    }
+    let label = format!("{} ({}, {})", manifest.name, manifest.language, dir_name);
+    results.push((label, lint_resolved(registry, template_dir, manifest)));
+  }
+  // Unparseable manifests can't be matched by name, so they only show up with --all
+  if selected.is_none() {
+    for (dir_name, error) in registry.invalid() {
+      results.push((dir_name.clone(), error.clone().into()));
+    }
+  }
+
+  if let Some((lang, name)) = selected {
+    if results.is_empty() {
+      return Err(SpawnError::TemplateNotFound(format!(
+        "Template '{}' for language '{}' not found.",
+        name, lang
+      )));
+    }
+  }
+
+  report_lint_results(&results, strict_warnings)
+}
+
+/// Lints the template at `path` (its directory or its `scaffold.yaml`), wherever it lives.
+pub fn run_validate_manifest(path: &Path, strict_warnings: bool) -> Result<(), SpawnError> {
+  let (template_dir, manifest_path) = if path.is_dir() {
+    (path.to_path_buf(), path.join(MANIFEST_FILE_NAME))
+  } else {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    (parent.unwrap_or(Path::new(".")).to_path_buf(), path.to_path_buf())
+  };
+  let result = match read_and_parse_manifest(&manifest_path) {
+    Ok(manifest) => {
+      let findings = if manifest.extends.is_some() {
+        // `extends` bases are looked up among the template's sibling directories
+        let siblings = TemplateRegistry::scan(&template_dir.join(".."), false)?;
+        lint_resolved(&siblings, &template_dir, &manifest)
+      } else {
+        lint_template(&template_dir, &manifest)
+      };
+      let label = format!("{} ({}, {})", manifest.name, manifest.language, template_dir.display());
+      (label, findings)
+    }
+    Err(e) => (manifest_path.display().to_string(), e.to_string().into()),
+  };
+  report_lint_results(&[result], strict_warnings)
+}
+
+/// Lints a template as it generates, with any `extends` bases layered underneath.
+fn lint_resolved(
+  registry: &TemplateRegistry,
+  template_dir: &Path,
+  manifest: &ScaffoldManifest,
+) -> LintFindings {
+  match registry.resolve(template_dir, manifest) {
+    Ok(resolved) => lint_template(&resolved.path, &resolved.manifest),
+    Err(e) => e.to_string().into(),
+  }
+}
+
+/// Structural issues of a manifest plus warnings about its placeholder usage.
+pub fn lint_template(template_path: &Path, manifest: &ScaffoldManifest) -> LintFindings {
+  LintFindings {
+    issues: lint_manifest(template_path, manifest),
+    warnings: lint_placeholder_usage(template_path, manifest),
+  }
+}
+
+/// Prints a per-template summary and fails if any template has issues (or, with
+/// `strict_warnings`, warnings).
+fn report_lint_results(
+  results: &[(String, LintFindings)],
+  strict_warnings: bool,
+) -> Result<(), SpawnError> {
+  let mut failed = 0;
+  for (label, findings) in results {
+    let (issues, warnings) = (&findings.issues, &findings.warnings);
+    if issues.is_empty() && warnings.is_empty() {
+      println!("✅ {}: OK", label);
+      continue;
+    }
+    let failing = !issues.is_empty() || strict_warnings;
+    if failing {
+      failed += 1;
+    }
+    let mut counts = Vec::new();
+    if !issues.is_empty() {
+      counts.push(format!("{} issue(s)", issues.len()));
+    }
+    if !warnings.is_empty() {
+      counts.push(format!("{} warning(s)", warnings.len()));
+    }
+    println!("{} {}: {}", if failing { "❌" } else { "⚠️" }, label, counts.join(", "));
+    for issue in issues {
+      println!("   - {}", issue);
+    }
+    for warning in warnings {
+      println!("   - warning: {}", warning);
+    }
+  }
+  info!(
+    "Manifest validation: {} template(s) checked, {} with problems.",
+    results.len(),
+    failed
+  );
+  if failed > 0 {
+    return Err(SpawnError::ValidationError {
+      step_name: "Manifest validation".to_string(),
+      reason: format!("{} template(s) with structural problems", failed),
+    });
+  }
+  Ok(())
+}
+
+/// Returns every structural problem found in a parsed manifest.
+pub fn lint_manifest(template_path: &Path, manifest: &ScaffoldManifest) -> Vec<String> {
+  let mut issues = Vec::new();
+  let defined: HashMap<&str, &VariableType> = manifest
+    .variables
+    .iter()
+    .map(|vd| (vd.name.as_str(), &vd.var_type))
+    .collect();
+
+  // --- Variable names and placeholder uniqueness ---
+  let mut seen_names = HashSet::new();
+  let mut placeholder_owners: HashMap<&str, &str> = HashMap::new();
+  for var_def in &manifest.variables {
+    if var_def.name.trim().is_empty() {
+      issues.push("A variable has an empty name.".to_string());
+    }
+    if !seen_names.insert(var_def.name.as_str()) {
+      issues.push(format!("Variable '{}' is defined more than once.", var_def.name));
+    }
+    let placeholders = std::iter::once(&var_def.placeholder_value).chain(var_def.transformations.values());
+    for placeholder in placeholders {
+      if placeholder.is_empty() {
+        issues.push(format!("Variable '{}' has an empty placeholder.", var_def.name));
+        continue;
+      }
+      match placeholder_owners.insert(placeholder, &var_def.name) {
+        Some(owner) if owner == var_def.name => issues.push(format!(
+          "Placeholder '{}' is used twice by variable '{}'.",
+          placeholder, var_def.name
+        )),
+        Some(owner) => issues.push(format!(
+          "Placeholder '{}' is used by both '{}' and '{}'.",
+          placeholder, owner, var_def.name
+        )),
+        None => {}
+      }
+    }
+  }
+
+  // --- Variable definition sanity ---
+  let mut declared_before: HashSet<&str> = HashSet::new();
+  // `<multiSelect>.<choice>` names usable in conditions
+  let mut declared_members: HashSet<String> = HashSet::new();
+  for var_def in &manifest.variables {
+    let name = &var_def.name;
+    match (&var_def.var_type, var_def.default.as_deref()) {
+      (VariableType::Boolean, Some(default)) if parse_bool_value(default).is_none() => {
+        issues.push(format!(
+          "Boolean variable '{}' has non-boolean default '{}'.",
+          name, default
+        ))
+      }
+      (VariableType::Choice, default) => {
+        if var_def.choices.is_empty() {
+          issues.push(format!("Choice variable '{}' has no choices.", name));
+        } else if let Some(default) = default.filter(|d| !var_def.choices.iter().any(|c| c == d)) {
+          issues.push(format!(
+            "Default '{}' of choice variable '{}' is not one of its choices.",
+            default, name
+          ));
+        }
+      }
+      (VariableType::MultiSelect, default) => {
+        if var_def.choices.is_empty() {
+          issues.push(format!("Multi-select variable '{}' has no choices.", name));
+        } else if let Some(Err(e)) = default.map(|d| parse_multi_select_value(var_def, d)) {
+          issues.push(format!("Default of '{}' is invalid: {}", name, e));
+        }
+        if var_def.separator.as_deref() == Some("") {
+          issues.push(format!("Multi-select variable '{}' has an empty separator.", name));
+        }
+      }
+      (VariableType::Integer, Some(default)) => {
+        if let Err(e) = parse_integer_value(var_def, default) {
+          issues.push(format!("Default of '{}' is invalid: {}", name, e));
+        }
+      }
+      (VariableType::Float, Some(default)) => {
+        if let Err(e) = parse_float_value(var_def, default) {
+          issues.push(format!("Default of '{}' is invalid: {}", name, e));
+        }
+      }
+      (VariableType::License, default) => {
+        let supported = license::supported_ids();
+        match default {
+          Some(id) if !supported.iter().any(|s| s.eq_ignore_ascii_case(id)) => issues.push(format!(
+            "License variable '{}' defaults to unsupported license '{}' (bundled: {}).",
+            name,
+            id,
+            supported.join(", ")
+          )),
+          None if var_def.prompt.is_none() => issues.push(format!(
+            "License variable '{}' has neither a prompt nor a default license.",
+            name
+          )),
+          _ => {}
+        }
+        if let Some(holder) = &var_def.license_holder {
+          if !defined.contains_key(holder.as_str()) {
+            issues.push(format!(
+              "License variable '{}' uses undefined holder variable '{}'.",
+              name, holder
+            ));
+          }
+        }
+      }
+      _ => {}
+    }
+    let numeric = matches!(var_def.var_type, VariableType::Integer | VariableType::Float);
+    if !numeric && (var_def.min.is_some() || var_def.max.is_some()) {
+      issues.push(format!("'{}' sets min/max but is not an integer or float variable.", name));
+    }
+    if let (Some(min), Some(max)) = (var_def.min, var_def.max) {
+      if min > max {
+        issues.push(format!("'{}' has min {} greater than max {}.", name, min, max));
+      }
+    }
+    let has_choices = matches!(var_def.var_type, VariableType::Choice | VariableType::MultiSelect);
+    if !has_choices && !var_def.choices.is_empty() {
+      issues.push(format!("'{}' sets choices but is not a choice or multiSelect variable.", name));
+    }
+    if var_def.var_type != VariableType::MultiSelect && var_def.separator.is_some() {
+      issues.push(format!("'{}' sets separator but is not a multiSelect variable.", name));
+    }
+    match (&var_def.generator, &var_def.var_type) {
+      (None, _) | (Some(_), VariableType::String) => {}
+      (Some(VariableGenerator::RandomPort), VariableType::Integer) => {}
+      (Some(generator), _) => issues.push(format!(
+        "Generator {} of '{}' does not produce a valid {:?} value.",
+        generator, name, var_def.var_type
+      )),
+    }
+    if var_def.generator == Some(VariableGenerator::RandomHex(0)) {
+      issues.push(format!("'{}' generates an empty value (randomHex: 0).", name));
+    }
+    if var_def.keychain_key.is_some() && !var_def.sensitive {
+      issues.push(format!("'{}' sets keychainKey but is not sensitive.", name));
+    }
+    if let Some(condition) = &var_def.when {
+      if !declared_before.contains(condition.variable.as_str())
+        && !declared_members.contains(&condition.variable)
+      {
+        issues.push(format!(
+          "'{}' has a `when` condition on '{}', which is not defined before it.",
+          name, condition.variable
+        ));
+      }
+      issues.extend(lint_condition_operator(condition, &format!("'{}' `when`", name)));
+    }
+    let default_references = var_def.default.as_deref().map(utils::derived_template_references);
+    for reference in default_references.unwrap_or_default() {
+      let base = reference.split('.').next().unwrap_or(reference);
+      if !declared_before.contains(base) {
+        issues.push(format!(
+          "Default of '{}' references '{}', which is not a variable defined before it.",
+          name, reference
+        ));
+      }
+    }
+    #[cfg(feature = "regex")]
+    if let Some(regex_str) = &var_def.validation_regex {
+      if let Err(e) = Regex::new(regex_str) {
+        issues.push(format!("'{}' has an invalid validationRegex: {}", name, e));
+      }
+    }
+    declared_before.insert(name.as_str());
+    if var_def.var_type == VariableType::MultiSelect {
+      declared_members.extend(var_def.choices.iter().map(|c| utils::multi_select_member(name, c)));
+    }
+  }
+
+  // --- Derived values ---
+  let mut derived_before: HashSet<&str> = HashSet::new();
+  for derived_def in &manifest.derived {
+    let name = derived_def.name.as_str();
+    if defined.contains_key(name) {
+      issues.push(format!("Derived value '{}' has the same name as a variable.", name));
+    }
+    if derived_def.placeholder_value.is_empty() {
+      issues.push(format!("Derived value '{}' has an empty placeholder.", name));
+    } else if let Some(owner) = placeholder_owners.get(derived_def.placeholder_value.as_str()) {
+      issues.push(format!(
+        "Placeholder '{}' is used by both '{}' and derived value '{}'.",
+        derived_def.placeholder_value, owner, name
+      ));
+    }
+    for reference in utils::derived_template_references(&derived_def.template) {
+      let known = |r: &str| {
+        defined.contains_key(r) || declared_members.contains(r) || derived_before.contains(r)
+      };
+      if known(reference) {
+        continue;
+      }
+      match reference.rsplit_once('.') {
+        Some((base, transformation)) if known(base) => {
+          if CaseTransformation::from_name(transformation).is_none() {
+            issues.push(format!(
+              "Derived value '{}' uses unknown transformation '{}'.",
+              name, transformation
+            ));
+          }
+        }
+        _ => issues.push(format!(
+          "Derived value '{}' references '{}', which is not a variable or an earlier derived value.",
+          name, reference
+        )),
+      }
+    }
+    if let Some(when) = &derived_def.when {
+      for condition in when.leaves() {
+        if !defined.contains_key(condition.variable.as_str())
+          && !declared_members.contains(&condition.variable)
+          && !derived_before.contains(condition.variable.as_str())
+        {
+          issues.push(format!(
+            "Derived value '{}' depends on undefined variable '{}'.",
+            name, condition.variable
+          ));
+        }
+        issues.extend(lint_condition_operator(
+          condition,
+          &format!("Derived value '{}'", name),
+        ));
+      }
+    }
+    derived_before.insert(name);
+  }
+
+  // --- Hook and validation steps ---
+  let mut steps: Vec<(&str, &ValidationStep)> = Vec::new();
+  steps.extend(manifest.pre_generate.iter().map(|s| ("preGenerate", s)));
+  steps.extend(manifest.post_generate.iter().map(|s| ("postGenerate", s)));
+  steps.extend(manifest.build_check.iter().map(|s| ("buildCheck", s)));
+  if let Some(validation) = &manifest.validation {
+    steps.extend(validation.setup.iter().map(|s| ("validation.setup", s)));
+    steps.extend(validation.steps.iter().map(|s| ("validation.steps", s)));
+    steps.extend(validation.teardown.iter().map(|s| ("validation.teardown", s)));
+  }
+  for (phase, step) in steps {
+    if step.args.is_empty() && step.command.trim().is_empty() {
+      issues.push(format!("{} step '{}' has neither a command nor args.", phase, step.name));
+    }
+    if !step.args.is_empty() && step.shell.is_some() {
+      issues.push(format!(
+        "{} step '{}' sets shell, which is ignored for args.",
+        phase, step.name
+      ));
+    }
+  }
+
+  // --- Content delimiters ---
+  if let Some(delimiters) = &manifest.content_delimiters {
+    if delimiters.open.is_empty() || delimiters.close.is_empty() {
+      issues.push("contentDelimiters: open and close must not be empty.".to_string());
+    }
+  }
+
+  // --- Exclude patterns ---
+  if let Err(e) = utils::ExcludeMatcher::new(&manifest.exclude) {
+    issues.push(e.to_string());
+  }
+
+  // --- Conditional paths ---
+  let mut conditional_keys: Vec<&String> = manifest.conditional_paths.keys().collect();
+  conditional_keys.sort();
+  for key in conditional_keys {
+    let relative: PathBuf = key.replace('\\', "/").split('/').collect();
+    if !template_path.join(&relative).exists() {
+      issues.push(format!("conditionalPaths entry '{}' does not exist in the template.", key));
+    }
+    for condition in manifest.conditional_paths[key].leaves() {
+      if !defined.contains_key(condition.variable.as_str())
+        && !declared_members.contains(&condition.variable)
+      {
+        issues.push(format!(
+          "conditionalPaths entry '{}' depends on undefined variable '{}'.",
+          key, condition.variable
+        ));
+      }
+      issues.extend(lint_condition_operator(
+        condition,
+        &format!("conditionalPaths entry '{}'", key),
+      ));
+    }
+  }
+
+  issues
+}
+
+/// Warns about placeholders that no template file path or content uses. A variable's own
+/// placeholder is not required when the variable is used some other way (hook commands,
+/// conditions, derived values, license holder, filename markers).
+fn lint_placeholder_usage(template_path: &Path, manifest: &ScaffoldManifest) -> Vec<String> {
+  let markers = manifest.placeholder_filenames.as_ref().map(|config| {
+    manifest
+      .variables
+      .iter()
+      .map(|vd| format!("{}{}{}", config.prefix, vd.name, config.suffix))
+      .collect::<Vec<_>>()
+  });
+  let mut candidates: Vec<&str> = Vec::new();
+  for var_def in &manifest.variables {
+    candidates.push(&var_def.placeholder_value);
+    candidates.extend(var_def.transformations.values().map(String::as_str));
+  }
+  candidates.extend(manifest.derived.iter().map(|d| d.placeholder_value.as_str()));
+  candidates.extend(markers.iter().flatten().map(String::as_str));
+  candidates.retain(|c| !c.is_empty());
+
+  let found = match utils::find_placeholders_in_template(template_path, manifest, &candidates) {
+    Ok(found) => found,
+    Err(e) => return vec![format!("Could not scan template files for placeholders: {}", e)],
+  };
+  let used_elsewhere = variables_used_outside_files(manifest);
+
+  let mut issues = Vec::new();
+  for (index, var_def) in manifest.variables.iter().enumerate() {
+    let marker_used = markers.as_ref().is_some_and(|m| found.contains(&m[index]));
+    if !var_def.placeholder_value.is_empty()
+      && !found.contains(&var_def.placeholder_value)
+      && !marker_used
+      && !used_elsewhere.contains(var_def.name.as_str())
+    {
+      issues.push(format!(
+        "Placeholder '{}' of variable '{}' does not appear in any template file.",
+        var_def.placeholder_value, var_def.name
+      ));
+    }
+    let mut transformation_placeholders: Vec<&String> = var_def.transformations.values().collect();
+    transformation_placeholders.sort();
+    for placeholder in transformation_placeholders {
+      if !placeholder.is_empty() && !found.contains(placeholder) {
+        issues.push(format!(
+          "Placeholder '{}' (a transformation of '{}') does not appear in any template file.",
+          placeholder, var_def.name
+        ));
+      }
+    }
+  }
+  for derived_def in &manifest.derived {
+    let placeholder = &derived_def.placeholder_value;
+    if !placeholder.is_empty() && !found.contains(placeholder) {
+      issues.push(format!(
+        "Placeholder '{}' of derived value '{}' does not appear in any template file.",
+        placeholder, derived_def.name
+      ));
+    }
+  }
+  issues
+}
+
+/// Names of variables referenced by hooks, conditions, derived values or license holders.
+fn variables_used_outside_files(manifest: &ScaffoldManifest) -> HashSet<&str> {
+  let mut referenced: HashSet<String> = HashSet::new();
+  let mut steps: Vec<&ValidationStep> = Vec::new();
+  steps.extend(&manifest.pre_generate);
+  steps.extend(&manifest.post_generate);
+  steps.extend(&manifest.build_check);
+  if let Some(validation) = &manifest.validation {
+    steps.extend(validation.setup.iter().chain(&validation.steps).chain(&validation.teardown));
+  }
+  for step in steps {
+    for text in std::iter::once(&step.command).chain(&step.args).chain(step.env.values()) {
+      referenced.extend(utils::derived_template_references(text).into_iter().map(str::to_string));
+    }
+  }
+  if let Some(message) = &manifest.post_message {
+    referenced.extend(utils::derived_template_references(message).into_iter().map(str::to_string));
+  }
+  for derived_def in &manifest.derived {
+    referenced.extend(
+      utils::derived_template_references(&derived_def.template).into_iter().map(str::to_string),
+    );
+    referenced.extend(derived_def.when.iter().flat_map(|w| w.leaves()).map(|c| c.variable.clone()));
+  }
+  referenced.extend(
+    manifest.conditional_paths.values().flat_map(|c| c.leaves()).map(|c| c.variable.clone()),
+  );
+  for var_def in &manifest.variables {
+    referenced.extend(var_def.when.iter().map(|c| c.variable.clone()));
+    referenced.extend(var_def.license_holder.iter().cloned());
+  }
+
+  // `name.transformation` and `<multiSelect>.<choice>` references count for `name`
+  let referenced: HashSet<&str> = referenced
+    .iter()
+    .map(|r| r.split('.').next().unwrap_or(r))
+    .collect();
+  manifest
+    .variables
+    .iter()
+    .map(|vd| vd.name.as_str())
+    .filter(|name| referenced.contains(name))
+    .collect()
+}
+
+/// Checks that a condition's fields fit its operator.
+fn lint_condition_operator(condition: &Condition, context: &str) -> Vec<String> {
+  let mut issues = Vec::new();
+  let uses_values = matches!(condition.operator, ConditionOperator::In | ConditionOperator::NotIn);
+  if !uses_values && !condition.values.is_empty() {
+    issues.push(format!(
+      "{} sets `values`, which only `in`/`notIn` conditions use.",
+      context
+    ));
+  }
+  if condition.operator == ConditionOperator::Matches {
+    #[cfg(feature = "regex")]
+    if let Err(e) = Regex::new(&condition.value) {
+      issues.push(format!("{} has an invalid `matches` regex: {}", context, e));
+    }
+    #[cfg(not(feature = "regex"))]
+    issues.push(format!(
+      "{} uses `matches`, which requires the `regex` feature.",
+      context
+    ));
+  }
+  issues
+}
@@ -1,72 +1,241 @@
 // src/list.rs
-use crate::config::ScaffoldManifest;
+use crate::cli::{ListArgs, WhichArgs};
+use crate::config::{apply_manifest_overrides, ScaffoldManifest};
 use crate::error::SpawnError;
-use log::{debug, warn};
+use crate::registry::{self, TemplateRegistry};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
-pub fn run_list(templates_dir: &Path) -> Result<(), SpawnError> {
-  println!("Available Spawn Point Templates:");
-  println!("{:<25} | {:<15} | {}", "Name", "Language", "Description");
-  println!("{:-<25}-+-{:-<15}-+-{:-<50}", "", "", ""); // Separator
+pub fn run_list(args: ListArgs, registry: &TemplateRegistry) -> Result<(), SpawnError> {
+  // Missing/invalid directories just yield no templates (the scan warns)
+  let mut templates: Vec<_> = registry.templates().iter().collect();
+  // --tag filters are ANDed and compared case-insensitively
+  templates.retain(|(_, _, manifest)| {
+    args.tags.iter().all(|wanted| {
+      manifest.tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted))
+    })
+  });
+  if let Some(language) = &args.language {
+    templates.retain(|(_, _, manifest)| &manifest.language == language);
+  }
 
-  if !templates_dir.is_dir() {
-    warn!(
-      "Templates directory not found or is not a directory: {}",
-      templates_dir.display()
-    );
-    return Ok(()); // Or return an error? Let's allow running list even if empty/missing.
+  if args.json {
+    let entries: Vec<ListEntry> = templates
+      .iter()
+      .map(|(_, path, manifest)| ListEntry {
+        name: &manifest.name,
+        language: &manifest.language,
+        version: manifest.version.as_ref(),
+        description: &manifest.description,
+        tags: &manifest.tags,
+        path: args.show_path.then_some(path.as_path()),
+      })
+      .collect();
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+    return Ok(());
   }
+  // The Tags column only appears when a listed template has tags
+  let show_tags = templates.iter().any(|(_, _, manifest)| !manifest.tags.is_empty());
+  // Likewise the Version column, when a listed template is versioned
+  let show_versions = templates.iter().any(|(_, _, manifest)| manifest.version.is_some());
 
-  for entry_result in fs::read_dir(templates_dir)? {
-    let entry = match entry_result {
-      Ok(e) => e,
-      Err(e) => {
-        warn!("Failed to read entry in templates directory: {}", e);
-        continue;
-      }
-    };
+  println!("Available Spawn Point Templates:");
+  if args.show_path {
+    for root in registry.roots() {
+      println!("Templates root: {}", root.display());
+    }
+  }
+  let mut header = format!("{:<25} | {:<15} | ", "Name", "Language");
+  let mut separator = format!("{:-<25}-+-{:-<15}-+-", "", "");
+  if show_versions {
+    header.push_str(&format!("{:<10} | ", "Version"));
+    separator.push_str(&format!("{:-<10}-+-", ""));
+  }
+  if show_tags {
+    header.push_str(&format!("{:<20} | ", "Tags"));
+    separator.push_str(&format!("{:-<20}-+-", ""));
+  }
+  if args.show_path {
+    header.push_str(&format!("{:<50} | Path", "Description"));
+    separator.push_str(&format!("{:-<50}-+-{:-<40}", "", ""));
+  } else {
+    header.push_str("Description");
+    separator.push_str(&format!("{:-<50}", ""));
+  }
+  println!("{}", header);
+  println!("{}", separator);
 
-    let path = entry.path();
-    if path.is_dir() {
-      let manifest_path = path.join("scaffold.yaml");
-      if manifest_path.is_file() {
-        match read_and_parse_manifest(&manifest_path) {
-          Ok(manifest) => {
-            println!(
-              "{:<25} | {:<15} | {}",
-              manifest.name, manifest.language, manifest.description
-            );
-          }
-          Err(e) => {
-            warn!(
-              "Skipping directory '{}': Could not read or parse scaffold.yaml: {}",
-              path
-                .file_name()
-                .map_or_else(|| ".".into(), |n| n.to_string_lossy()),
-              e
-            );
-          }
-        }
-      } else {
-        debug!(
-          "Directory {} does not contain scaffold.yaml, skipping.",
-          path.display()
-        );
-      }
+  for (_dir_name, path, manifest) in &templates {
+    let mut row = format!("{:<25} | {:<15} | ", manifest.name, manifest.language);
+    if show_versions {
+      let version = manifest.version.as_ref().map(|v| v.to_string()).unwrap_or_default();
+      row.push_str(&format!("{:<10} | ", version));
+    }
+    if show_tags {
+      row.push_str(&format!("{:<20} | ", manifest.tags.join(", ")));
+    }
+    if args.show_path {
+      row.push_str(&format!("{:<50} | {}", manifest.description, path.display()));
+    } else {
+      row.push_str(&manifest.description);
     }
+    println!("{}", row);
   }
 
   Ok(())
 }
 
-pub(crate) fn read_and_parse_manifest(manifest_path: &Path) -> Result<ScaffoldManifest, SpawnError> {
+/// One template in `list --json` output.
+#[derive(Debug, Serialize)]
+struct ListEntry<'a> {
+  name: &'a str,
+  language: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  version: Option<&'a semver::Version>,
+  description: &'a str,
+  tags: &'a [String],
+  /// Only with `--show-path`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  path: Option<&'a Path>,
+}
+
+/// `which`: prints the absolute template directory and its `scaffold.yaml`, one per line.
+pub fn run_which(args: WhichArgs, registry: &TemplateRegistry) -> Result<(), SpawnError> {
+  let version = args
+    .template_version
+    .as_deref()
+    .map(registry::parse_version_selector)
+    .transpose()?;
+  let Some((_, template_path, _)) =
+    registry.find_version(&args.language, &args.template, version.as_ref())
+  else {
+    let version_note = version.map(|v| format!(" with version {}", v)).unwrap_or_default();
+    return Err(SpawnError::TemplateNotFound(format!(
+      "Template '{}' for language '{}'{} not found.",
+      args.template, args.language, version_note
+    )));
+  };
+  let template_dir = fs::canonicalize(template_path)?;
+  println!("{}", template_dir.display());
+  println!("{}", template_dir.join(registry::MANIFEST_FILE_NAME).display());
+  Ok(())
+}
+
+pub fn read_and_parse_manifest(manifest_path: &Path) -> Result<ScaffoldManifest, SpawnError> {
+  let content = fs::read_to_string(manifest_path).map_err(|e| SpawnError::ManifestReadError {
+    manifest_path: manifest_path.to_path_buf(),
+    source: e,
+  })?;
+  if let Ok(doc) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+    check_min_spawnpoint_version(&doc, manifest_path)?;
+  }
+  let manifest = serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+    manifest_path: manifest_path.to_path_buf(),
+    source: e,
+  })?;
+  check_distinct_placeholders(&manifest, manifest_path)?;
+  Ok(manifest)
+}
+
+/// Fails if two placeholders (variables', their transformations' or derived values') are the
+/// same string: substitution would silently keep only one of the values.
+fn check_distinct_placeholders(
+  manifest: &ScaffoldManifest,
+  manifest_path: &Path,
+) -> Result<(), SpawnError> {
+  let mut owners: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+  for var_def in &manifest.variables {
+    owners.entry(&var_def.placeholder_value).or_default().push(format!("'{}'", var_def.name));
+    let mut transformations: Vec<_> = var_def.transformations.iter().collect();
+    transformations.sort_by_key(|(transformation, _)| transformation.name());
+    for (transformation, placeholder) in transformations {
+      owners
+        .entry(placeholder)
+        .or_default()
+        .push(format!("'{}' ({})", var_def.name, transformation.name()));
+    }
+  }
+  // Derived entries sharing a name are alternatives (the first matching one applies)
+  for derived_def in &manifest.derived {
+    let owner = format!("derived value '{}'", derived_def.name);
+    let users = owners.entry(&derived_def.placeholder_value).or_default();
+    if !users.contains(&owner) {
+      users.push(owner);
+    }
+  }
+
+  // Empty placeholders are reported by `validate-manifest`
+  let conflicts: Vec<String> = owners
+    .iter()
+    .filter(|(placeholder, users)| !placeholder.is_empty() && users.len() > 1)
+    .map(|(placeholder, users)| format!("'{}' is used by {}", placeholder, users.join(" and ")))
+    .collect();
+  if conflicts.is_empty() {
+    Ok(())
+  } else {
+    Err(SpawnError::DuplicatePlaceholders {
+      manifest_path: manifest_path.to_path_buf(),
+      conflicts: conflicts.join("; "),
+    })
+  }
+}
+
+/// Fails if the manifest's `minSpawnpointVersion` is newer than this binary. Runs on the raw
+/// YAML so a manifest using fields this version doesn't know yet reports the version
+/// mismatch instead of an unknown-field error.
+fn check_min_spawnpoint_version(
+  doc: &serde_yaml::Value,
+  manifest_path: &Path,
+) -> Result<(), SpawnError> {
+  let Some(value) = doc.get("minSpawnpointVersion") else {
+    return Ok(());
+  };
+  let invalid = |reason: String| SpawnError::InvalidMinSpawnpointVersion {
+    manifest_path: manifest_path.to_path_buf(),
+    value: serde_yaml::to_string(value).unwrap_or_default().trim().to_string(),
+    reason,
+  };
+  let required = value
+    .as_str()
+    .ok_or_else(|| invalid("expected a version string like \"1.2.0\"".to_string()))?;
+  // `>=1.2` also accepts partial versions
+  let requirement = semver::VersionReq::parse(&format!(">={}", required.trim()))
+    .map_err(|e| invalid(e.to_string()))?;
+  let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))
+    .expect("CARGO_PKG_VERSION is valid semver");
+  if requirement.matches(&current) {
+    Ok(())
+  } else {
+    Err(SpawnError::SpawnpointTooOld {
+      manifest_path: manifest_path.to_path_buf(),
+      required: required.trim().to_string(),
+      current: current.to_string(),
+    })
+  }
+}
+
+/// Like `read_and_parse_manifest`, but applies `--set` overrides to the raw YAML first.
+pub(crate) fn read_manifest_with_overrides(
+  manifest_path: &Path,
+  overrides: &[String],
+) -> Result<ScaffoldManifest, SpawnError> {
   let content = fs::read_to_string(manifest_path).map_err(|e| SpawnError::ManifestReadError {
     manifest_path: manifest_path.to_path_buf(),
     source: e,
   })?;
-  serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+  let mut doc: serde_yaml::Value =
+    serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+      manifest_path: manifest_path.to_path_buf(),
+      source: e,
+    })?;
+  check_min_spawnpoint_version(&doc, manifest_path)?;
+  apply_manifest_overrides(&mut doc, overrides)?;
+  let manifest = serde_yaml::from_value(doc).map_err(|e| SpawnError::ManifestParseError {
     manifest_path: manifest_path.to_path_buf(),
     source: e,
-  })
+  })?;
+  check_distinct_placeholders(&manifest, manifest_path)?;
+  Ok(manifest)
 }
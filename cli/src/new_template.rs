@@ -0,0 +1,153 @@
+// src/new_template.rs
+//! `new-template`: creates a starter template (manifest plus one sample file) for authors
+//! to build on. The manifest is emitted from the `ScaffoldManifest` structs, so it always parses.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use heck::ToSnakeCase;
+
+use crate::cli::NewTemplateArgs;
+use crate::config::{
+  CaseTransformation, ScaffoldManifest, ValidationConfig, ValidationStep, VariableDefinition, VariableType,
+};
+use crate::error::SpawnError;
+
+const SAMPLE_FILE_NAME: &str = "README.md";
+const SAMPLE_FILE_CONTENT: &str = "# --project-name--
+
+Welcome to --project-name--! Every `--project-name--` in this template is replaced with the
+`projectName` variable, and `--ProjectNamePascal--` with its PascalCase form.
+";
+
+/// Creates `<templates_dir>/<name in snake_case>/` with a starter `scaffold.yaml` and sample file.
+pub fn run_new_template(args: NewTemplateArgs, templates_dir: &Path) -> Result<PathBuf, SpawnError> {
+  let dir_name = args.name.to_snake_case();
+  if dir_name.is_empty() {
+    return Err(SpawnError::NewTemplate {
+      path: templates_dir.to_path_buf(),
+      reason: format!("'{}' does not make a usable directory name", args.name),
+    });
+  }
+  let template_dir = templates_dir.join(&dir_name);
+  if template_dir.exists() {
+    return Err(SpawnError::NewTemplate {
+      path: template_dir,
+      reason: "already exists".to_string(),
+    });
+  }
+
+  let manifest_yaml = render_manifest(&args.name, &args.language)?;
+  fs::create_dir_all(&template_dir).map_err(|e| SpawnError::OutputDirCreation {
+    path: template_dir.clone(),
+    source: e,
+  })?;
+  fs::write(template_dir.join("scaffold.yaml"), manifest_yaml)?;
+  fs::write(template_dir.join(SAMPLE_FILE_NAME), SAMPLE_FILE_CONTENT)?;
+
+  println!("Created template '{}' in {}", args.name, template_dir.display());
+  println!(
+    "Next: edit scaffold.yaml, add your files, then run `spawnpoint validate {} \"{}\"`.",
+    args.language, args.name
+  );
+  Ok(template_dir)
+}
+
+/// Starter `scaffold.yaml`: one variable with one transformation, and a commented-out
+/// validation block to enable once the template has something to check.
+fn render_manifest(name: &str, language: &str) -> Result<String, SpawnError> {
+  // Parse the required fields only, so every optional field holds its real default
+  let mut required = serde_yaml::Mapping::new();
+  required.insert("name".into(), name.into());
+  required.insert("description".into(), "TODO: describe what this template generates".into());
+  required.insert("language".into(), language.into());
+  required.insert("variables".into(), serde_yaml::Value::Sequence(Vec::new()));
+  let bare: ScaffoldManifest = serde_yaml::from_value(serde_yaml::Value::Mapping(required))?;
+  let mut manifest = bare.clone();
+  manifest.variables.push(VariableDefinition {
+    name: "projectName".to_string(),
+    prompt: Some("Project name:".to_string()),
+    placeholder_value: "--project-name--".to_string(),
+    var_type: VariableType::String,
+    sensitive: false,
+    keychain_key: None,
+    default: Some("my-project".to_string()),
+    transformations: HashMap::from([(
+      CaseTransformation::PascalCase,
+      "--ProjectNamePascal--".to_string(),
+    )]),
+    validation_regex: None,
+    when: None,
+    min: None,
+    max: None,
+    license_holder: None,
+    choices: Vec::new(),
+    separator: None,
+    generator: None,
+  });
+  manifest.exclude = vec![".git".to_string(), ".DS_Store".to_string()];
+
+  // Leave out optional fields still at their defaults; they only add noise for a new author
+  let bare_doc = serde_yaml::to_value(&bare)?;
+  let mut doc = serde_yaml::to_value(&manifest)?;
+  if let (serde_yaml::Value::Mapping(map), serde_yaml::Value::Mapping(defaults)) =
+    (&mut doc, &bare_doc)
+  {
+    map.retain(|key, value| {
+      matches!(key.as_str(), Some("name" | "description" | "language" | "variables"))
+        || defaults.get(key) != Some(&*value)
+    });
+  }
+  prune_empty(&mut doc);
+
+  let validation = ValidationConfig {
+    test_variables: HashMap::from([("projectName".to_string(), "test-project".to_string())]),
+    steps: vec![ValidationStep {
+      name: "Check README".to_string(),
+      command: "grep -q test-project README.md".to_string(),
+      args: Vec::new(),
+      shell: None,
+      working_dir: None,
+      env: HashMap::new(),
+      clean_env: false,
+      timeout_secs: Some(30),
+      ignore_errors: false,
+      ignore: Vec::new(),
+      always_run: false,
+      check_stderr: false,
+      tags: Vec::new(),
+      group: None,
+    }],
+    ..ValidationConfig::default()
+  };
+  let mut validation_doc = serde_yaml::to_value(HashMap::from([("validation", validation)]))?;
+  prune_empty(&mut validation_doc);
+  let commented_validation: String = serde_yaml::to_string(&validation_doc)?
+    .lines()
+    .map(|line| format!("# {}\n", line))
+    .collect();
+
+  Ok(format!(
+    "{}\n# --- Validation (run with `spawnpoint validate`) ---\n\
+     # Uncomment to generate a test project from testVariables and run the steps in it.\n{}",
+    serde_yaml::to_string(&doc)?,
+    commented_validation
+  ))
+}
+
+/// Recursively drops nulls, `false` and empty lists/maps from mappings.
+fn prune_empty(value: &mut serde_yaml::Value) {
+  match value {
+    serde_yaml::Value::Mapping(map) => {
+      map.retain(|_, v| {
+        prune_empty(v);
+        !matches!(v, serde_yaml::Value::Null | serde_yaml::Value::Bool(false))
+          && !matches!(v, serde_yaml::Value::Sequence(s) if s.is_empty())
+          && !matches!(v, serde_yaml::Value::Mapping(m) if m.is_empty())
+      });
+    }
+    serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(prune_empty),
+    _ => {}
+  }
+}
@@ -0,0 +1,291 @@
+// src/prompt.rs
+//! Asking the user for variable values. `generate` goes through a `VariableProvider`, so
+//! embedders can collect values their own way (a GUI, a web form, a config file) while the
+//! CLI uses `DialoguerProvider`.
+
+use std::env;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use dialoguer::console::{self, Term};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use dialoguer::{Confirm, Input, MultiSelect, Password, Select};
+
+use crate::cli::ColorChoice;
+use crate::config::{VariableDefinition, VariableType};
+use crate::error::SpawnError;
+use crate::generate::{parse_multi_select_value, parse_provided_value};
+use crate::utils::SensitiveValue;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+/// Turns colors on or off for prompts (`theme`) and the progress bar; returns whether they
+/// are on, for the logger. `Auto` follows https://no-color.org and whether stderr is a terminal.
+pub fn init_colors(choice: ColorChoice) -> bool {
+  let enabled = match choice {
+    ColorChoice::Always => true,
+    ColorChoice::Never => false,
+    ColorChoice::Auto => {
+      env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && Term::stderr().features().colors_supported()
+    }
+  };
+  // indicatif styles through the stdout setting, dialoguer through the stderr one
+  console::set_colors_enabled(enabled);
+  console::set_colors_enabled_stderr(enabled);
+  enabled
+}
+
+/// Theme for every dialoguer prompt: `ColorfulTheme`, or plain text when colors are off.
+pub(crate) fn theme() -> &'static dyn Theme {
+  static COLORFUL: OnceLock<ColorfulTheme> = OnceLock::new();
+  if console::colors_enabled_stderr() {
+    COLORFUL.get_or_init(ColorfulTheme::default)
+  } else {
+    &SimpleTheme
+  }
+}
+
+/// Supplies values for variables that were not given up front (`--var`, or the map passed
+/// to `generate_project_with`). `prompt` is the manifest's prompt text for the variable.
+/// Returned values are validated like `--var` values, so an invalid answer is an error.
+pub trait VariableProvider {
+  /// Free text: `string`, `integer` and `float` variables.
+  fn prompt_string(
+    &mut self,
+    var: &VariableDefinition,
+    prompt: &str,
+    default: Option<&str>,
+  ) -> Result<String, SpawnError>;
+
+  /// Yes/no: `boolean` variables, and follow-up questions such as saving to the keychain.
+  fn prompt_bool(&mut self, var: &VariableDefinition, prompt: &str, default: bool) -> Result<bool, SpawnError>;
+
+  /// One of `choices`, returned as its index: `choice` and `license` variables.
+  fn prompt_choice(
+    &mut self,
+    var: &VariableDefinition,
+    prompt: &str,
+    choices: &[String],
+    default: usize,
+  ) -> Result<usize, SpawnError>;
+
+  /// Any number of `choices`, returned as indices: `multiSelect` variables.
+  /// By default asks `prompt_string` for the selection joined with the variable's separator.
+  fn prompt_multi_choice(
+    &mut self,
+    var: &VariableDefinition,
+    prompt: &str,
+    choices: &[String],
+    defaults: &[bool],
+  ) -> Result<Vec<usize>, SpawnError> {
+    let default = choices
+      .iter()
+      .zip(defaults)
+      .filter(|(_, selected)| **selected)
+      .map(|(choice, _)| choice.as_str())
+      .collect::<Vec<_>>()
+      .join(var.separator());
+    let raw = self.prompt_string(var, prompt, Some(&default))?;
+    let selection = parse_multi_select_value(var, &raw)?;
+    let selected: Vec<&str> = selection.split(var.separator()).collect();
+    Ok((0..choices.len()).filter(|i| selected.contains(&choices[*i].as_str())).collect())
+  }
+
+  /// Hidden input for `sensitive` variables. By default the same as `prompt_string`.
+  fn prompt_secret(&mut self, var: &VariableDefinition, prompt: &str) -> Result<SensitiveValue, SpawnError> {
+    self.prompt_string(var, prompt, None).map(SensitiveValue::new)
+  }
+}
+
+/// Terminal prompts via `dialoguer`, as used by the CLI.
+#[derive(Debug, Default)]
+pub struct DialoguerProvider {
+  /// Give up on a prompt after this long, failing with `SpawnError::PromptTimeout`
+  pub timeout: Option<Duration>,
+}
+
+impl DialoguerProvider {
+  pub fn new(timeout: Option<Duration>) -> Self {
+    DialoguerProvider { timeout }
+  }
+}
+
+impl VariableProvider for DialoguerProvider {
+  fn prompt_string(
+    &mut self,
+    var: &VariableDefinition,
+    prompt: &str,
+    default: Option<&str>,
+  ) -> Result<String, SpawnError> {
+    // Re-ask until the input is valid; the caller validates once more (with strict warnings)
+    #[cfg(feature = "regex")]
+    let regex = match (&var.var_type, &var.validation_regex) {
+      (VariableType::String, Some(regex_str)) => Regex::new(regex_str).ok(),
+      _ => None,
+    };
+    let validation_def = var.clone();
+    let prompt_text = prompt.to_string();
+    let default_owned = default.map(str::to_string);
+    prompt_with_timeout(self.timeout, prompt, move || {
+      let mut input = Input::<String>::with_theme(theme())
+        .with_prompt(prompt_text)
+        .validate_with(move |input: &String| -> Result<(), String> {
+          if validation_def.var_type == VariableType::String {
+            #[cfg(feature = "regex")]
+            if let Some(regex) = &regex {
+              if !regex.is_match(input) {
+                return Err(format!("Input must match regex: {}", regex.as_str()));
+              }
+            }
+            return Ok(());
+          }
+          parse_provided_value(&validation_def, input, false)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+        });
+      if let Some(default_val) = default_owned {
+        input = input.default(default_val);
+      }
+      input.interact_text()
+    })
+  }
+
+  fn prompt_bool(&mut self, _var: &VariableDefinition, prompt: &str, default: bool) -> Result<bool, SpawnError> {
+    let prompt_text = prompt.to_string();
+    prompt_with_timeout(self.timeout, prompt, move || {
+      Confirm::with_theme(theme())
+        .with_prompt(prompt_text)
+        .default(default)
+        .interact()
+    })
+  }
+
+  fn prompt_choice(
+    &mut self,
+    _var: &VariableDefinition,
+    prompt: &str,
+    choices: &[String],
+    default: usize,
+  ) -> Result<usize, SpawnError> {
+    let prompt_text = prompt.to_string();
+    let items = choices.to_vec();
+    prompt_with_timeout(self.timeout, prompt, move || {
+      Select::with_theme(theme())
+        .with_prompt(prompt_text)
+        .items(&items)
+        .default(default)
+        .interact()
+    })
+  }
+
+  fn prompt_multi_choice(
+    &mut self,
+    _var: &VariableDefinition,
+    prompt: &str,
+    choices: &[String],
+    defaults: &[bool],
+  ) -> Result<Vec<usize>, SpawnError> {
+    let prompt_text = prompt.to_string();
+    let items = choices.to_vec();
+    let defaults = defaults.to_vec();
+    prompt_with_timeout(self.timeout, prompt, move || {
+      MultiSelect::with_theme(theme())
+        .with_prompt(prompt_text)
+        .items(&items)
+        .defaults(&defaults)
+        .interact()
+    })
+  }
+
+  fn prompt_secret(&mut self, _var: &VariableDefinition, prompt: &str) -> Result<SensitiveValue, SpawnError> {
+    let prompt_text = prompt.to_string();
+    prompt_with_timeout(self.timeout, prompt, move || {
+      Password::with_theme(theme())
+        .with_prompt(prompt_text)
+        .interact()
+    })
+    .map(SensitiveValue::new)
+  }
+}
+
+/// Runs an interactive prompt, giving up after `timeout` if one is set.
+/// The prompt runs on its own thread, so it must own everything it needs. A prompt blocked
+/// on a key read can't be interrupted, so expiry is always `SpawnError::PromptTimeout`, which
+/// ends the run: falling back to a default would leave that thread reading the input meant
+/// for the next prompt. The terminal is restored first (dialoguer reads keys in raw mode
+/// with the cursor hidden).
+pub(crate) fn prompt_with_timeout<T, F>(
+  timeout: Option<Duration>,
+  label: &str,
+  prompt: F,
+) -> Result<T, SpawnError>
+where
+  T: Send + 'static,
+  F: FnOnce() -> Result<T, dialoguer::Error> + Send + 'static,
+{
+  let Some(timeout) = timeout else {
+    return Ok(prompt()?);
+  };
+
+  let terminal = SavedTerminal::save();
+  let (tx, rx) = mpsc::channel();
+  thread::spawn(move || {
+    let _ = tx.send(prompt()); // Receiver may be gone after a timeout
+  });
+
+  match rx.recv_timeout(timeout) {
+    Ok(result) => Ok(result?),
+    Err(RecvTimeoutError::Timeout) => {
+      terminal.restore();
+      let stderr = Term::stderr();
+      let _ = stderr.show_cursor();
+      let _ = stderr.write_line("");
+      Err(SpawnError::PromptTimeout {
+        prompt: label.to_string(),
+        secs: timeout.as_secs(),
+      })
+    }
+    Err(RecvTimeoutError::Disconnected) => Err(SpawnError::GenerationError(format!(
+      "Prompt '{}' ended unexpectedly.",
+      label
+    ))),
+  }
+}
+
+/// Terminal settings of stdin from before a prompt, put back when the prompt times out.
+#[cfg(unix)]
+struct SavedTerminal(Option<libc::termios>);
+
+#[cfg(unix)]
+impl SavedTerminal {
+  fn save() -> Self {
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr only writes to the struct it is given; it is read only on success
+    let saved = unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } == 0;
+    SavedTerminal(saved.then(|| unsafe { termios.assume_init() }))
+  }
+
+  fn restore(&self) {
+    if let Some(termios) = &self.0 {
+      // SAFETY: `termios` came from tcgetattr on the same descriptor
+      unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios) };
+    }
+  }
+}
+
+/// The Windows console leaves raw mode with the process, so there is nothing to restore.
+#[cfg(not(unix))]
+struct SavedTerminal;
+
+#[cfg(not(unix))]
+impl SavedTerminal {
+  fn save() -> Self {
+    SavedTerminal
+  }
+
+  fn restore(&self) {}
+}
@@ -0,0 +1,443 @@
+// src/registry.rs
+//! `TemplateRegistry`: every template under the templates directories, found and parsed once
+//! and then shared by the commands that need to look templates up (list, generate, validate).
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+use rayon::prelude::*;
+use semver::{Version, VersionReq};
+use serde_yaml::{Mapping, Value};
+use tempfile::{Builder, TempDir};
+use walkdir::WalkDir;
+
+use crate::config::ScaffoldManifest;
+use crate::error::SpawnError;
+use crate::list::read_and_parse_manifest;
+use crate::utils::{self, ExcludeMatcher};
+
+pub const MANIFEST_FILE_NAME: &str = "scaffold.yaml";
+
+/// A template ready to generate from. For a template with `extends`, `path` is a temporary
+/// directory holding its base templates with its own files layered on top, plus the merged
+/// `scaffold.yaml`; it is removed when this is dropped. Otherwise `path` is the template itself.
+#[derive(Debug)]
+pub struct ResolvedTemplate {
+  pub path: PathBuf,
+  pub manifest: ScaffoldManifest,
+  _composed_dir: Option<TempDir>,
+}
+
+/// Parsed templates of one or more templates directories, each sorted by directory name.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRegistry {
+  roots: Vec<PathBuf>,
+  /// (directory name, template path, manifest)
+  templates: Vec<(String, PathBuf, ScaffoldManifest)>,
+  /// (directory name, error) for directories whose manifest could not be read or parsed
+  invalid: Vec<(String, String)>,
+}
+
+impl TemplateRegistry {
+  /// Parses the manifest of every subdirectory of `templates_dir` that has a `scaffold.yaml`;
+  /// other subdirectories are skipped. A missing directory yields an empty registry (with a
+  /// warning), unreadable manifests are skipped with a warning, and a template shadowed by an
+  /// earlier one with the same name and language is a (strict) warning.
+  pub fn scan(templates_dir: &Path, strict_warnings: bool) -> Result<Self, SpawnError> {
+    let mut registry = TemplateRegistry {
+      roots: vec![templates_dir.to_path_buf()],
+      ..TemplateRegistry::default()
+    };
+    if !templates_dir.is_dir() {
+      warn!(
+        "Templates directory not found or is not a directory: {}",
+        templates_dir.display()
+      );
+      return Ok(registry);
+    }
+
+    // Collect candidate directories first so results are ordered deterministically
+    let mut candidate_dirs = Vec::new();
+    for entry_result in fs::read_dir(templates_dir)? {
+      let entry = match entry_result {
+        Ok(e) => e,
+        Err(e) => {
+          warn!("Failed to read entry in templates directory: {}", e);
+          continue;
+        }
+      };
+
+      let path = entry.path();
+      if path.is_dir() {
+        if path.join(MANIFEST_FILE_NAME).is_file() {
+          candidate_dirs.push(path);
+        } else {
+          debug!(
+            "Directory {} does not contain {}.",
+            path.display(),
+            MANIFEST_FILE_NAME
+          );
+        }
+      }
+    }
+    candidate_dirs.sort();
+
+    // Parse manifests in parallel; collect() preserves the sorted order
+    let parsed: Vec<_> = candidate_dirs
+      .into_par_iter()
+      .map(|path| {
+        let manifest_result = read_and_parse_manifest(&path.join(MANIFEST_FILE_NAME));
+        (path, manifest_result)
+      })
+      .collect();
+
+    for (path, manifest_result) in parsed {
+      let dir_name = path
+        .file_name()
+        .map_or_else(|| ".".into(), |n| n.to_string_lossy().to_string());
+
+      match manifest_result {
+        Ok(manifest) => {
+          let existing = registry.templates.iter().find(|(_, _, m)| same_template(m, &manifest));
+          if let Some((existing_dir, _, _)) = existing {
+            utils::strict_warn(
+              strict_warnings,
+              format!(
+                "Template '{}' (lang: '{}'{}) in '{}' is shadowed by a template with the same name in '{}'.",
+                manifest.name,
+                manifest.language,
+                version_label(&manifest),
+                dir_name,
+                existing_dir
+              ),
+            )?;
+          }
+          registry.templates.push((dir_name, path, manifest));
+        }
+        Err(e) => {
+          warn!(
+            "Skipping directory '{}': Could not read or parse {}: {}",
+            dir_name, MANIFEST_FILE_NAME, e
+          );
+          registry.invalid.push((dir_name, e.to_string()));
+        }
+      }
+    }
+    registry.group_versions();
+    Ok(registry)
+  }
+
+  /// Scans each of `templates_dirs` (see `scan`) and merges them in order: a template in a
+  /// later directory overrides one with the same name and language in an earlier directory,
+  /// taking its place in the list. Each override is a (strict) warning, so authors notice.
+  pub fn scan_all(templates_dirs: &[PathBuf], strict_warnings: bool) -> Result<Self, SpawnError> {
+    let mut merged = TemplateRegistry::default();
+    for templates_dir in templates_dirs {
+      let scanned = TemplateRegistry::scan(templates_dir, strict_warnings)?;
+      for template in scanned.templates {
+        let existing = merged
+          .templates
+          .iter()
+          .position(|(_, _, manifest)| same_template(manifest, &template.2));
+        match existing {
+          Some(index) => {
+            utils::strict_warn(
+              strict_warnings,
+              format!(
+                "Template '{}' (lang: '{}'{}) in '{}' is overridden by the one in '{}'.",
+                template.2.name,
+                template.2.language,
+                version_label(&template.2),
+                merged.templates[index].1.display(),
+                template.1.display()
+              ),
+            )?;
+            merged.templates[index] = template;
+          }
+          None => merged.templates.push(template),
+        }
+      }
+      merged.invalid.extend(scanned.invalid);
+      merged.roots.extend(scanned.roots);
+    }
+    merged.group_versions();
+    Ok(merged)
+  }
+
+  /// Moves every version of a template next to the first one found, highest version first
+  /// (unversioned last), so lookups by name get the highest version.
+  fn group_versions(&mut self) {
+    let mut first_seen: HashMap<(String, String), usize> = HashMap::new();
+    for (index, (_, _, manifest)) in self.templates.iter().enumerate() {
+      first_seen
+        .entry((manifest.language.clone(), manifest.name.clone()))
+        .or_insert(index);
+    }
+    self.templates.sort_by_cached_key(|(_, _, manifest)| {
+      (
+        first_seen[&(manifest.language.clone(), manifest.name.clone())],
+        Reverse(manifest.version.clone()),
+      )
+    });
+  }
+
+  /// The scanned templates directories, in precedence order (later ones win).
+  pub fn roots(&self) -> &[PathBuf] {
+    &self.roots
+  }
+
+  /// Templates with a valid manifest: (directory name, template path, manifest).
+  pub fn templates(&self) -> &[(String, PathBuf, ScaffoldManifest)] {
+    &self.templates
+  }
+
+  /// Directories whose manifest failed to parse: (directory name, error).
+  pub fn invalid(&self) -> &[(String, String)] {
+    &self.invalid
+  }
+
+  /// The highest version of the template with this language and manifest name.
+  pub fn find(&self, language: &str, name: &str) -> Option<&(String, PathBuf, ScaffoldManifest)> {
+    self.find_version(language, name, None)
+  }
+
+  /// The highest version of the template with this language and manifest name that matches
+  /// `version` (any version when `None`).
+  pub fn find_version(
+    &self,
+    language: &str,
+    name: &str,
+    version: Option<&VersionReq>,
+  ) -> Option<&(String, PathBuf, ScaffoldManifest)> {
+    self.templates.iter().find(|(_, _, manifest)| {
+      manifest.language == language && manifest.name == name && version_matches(manifest, version)
+    })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.templates.is_empty()
+  }
+
+  pub fn into_templates(self) -> Vec<(String, PathBuf, ScaffoldManifest)> {
+    self.templates
+  }
+
+  /// Resolves `extends` for the template at `template_path`: each base (looked up by name
+  /// in the same language, and possibly extending another) is laid down first, then the
+  /// template's own files, a later layer's file replacing an earlier one at the same path.
+  /// Each layer's `exclude` applies to its own files. Manifests merge the same way: keys the
+  /// template sets replace the base's, except `variables` (merged by name) and
+  /// `conditionalPaths` (merged by path).
+  pub fn resolve(
+    &self,
+    template_path: &Path,
+    manifest: &ScaffoldManifest,
+  ) -> Result<ResolvedTemplate, SpawnError> {
+    if manifest.extends.is_none() {
+      return Ok(ResolvedTemplate {
+        path: template_path.to_path_buf(),
+        manifest: manifest.clone(),
+        _composed_dir: None,
+      });
+    }
+
+    // Base-most first, ending with the template itself
+    let mut layers: Vec<(&Path, &ScaffoldManifest)> = vec![(template_path, manifest)];
+    let mut seen: HashSet<&Path> = HashSet::from([template_path]);
+    while let Some(base_name) = layers[0].1.extends.as_deref() {
+      let child = layers[0].1;
+      let extends_error = |reason: String| SpawnError::TemplateExtends {
+        template: child.name.clone(),
+        base: base_name.to_string(),
+        reason,
+      };
+      let (_, base_path, base_manifest) = self.find(&child.language, base_name).ok_or_else(|| {
+        extends_error(format!("no template with that name for language '{}'", child.language))
+      })?;
+      if !seen.insert(base_path) {
+        return Err(extends_error("templates extend each other in a cycle".to_string()));
+      }
+      layers.insert(0, (base_path, base_manifest));
+    }
+
+    let composed_dir = Builder::new().prefix("spawnpoint_extends_").tempdir()?;
+    let mut merged_doc = Value::Mapping(Mapping::new());
+    for (layer_path, layer_manifest) in &layers {
+      debug!("Laying down template layer {}", layer_path.display());
+      copy_layer(layer_path, composed_dir.path(), layer_manifest)?;
+      let manifest_path = layer_path.join(MANIFEST_FILE_NAME);
+      let content = fs::read_to_string(&manifest_path).map_err(|e| SpawnError::ManifestReadError {
+        manifest_path: manifest_path.clone(),
+        source: e,
+      })?;
+      let layer_doc: Value = serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+        manifest_path: manifest_path.clone(),
+        source: e,
+      })?;
+      merge_manifest_doc(&mut merged_doc, layer_doc);
+    }
+    if let Value::Mapping(map) = &mut merged_doc {
+      map.remove("extends");
+      // A version belongs to the template itself, never inherited from a base
+      match &manifest.version {
+        Some(version) => map.insert("version".into(), version.to_string().into()),
+        None => map.remove("version"),
+      };
+    }
+    let manifest_path = composed_dir.path().join(MANIFEST_FILE_NAME);
+    fs::write(&manifest_path, serde_yaml::to_string(&merged_doc)?)?;
+    let composed_manifest = read_and_parse_manifest(&manifest_path)?;
+    info!(
+      "Template '{}' is layered on {} (base-most first)",
+      manifest.name,
+      layers[..layers.len() - 1]
+        .iter()
+        .map(|(_, m)| format!("'{}'", m.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+    );
+
+    Ok(ResolvedTemplate {
+      path: composed_dir.path().to_path_buf(),
+      manifest: composed_manifest,
+      _composed_dir: Some(composed_dir),
+    })
+  }
+}
+
+/// Parses `--template-version`: an exact version (`2.1.0`) or a semver requirement (`^2`, `~2.1`).
+pub fn parse_version_selector(selector: &str) -> Result<VersionReq, SpawnError> {
+  let selector = selector.trim();
+  let requirement = match Version::parse(selector) {
+    Ok(exact) => VersionReq::parse(&format!("={}", exact)),
+    Err(_) => VersionReq::parse(selector),
+  };
+  requirement.map_err(|e| {
+    SpawnError::GenerationError(format!("Invalid --template-version '{}': {}", selector, e))
+  })
+}
+
+/// Whether `manifest` satisfies a version selector; an unversioned template matches only `None`.
+pub fn version_matches(manifest: &ScaffoldManifest, version: Option<&VersionReq>) -> bool {
+  match version {
+    None => true,
+    Some(requirement) => manifest.version.as_ref().is_some_and(|v| requirement.matches(v)),
+  }
+}
+
+/// `manifest.name` with its version, e.g. `rust_cli 2.1.0`, for prompts and listings.
+pub fn display_name(manifest: &ScaffoldManifest) -> String {
+  match &manifest.version {
+    Some(version) => format!("{} {}", manifest.name, version),
+    None => manifest.name.clone(),
+  }
+}
+
+/// Same name, language and version: one shadows or overrides the other.
+fn same_template(a: &ScaffoldManifest, b: &ScaffoldManifest) -> bool {
+  a.language == b.language && a.name == b.name && a.version == b.version
+}
+
+fn version_label(manifest: &ScaffoldManifest) -> String {
+  manifest
+    .version
+    .as_ref()
+    .map(|version| format!(", version: '{}'", version))
+    .unwrap_or_default()
+}
+
+/// Copies one `extends` layer into the composed directory as is (no substitution), skipping
+/// its manifest and `exclude`d entries and replacing files from earlier layers.
+fn copy_layer(layer_path: &Path, composed_dir: &Path, manifest: &ScaffoldManifest) -> Result<(), SpawnError> {
+  let exclude = ExcludeMatcher::new(&manifest.exclude)?;
+  let mut walker = WalkDir::new(layer_path).min_depth(1).into_iter();
+  while let Some(entry) = walker.next() {
+    let entry = entry.map_err(|e| SpawnError::Io(e.into()))?;
+    let Ok(relative_path) = entry.path().strip_prefix(layer_path) else {
+      continue;
+    };
+    if exclude.is_excluded(&utils::path_key(relative_path), entry.file_type().is_dir()) {
+      if entry.file_type().is_dir() {
+        walker.skip_current_dir();
+      }
+      continue;
+    }
+    let target = composed_dir.join(relative_path);
+    if entry.file_type().is_dir() {
+      fs::create_dir_all(&target)?;
+    } else if entry.file_type().is_file() && relative_path != Path::new(MANIFEST_FILE_NAME) {
+      fs::copy(entry.path(), &target)?;
+    }
+  }
+  Ok(())
+}
+
+/// Overlays a layer's raw manifest on the merged one: its keys replace the merged ones,
+/// except `variables` (replaced by `name`, new ones appended) and `conditionalPaths`
+/// (merged by path).
+fn merge_manifest_doc(merged: &mut Value, layer: Value) {
+  let (Value::Mapping(merged), Value::Mapping(layer)) = (merged, layer) else {
+    return;
+  };
+  for (key, value) in layer {
+    match (key.as_str(), merged.get_mut(&key), value) {
+      (Some("variables"), Some(Value::Sequence(variables)), Value::Sequence(layer_variables)) => {
+        for variable in layer_variables {
+          let existing = variables
+            .iter_mut()
+            .find(|v| v.get("name").is_some() && v.get("name") == variable.get("name"));
+          match existing {
+            Some(slot) => *slot = variable,
+            None => variables.push(variable),
+          }
+        }
+      }
+      (Some("conditionalPaths"), Some(Value::Mapping(paths)), Value::Mapping(layer_paths)) => {
+        paths.extend(layer_paths);
+      }
+      (_, _, value) => {
+        merged.insert(key, value);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scan_discovers_every_valid_template_at_any_parse_concurrency() {
+    let templates = tempfile::tempdir().unwrap();
+    let mut expected = Vec::new();
+    for i in 0..60 {
+      let dir = templates.path().join(format!("template-{:02}", i));
+      fs::create_dir_all(&dir).unwrap();
+      match i % 10 {
+        // Broken manifest: skipped and reported as invalid
+        3 => fs::write(dir.join(MANIFEST_FILE_NAME), "name: [unterminated").unwrap(),
+        // No manifest: not a template
+        7 => fs::write(dir.join("README.md"), "not a template").unwrap(),
+        _ => {
+          fs::write(
+            dir.join(MANIFEST_FILE_NAME),
+            format!("name: T{i}\ndescription: Template {i}\nlanguage: test\nvariables: []\n"),
+          )
+          .unwrap();
+          expected.push(format!("template-{:02}", i));
+        }
+      }
+    }
+
+    for threads in [1, 8] {
+      let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+      let registry = pool.install(|| TemplateRegistry::scan(templates.path(), false)).unwrap();
+      let found: Vec<_> = registry.templates().iter().map(|(dir, _, _)| dir.clone()).collect();
+      assert_eq!(found, expected, "with {} thread(s)", threads);
+      assert_eq!(registry.invalid().len(), 6, "with {} thread(s)", threads);
+    }
+  }
+}
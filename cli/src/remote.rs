@@ -0,0 +1,85 @@
+// src/remote.rs
+//! Templates pulled from a git repository (`--template-repo`).
+//! Repositories are shallow-cloned into the user cache directory, keyed by a hash of the
+//! URL, and reused on later runs until `--refresh` pulls the latest commit.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use duct::cmd;
+use log::{debug, info};
+
+use crate::error::SpawnError;
+use crate::state::sha256_hex;
+
+/// Returns the local checkout of `url`, cloning it on first use and updating it when
+/// `refresh` is set.
+pub fn fetch_template_repo(url: &str, refresh: bool) -> Result<PathBuf, SpawnError> {
+  let cache_root = ProjectDirs::from("com", "excsn", "spawnpoint")
+    .map(|p| p.cache_dir().to_path_buf())
+    .unwrap_or_else(|| std::env::temp_dir().join("spawnpoint"));
+  let repos_dir = cache_root.join("template-repos");
+  let checkout = repos_dir.join(&sha256_hex(url.as_bytes())[..16]);
+
+  if checkout.join(".git").is_dir() {
+    if refresh {
+      info!("Refreshing template repository {}...", url);
+      run_git(url, Some(&checkout), &["fetch", "--quiet", "--depth", "1", "origin", "HEAD"])?;
+      run_git(url, Some(&checkout), &["reset", "--quiet", "--hard", "FETCH_HEAD"])?;
+    } else {
+      debug!("Using cached checkout of {} at {}", url, checkout.display());
+    }
+    return Ok(checkout);
+  }
+
+  fs::create_dir_all(&repos_dir).map_err(|e| SpawnError::OutputDirCreation {
+    path: repos_dir.clone(),
+    source: e,
+  })?;
+  // Clone next to the final location so an interrupted clone never looks like a valid cache
+  let staging = tempfile::Builder::new()
+    .prefix("clone-")
+    .tempdir_in(&repos_dir)?;
+  let staging_checkout = staging.path().join("repo");
+  info!("Cloning template repository {}...", url);
+  // Run from the current directory so local repository paths resolve as the user typed them;
+  // `--` keeps a url starting with `-` from being read as a git option
+  let target = staging_checkout.to_string_lossy();
+  run_git(url, None, &["clone", "--quiet", "--depth", "1", "--", url, &target])?;
+  if checkout.exists() {
+    // Leftover without a .git directory
+    fs::remove_dir_all(&checkout)?;
+  }
+  fs::rename(&staging_checkout, &checkout)?;
+  info!("Cached template repository at {}", checkout.display());
+  Ok(checkout)
+}
+
+fn run_git(url: &str, dir: Option<&Path>, args: &[&str]) -> Result<(), SpawnError> {
+  debug!("Running: git {}", args.join(" "));
+  let mut expression = cmd("git", args);
+  if let Some(dir) = dir {
+    expression = expression.dir(dir);
+  }
+  let output = expression
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()
+    .map_err(|e| SpawnError::TemplateRepo {
+      url: url.to_string(),
+      reason: format!("could not run git: {}", e),
+    })?;
+  if !output.status.success() {
+    return Err(SpawnError::TemplateRepo {
+      url: url.to_string(),
+      reason: format!(
+        "git {} failed: {}",
+        args[0],
+        String::from_utf8_lossy(&output.stderr).trim()
+      ),
+    });
+  }
+  Ok(())
+}
@@ -0,0 +1,354 @@
+// src/state.rs
+//! Generation state file (`.spawnpoint-state.yaml`) recording what a project was generated from.
+//! Intended as the basis for later "update my project from the template" workflows.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::ScaffoldManifest;
+use crate::error::SpawnError;
+use crate::utils::{CopyOptions, CopyReport};
+
+pub const STATE_FILE_NAME: &str = ".spawnpoint-state.yaml";
+pub const LOCK_FILE_NAME: &str = ".spawnpoint.lock";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectState {
+  pub spawnpoint_version: String,
+  pub template_name: String,
+  pub template_language: String,
+  /// The template's `version`, if it has one (`update` looks for that version).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub template_version: Option<semver::Version>,
+  /// Non-sensitive variable values used for generation.
+  pub variables: BTreeMap<String, String>,
+  /// Names of sensitive variables whose values were not recorded.
+  #[serde(default)]
+  pub redacted_variables: Vec<String>,
+  /// Generation options that shaped the output; `update` regenerates with the same ones.
+  #[serde(default)]
+  pub options: RecordedOptions,
+  pub files: Vec<FileState>,
+}
+
+/// The `CopyOptions` that change what a generation writes (not how it reports).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedOptions {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub placeholder_prefix: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub placeholder_suffix: Option<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub replace_in_binary: Vec<PathBuf>,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub preserve_bom: bool,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub only: Vec<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub skip: Vec<String>,
+}
+
+impl RecordedOptions {
+  pub fn from_copy_options(options: &CopyOptions) -> Self {
+    RecordedOptions {
+      placeholder_prefix: options.placeholder_prefix.clone(),
+      placeholder_suffix: options.placeholder_suffix.clone(),
+      replace_in_binary: options.replace_in_binary.clone(),
+      preserve_bom: options.preserve_bom,
+      only: options.only.clone(),
+      skip: options.skip.clone(),
+    }
+  }
+
+  /// Copy options reproducing the recorded generation.
+  pub fn to_copy_options(&self, strict_warnings: bool) -> CopyOptions {
+    CopyOptions {
+      strict_warnings,
+      placeholder_prefix: self.placeholder_prefix.clone(),
+      placeholder_suffix: self.placeholder_suffix.clone(),
+      replace_in_binary: self.replace_in_binary.clone(),
+      preserve_bom: self.preserve_bom,
+      only: self.only.clone(),
+      skip: self.skip.clone(),
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileState {
+  /// Output path relative to the project root.
+  pub path: PathBuf,
+  /// Source path relative to the template root.
+  pub source: PathBuf,
+  /// SHA-256 of the generated content (hex).
+  pub sha256: String,
+  /// The generated text, the base `update` merges local and template changes against.
+  /// Not recorded for binary files or files using a sensitive variable's placeholder.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub content: Option<String>,
+}
+
+/// Generation lockfile (`.spawnpoint.lock`): the answers a project was generated with,
+/// readable by `generate --answers` to regenerate it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LockFile {
+  /// Format version; its presence is how `--answers` tells a lockfile from a plain answers map.
+  pub lock_version: u32,
+  pub spawnpoint_version: String,
+  pub template_name: String,
+  pub template_language: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub template_version: Option<semver::Version>,
+  /// Non-sensitive variable values used for generation.
+  pub variables: BTreeMap<String, String>,
+  /// Names of sensitive variables whose values were not recorded (asked for again on reuse).
+  #[serde(default)]
+  pub redacted_variables: Vec<String>,
+}
+
+/// Writes the lockfile for a finished generation into the output directory.
+pub fn write_lock(
+  output_path: &Path,
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+) -> Result<PathBuf, SpawnError> {
+  let (variables, redacted_variables) = split_sensitive(manifest, base_variables);
+  let lock = LockFile {
+    lock_version: 1,
+    spawnpoint_version: env!("CARGO_PKG_VERSION").to_string(),
+    template_name: manifest.name.clone(),
+    template_language: manifest.language.clone(),
+    template_version: manifest.version.clone(),
+    variables,
+    redacted_variables,
+  };
+  let lock_path = output_path.join(LOCK_FILE_NAME);
+  fs::write(&lock_path, serde_yaml::to_string(&lock)?)?;
+  info!("Wrote generation lockfile to {}", lock_path.display());
+  Ok(lock_path)
+}
+
+/// Builds the state for a finished generation and writes it into the output directory.
+pub fn write_state(
+  output_path: &Path,
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+  report: &CopyReport,
+  options: &CopyOptions,
+) -> Result<PathBuf, SpawnError> {
+  let state = build_state(output_path, manifest, base_variables, report, options)?;
+  save_state(output_path, &state)
+}
+
+/// Builds the state for a generation, recording the files written under `generated_root`.
+pub fn build_state(
+  generated_root: &Path,
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+  report: &CopyReport,
+  options: &CopyOptions,
+) -> Result<ProjectState, SpawnError> {
+  let (variables, redacted_variables) = split_sensitive(manifest, base_variables);
+
+  let mut files = Vec::with_capacity(report.files.len());
+  for generated in &report.files {
+    let content = fs::read(generated_root.join(&generated.output))?;
+    let sha256 = sha256_hex(&content);
+    // Content rendered from a sensitive placeholder is not recorded
+    let text = String::from_utf8(content).ok().filter(|_| !generated.sensitive);
+    files.push(FileState {
+      path: generated.output.clone(),
+      source: generated.source.clone(),
+      sha256,
+      content: text,
+    });
+  }
+
+  Ok(ProjectState {
+    spawnpoint_version: env!("CARGO_PKG_VERSION").to_string(),
+    template_name: manifest.name.clone(),
+    template_language: manifest.language.clone(),
+    template_version: manifest.version.clone(),
+    variables,
+    redacted_variables,
+    options: RecordedOptions::from_copy_options(options),
+    files,
+  })
+}
+
+/// Splits the used variables into recordable values and the names of sensitive ones.
+fn split_sensitive(
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+) -> (BTreeMap<String, String>, Vec<String>) {
+  let mut variables = BTreeMap::new();
+  let mut redacted_variables = Vec::new();
+  for var_def in &manifest.variables {
+    let Some(value) = base_variables.get(&var_def.name) else {
+      continue;
+    };
+    if var_def.sensitive {
+      redacted_variables.push(var_def.name.clone());
+    } else {
+      variables.insert(var_def.name.clone(), value.clone());
+    }
+  }
+  (variables, redacted_variables)
+}
+
+/// Writes a state file into the project directory.
+pub fn save_state(project_dir: &Path, state: &ProjectState) -> Result<PathBuf, SpawnError> {
+  let state_path = project_dir.join(STATE_FILE_NAME);
+  let yaml = serde_yaml::to_string(state)?;
+  fs::write(&state_path, yaml)?;
+  debug!("State recorded {} file(s)", state.files.len());
+  info!("Wrote generation state to {}", state_path.display());
+  Ok(state_path)
+}
+
+/// Reads the state file from a previously generated project.
+pub fn read_state(project_dir: &Path) -> Result<ProjectState, SpawnError> {
+  let state_path = project_dir.join(STATE_FILE_NAME);
+  let content = fs::read_to_string(&state_path).map_err(|e| SpawnError::ManifestReadError {
+    manifest_path: state_path.clone(),
+    source: e,
+  })?;
+  serde_yaml::from_str(&content).map_err(|e| SpawnError::ManifestParseError {
+    manifest_path: state_path,
+    source: e,
+  })
+}
+
+/// Hex-encoded SHA-256 of some content.
+pub fn sha256_hex(content: &[u8]) -> String {
+  Sha256::digest(content)
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::{self, CopyOptions};
+
+  #[test]
+  fn state_file_lists_the_generated_files() {
+    let manifest: ScaffoldManifest = serde_yaml::from_str(
+      r#"
+name: Sample
+description: Test template
+language: test
+placeholderFilenames: {}
+variables:
+  - { name: projectName, prompt: "Name:", placeholderValue: app_name }
+  - { name: apiToken, prompt: "Token:", placeholderValue: api_token, sensitive: true }
+"#,
+    )
+    .unwrap();
+    let template = tempfile::tempdir().unwrap();
+    fs::create_dir_all(template.path().join("src")).unwrap();
+    fs::write(template.path().join("README.md"), "# app_name\n").unwrap();
+    fs::write(template.path().join("src/app_name.rs"), "// app_name\n").unwrap();
+
+    let base_variables = HashMap::from([
+      ("projectName".to_string(), "demo".to_string()),
+      ("apiToken".to_string(), "s3cret".to_string()),
+    ]);
+    let substitutions = HashMap::from([
+      ("app_name".to_string(), "demo".to_string()),
+      ("api_token".to_string(), "s3cret".to_string()),
+    ]);
+    let output = tempfile::tempdir().unwrap();
+    let options = CopyOptions::default();
+    let report = utils::copy_template_dir(
+      template.path(),
+      output.path(),
+      &base_variables,
+      &substitutions,
+      &manifest,
+      &options,
+    )
+    .unwrap();
+    write_state(output.path(), &manifest, &base_variables, &report, &options).unwrap();
+
+    let state = read_state(output.path()).unwrap();
+    assert_eq!(state.template_name, "Sample");
+    assert_eq!(state.variables, BTreeMap::from([("projectName".to_string(), "demo".to_string())]));
+    assert_eq!(state.redacted_variables, ["apiToken"]);
+
+    let mut files = state.files.clone();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let entries: Vec<_> = files
+      .iter()
+      .map(|f| (f.path.clone(), f.source.clone(), f.content.as_deref()))
+      .collect();
+    assert_eq!(
+      entries,
+      [
+        (PathBuf::from("README.md"), PathBuf::from("README.md"), Some("# demo\n")),
+        (PathBuf::from("src/demo.rs"), PathBuf::from("src/app_name.rs"), Some("// demo\n")),
+      ]
+    );
+    for file in &files {
+      assert_eq!(file.sha256, sha256_hex(&fs::read(output.path().join(&file.path)).unwrap()));
+    }
+  }
+
+  #[test]
+  fn content_is_left_out_only_for_files_using_a_sensitive_placeholder() {
+    let manifest: ScaffoldManifest = serde_yaml::from_str(
+      r#"
+name: Sample
+description: Test template
+language: test
+variables:
+  - { name: projectName, prompt: "Name:", placeholderValue: app_name }
+  - { name: apiToken, prompt: "Token:", placeholderValue: api_token, sensitive: true }
+"#,
+    )
+    .unwrap();
+    let template = tempfile::tempdir().unwrap();
+    fs::write(template.path().join("README.md"), "# app_name\n").unwrap();
+    fs::write(template.path().join("token.env"), "TOKEN=api_token\n").unwrap();
+
+    // The token happens to equal the project name; only the file using its placeholder is hidden
+    let base_variables = HashMap::from([
+      ("projectName".to_string(), "demo".to_string()),
+      ("apiToken".to_string(), "demo".to_string()),
+    ]);
+    let substitutions = HashMap::from([
+      ("app_name".to_string(), "demo".to_string()),
+      ("api_token".to_string(), "demo".to_string()),
+    ]);
+    let output = tempfile::tempdir().unwrap();
+    let options = CopyOptions::default();
+    let report = utils::copy_template_dir(
+      template.path(),
+      output.path(),
+      &base_variables,
+      &substitutions,
+      &manifest,
+      &options,
+    )
+    .unwrap();
+    let state = build_state(output.path(), &manifest, &base_variables, &report, &options).unwrap();
+
+    let content = |path: &str| {
+      let file = state.files.iter().find(|f| f.path == Path::new(path)).unwrap();
+      file.content.as_deref()
+    };
+    assert_eq!(content("README.md"), Some("# demo\n"));
+    assert_eq!(content("token.env"), None);
+  }
+}
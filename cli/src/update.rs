@@ -0,0 +1,403 @@
+// src/update.rs
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dialoguer::Password;
+use log::{debug, info, warn};
+use tempfile::Builder;
+
+use crate::cli::UpdateArgs;
+use crate::config::ScaffoldManifest;
+use crate::error::SpawnError;
+use crate::prompt::theme;
+use crate::registry::TemplateRegistry;
+use crate::state::{self, FileState, ProjectState};
+use crate::utils;
+
+/// What happened to a single file during an update.
+#[derive(Debug, PartialEq, Eq)]
+enum MergeOutcome {
+  /// New in the template; written to the project.
+  Added,
+  /// Unmodified locally; replaced with the new template output.
+  Updated,
+  /// Identical to the new template output already.
+  Unchanged,
+  /// Modified locally, template unchanged; local version kept.
+  KeptLocal,
+  /// Modified both locally and in the template, in different lines; both kept.
+  Merged,
+  /// Modified both locally and in the template in the same lines.
+  Conflict,
+}
+
+pub fn run_update(
+  args: UpdateArgs,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+) -> Result<(), SpawnError> {
+  let project_dir = &args.project_dir;
+  info!("Updating project in '{}'...", project_dir.display());
+
+  // --- 1. Load recorded state ---
+  let recorded = state::read_state(project_dir)?;
+  debug!("Recorded state: {:?}", recorded);
+
+  // --- 2. Find the template it was generated from ---
+  let (template_dir_name, template_path, manifest) =
+    find_recorded_template(&recorded, registry)?;
+  info!(
+    "Using template '{}' from {}",
+    manifest.name,
+    template_path.display()
+  );
+  // Lays base templates (`extends`) underneath; a composed directory lives as long as `resolved`
+  let resolved = registry.resolve(&template_path, &manifest)?;
+  let template_path = resolved.path.clone();
+  let manifest = resolved.manifest.clone();
+
+  // --- 3. Restore variables (re-prompting for redacted sensitive ones) ---
+  let base_variables = restore_variables(&recorded, &manifest)?;
+  let all_substitutions = utils::compute_transformed_variables(
+    &base_variables,
+    &manifest.variables,
+    &manifest.derived,
+    strict_warnings,
+  )?;
+
+  // --- 4. Regenerate into a temp dir (with the recorded generation options) ---
+  let copy_options = recorded.options.to_copy_options(strict_warnings);
+  let temp_dir = Builder::new()
+    .prefix(&format!("spawnpoint_update_{}_", template_dir_name))
+    .tempdir()
+    .map_err(SpawnError::Io)?;
+  let temp_path = temp_dir.path();
+  let report = utils::copy_template_dir(
+    &template_path,
+    temp_path,
+    &base_variables,
+    &all_substitutions,
+    &manifest,
+    &copy_options,
+  )?;
+
+  // --- 5. Merge each regenerated file into the project ---
+  let recorded_files: HashMap<&Path, &FileState> = recorded
+    .files
+    .iter()
+    .map(|f| (f.path.as_path(), f))
+    .collect();
+  let mut outcomes: Vec<(PathBuf, MergeOutcome)> = Vec::new();
+  for generated in &report.files {
+    let new_content = fs::read(temp_path.join(&generated.output))?;
+    let target = project_dir.join(&generated.output);
+    let outcome = merge_file(
+      &target,
+      &new_content,
+      recorded_files.get(generated.output.as_path()).copied(),
+      args.dry_run,
+    )?;
+    outcomes.push((generated.output.clone(), outcome));
+  }
+
+  // Files the template no longer produces are reported, never deleted
+  let regenerated: HashSet<&Path> = report.files.iter().map(|f| f.output.as_path()).collect();
+  let removed_upstream: Vec<&FileState> = recorded
+    .files
+    .iter()
+    .filter(|f| !regenerated.contains(f.path.as_path()))
+    .collect();
+
+  // --- 6. Report ---
+  let count = |wanted: MergeOutcome| outcomes.iter().filter(|(_, o)| *o == wanted).count();
+  for (path, outcome) in &outcomes {
+    match outcome {
+      MergeOutcome::Unchanged => debug!("unchanged: {}", path.display()),
+      other => info!("{:?}: {}", other, path.display()),
+    }
+  }
+  for file in &removed_upstream {
+    warn!(
+      "No longer produced by the template (left in place): {}",
+      file.path.display()
+    );
+  }
+  let conflicts = count(MergeOutcome::Conflict);
+  info!(
+    "Update summary: {} added, {} updated, {} unchanged, {} kept local, {} merged, {} conflict(s), {} removed upstream.",
+    count(MergeOutcome::Added),
+    count(MergeOutcome::Updated),
+    count(MergeOutcome::Unchanged),
+    count(MergeOutcome::KeptLocal),
+    count(MergeOutcome::Merged),
+    conflicts,
+    removed_upstream.len()
+  );
+
+  if args.dry_run {
+    info!("Dry run: no files were changed.");
+    return Ok(());
+  }
+
+  // --- 7. Record the new template output as the new base ---
+  let new_state = state::build_state(temp_path, &manifest, &base_variables, &report, &copy_options)?;
+  state::save_state(project_dir, &new_state)?;
+
+  if conflicts > 0 {
+    return Err(SpawnError::GenerationError(format!(
+      "Update finished with {} conflict(s). Resolve the conflict markers (or '.spawnpoint-new' files) and commit.",
+      conflicts
+    )));
+  }
+  info!("✅ Project updated from template '{}'.", manifest.name);
+  Ok(())
+}
+
+/// Finds the template a project state was recorded from (matched by name and language).
+pub(crate) fn find_recorded_template(
+  recorded: &ProjectState,
+  registry: &TemplateRegistry,
+) -> Result<(String, PathBuf, ScaffoldManifest), SpawnError> {
+  registry
+    .find(&recorded.template_language, &recorded.template_name)
+    .cloned()
+    .ok_or_else(|| {
+      SpawnError::TemplateNotFound(format!(
+        "Template '{}' for language '{}' (recorded in {}) not found.",
+        recorded.template_name,
+        recorded.template_language,
+        state::STATE_FILE_NAME
+      ))
+    })
+}
+
+/// Rebuilds the base variables from a recorded state, prompting for redacted sensitive ones.
+pub(crate) fn restore_variables(
+  recorded: &ProjectState,
+  manifest: &ScaffoldManifest,
+) -> Result<HashMap<String, String>, SpawnError> {
+  let mut base_variables: HashMap<String, String> = recorded.variables.clone().into_iter().collect();
+  for name in &recorded.redacted_variables {
+    let prompt = manifest
+      .variables
+      .iter()
+      .find(|vd| &vd.name == name)
+      .and_then(|vd| vd.prompt.clone())
+      .unwrap_or_else(|| format!("Value for sensitive variable '{}'", name));
+    let value = Password::with_theme(theme())
+      .with_prompt(prompt)
+      .interact()?;
+    base_variables.insert(name.clone(), value);
+  }
+  utils::expand_multi_select_members(manifest, &mut base_variables);
+  Ok(base_variables)
+}
+
+/// Merges one regenerated file into the project.
+/// The recorded hash tells whether the local file and/or the template output changed since
+/// the last generation; when both did, the recorded content is the base of a line merge.
+fn merge_file(
+  target: &Path,
+  new_content: &[u8],
+  recorded: Option<&FileState>,
+  dry_run: bool,
+) -> Result<MergeOutcome, SpawnError> {
+  let write = |content: &[u8], path: &Path| -> Result<(), SpawnError> {
+    if !dry_run {
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      fs::write(path, content)?;
+    }
+    Ok(())
+  };
+
+  if !target.exists() {
+    write(new_content, target)?;
+    return Ok(MergeOutcome::Added);
+  }
+
+  let current = fs::read(target)?;
+  if current == new_content {
+    return Ok(MergeOutcome::Unchanged);
+  }
+
+  let current_hash = state::sha256_hex(&current);
+  let new_hash = state::sha256_hex(new_content);
+  let base_hash = recorded.map(|f| f.sha256.as_str());
+
+  if base_hash == Some(current_hash.as_str()) {
+    // Untouched locally: take the template's version
+    write(new_content, target)?;
+    return Ok(MergeOutcome::Updated);
+  }
+  if base_hash == Some(new_hash.as_str()) {
+    // Template didn't change this file: keep local edits
+    return Ok(MergeOutcome::KeptLocal);
+  }
+
+  // Both sides changed (or the file is unknown to the recorded state)
+  let base = recorded.and_then(|f| f.content.as_deref());
+  match (std::str::from_utf8(&current), std::str::from_utf8(new_content)) {
+    (Ok(ours), Ok(theirs)) => {
+      // Without a recorded base (a file using a sensitive placeholder, or a state file from an
+      // older spawnpoint) the whole file is one conflict
+      let (merged, conflicts) = merge_lines(base.unwrap_or(""), ours, theirs);
+      write(merged.as_bytes(), target)?;
+      if conflicts == 0 {
+        return Ok(MergeOutcome::Merged);
+      }
+    }
+    _ => {
+      let mut sidecar = target.as_os_str().to_owned();
+      sidecar.push(".spawnpoint-new");
+      write(new_content, Path::new(&sidecar))?;
+    }
+  }
+  Ok(MergeOutcome::Conflict)
+}
+
+/// A change one side made to the base: `base[start..end]` replaced by `lines`.
+struct Change<'a> {
+  start: usize,
+  end: usize,
+  lines: &'a [&'a str],
+}
+
+/// The changes turning `base` into `other`, in base order.
+fn line_changes<'a>(base: &[&str], other: &'a [&'a str]) -> Vec<Change<'a>> {
+  similar::capture_diff_slices(similar::Algorithm::Myers, base, other)
+    .into_iter()
+    .filter(|op| op.tag() != similar::DiffTag::Equal)
+    .map(|op| {
+      let (old, new) = (op.old_range(), op.new_range());
+      Change {
+        start: old.start,
+        end: old.end,
+        lines: &other[new],
+      }
+    })
+    .collect()
+}
+
+/// `base[start..end]` with `changes` (all inside that range) applied.
+fn apply_changes(base: &[&str], start: usize, end: usize, changes: &[&Change]) -> String {
+  let mut text = String::new();
+  let mut pos = start;
+  for change in changes {
+    text.push_str(&base[pos..change.start].concat());
+    text.push_str(&change.lines.concat());
+    pos = change.end;
+  }
+  text.push_str(&base[pos..end].concat());
+  text
+}
+
+/// Three-way line merge of the local (`ours`) and template (`theirs`) versions against
+/// `base`. Changes to different lines are both kept; where both sides changed the same or
+/// adjacent lines differently, that region gets conflict markers. Returns the merged text
+/// and the number of conflicting regions.
+fn merge_lines(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+  let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+  let our_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+  let their_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+  let our_changes = line_changes(&base_lines, &our_lines);
+  let their_changes = line_changes(&base_lines, &their_lines);
+
+  let mut merged = String::new();
+  let mut conflicts = 0;
+  let mut copied_to = 0;
+  let (mut next_ours, mut next_theirs) = (0, 0);
+  loop {
+    // The earliest remaining change starts a region...
+    let first = match (our_changes.get(next_ours), their_changes.get(next_theirs)) {
+      (Some(o), Some(t)) => o.start.min(t.start),
+      (Some(o), None) => o.start,
+      (None, Some(t)) => t.start,
+      (None, None) => break,
+    };
+    // ...which grows while a change from either side overlaps or touches it
+    let (mut start, mut end) = (first, first);
+    let (mut region_ours, mut region_theirs) = (Vec::new(), Vec::new());
+    loop {
+      let touches = |change: &Change| change.start <= end && change.end >= start;
+      if let Some(change) = our_changes.get(next_ours).filter(|c| touches(c)) {
+        (start, end) = (start.min(change.start), end.max(change.end));
+        region_ours.push(change);
+        next_ours += 1;
+      } else if let Some(change) = their_changes.get(next_theirs).filter(|c| touches(c)) {
+        (start, end) = (start.min(change.start), end.max(change.end));
+        region_theirs.push(change);
+        next_theirs += 1;
+      } else {
+        break;
+      }
+    }
+
+    merged.push_str(&base_lines[copied_to..start].concat());
+    let our_text = apply_changes(&base_lines, start, end, &region_ours);
+    let their_text = apply_changes(&base_lines, start, end, &region_theirs);
+    if region_theirs.is_empty() || our_text == their_text {
+      merged.push_str(&our_text);
+    } else if region_ours.is_empty() {
+      merged.push_str(&their_text);
+    } else {
+      conflicts += 1;
+      for (marker, text) in [("<<<<<<< local\n", &our_text), ("=======\n", &their_text)] {
+        merged.push_str(marker);
+        merged.push_str(text);
+        if !text.is_empty() && !text.ends_with('\n') {
+          merged.push('\n');
+        }
+      }
+      merged.push_str(">>>>>>> template\n");
+    }
+    copied_to = end;
+  }
+  merged.push_str(&base_lines[copied_to..].concat());
+  (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn merge_keeps_changes_to_different_lines() {
+    let base = "a\nb\nc\nd\ne\n";
+    let ours = "a\nB (local)\nc\nd\ne\n";
+    let theirs = "a\nb\nc\nd\nE (template)\nf\n";
+    assert_eq!(
+      merge_lines(base, ours, theirs),
+      ("a\nB (local)\nc\nd\nE (template)\nf\n".to_string(), 0)
+    );
+  }
+
+  #[test]
+  fn merge_marks_only_the_conflicting_lines() {
+    let base = "a\nb\nc\nd\ne\n";
+    let ours = "a\nlocal b\nc\nd\nlocal e\n";
+    let theirs = "a\ntemplate b\nc\nd\ne\n";
+    let (merged, conflicts) = merge_lines(base, ours, theirs);
+    assert_eq!(conflicts, 1);
+    assert_eq!(
+      merged,
+      "a\n<<<<<<< local\nlocal b\n=======\ntemplate b\n>>>>>>> template\nc\nd\nlocal e\n"
+    );
+  }
+
+  #[test]
+  fn merge_takes_identical_changes_once() {
+    let base = "a\nb\n";
+    let both = "a\nb2\n";
+    assert_eq!(merge_lines(base, both, both), (both.to_string(), 0));
+  }
+
+  #[test]
+  fn merge_without_base_conflicts_on_the_whole_file() {
+    let (merged, conflicts) = merge_lines("", "local", "template\n");
+    assert_eq!(conflicts, 1);
+    assert_eq!(merged, "<<<<<<< local\nlocal\n=======\ntemplate\n>>>>>>> template\n");
+  }
+}
@@ -1,23 +1,391 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::ErrorKind;
-use std::os::unix::process::ExitStatusExt;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{ExitStatus, Output};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use duct::{cmd, Handle};
-use heck::{ToKebabCase, ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use heck::{
+  ToKebabCase, ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase, ToSnakeCase, ToTitleCase, ToTrainCase,
+};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::{debug, error, info, trace, warn};
+use rayon::prelude::*;
+use serde::Serialize;
 use walkdir::WalkDir;
 
 use crate::config::{
-  CaseTransformation, Condition, PlaceholderFilenames, ScaffoldManifest, ValidationStep,
-  VariableDefinition,
+  BomHandling, CaseTransformation, ContentDelimiters, DEFAULT_MAX_SUBSTITUTION_BYTES, Condition, ConditionExpr, ConditionOperator, DerivedVariable, PlaceholderFilenames, ProgressUnit,
+  ScaffoldManifest, StepFailure, UndefinedEnv, ValidationStep, VariableDefinition, VariableType,
 };
 use crate::error::SpawnError;
+use crate::license;
+
+/// Runtime options for `copy_template_dir` that don't come from the manifest.
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+  /// Promote recoverable warnings (e.g., non-UTF8 path components) to errors.
+  pub strict_warnings: bool,
+  /// Overrides `placeholderFilenames.prefix` from the manifest.
+  pub placeholder_prefix: Option<String>,
+  /// Overrides `placeholderFilenames.suffix` from the manifest.
+  pub placeholder_suffix: Option<String>,
+  /// Extra binary files (relative to template root) to substitute at the byte level,
+  /// in addition to the manifest's `replaceInBinary`.
+  pub replace_in_binary: Vec<PathBuf>,
+  /// Record per-file failures in the report and keep going instead of aborting.
+  pub continue_on_file_error: bool,
+  /// Keep UTF-8 BOMs in text files regardless of the manifest's `bom` setting.
+  pub preserve_bom: bool,
+  /// Overwrite files that already exist in the output directory instead of failing.
+  pub overwrite_existing: bool,
+  /// Record placeholder-like tokens left in generated text files in `CopyReport::leftover_placeholders`.
+  pub strict_placeholders: bool,
+  /// When non-empty, only template paths matching one of these `exclude`-style patterns
+  /// (or inside a matching directory) are generated.
+  pub only: Vec<String>,
+  /// `exclude`-style patterns left out in addition to the manifest's `exclude`.
+  pub skip: Vec<String>,
+  /// Compare each file with the one already in the output directory and record the
+  /// differences in `CopyReport::diffs`.
+  pub diff: bool,
+  /// Render files without writing anything to the output directory (with `diff`, to preview
+  /// what regenerating would change).
+  pub dry_run: bool,
+  /// Overrides the manifest's `progress` unit for the progress bar.
+  pub progress: Option<ProgressUnit>,
+}
+
+/// Variables a `cleanEnv` step still gets from spawnpoint's environment (when set), so
+/// programs can be found and tools that need a home or temp directory still work.
+#[cfg(not(windows))]
+pub const CLEAN_ENV_PRESERVED: &[&str] = &["PATH", "HOME"];
+#[cfg(windows)]
+pub const CLEAN_ENV_PRESERVED: &[&str] =
+  &["PATH", "PATHEXT", "SYSTEMROOT", "COMSPEC", "USERPROFILE", "TEMP", "TMP"];
+
+/// Environment variable selecting the file-copy progress bar style.
+pub const PROGRESS_TEMPLATE_ENV: &str = "SPAWNPOINT_PROGRESS_TEMPLATE";
+
+const DEFAULT_PROGRESS_TEMPLATE: &str =
+  "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}";
+const MINIMAL_PROGRESS_TEMPLATE: &str = "{pos}/{len} {msg}";
+const DEFAULT_BYTES_PROGRESS_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] \
+  {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta}) {msg}";
+const MINIMAL_BYTES_PROGRESS_TEMPLATE: &str = "{bytes}/{total_bytes} {msg}";
+
+/// UTF-8 byte order mark, as it appears at the start of a decoded string.
+const UTF8_BOM: char = '\u{FEFF}';
+const UTF8_BOM_BYTES: &[u8] = b"\xEF\xBB\xBF";
+
+/// A file written by `copy_template_dir`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedFile {
+  /// Path relative to the template root (before substitution).
+  pub source: PathBuf,
+  /// Path relative to the output root (after substitution).
+  pub output: PathBuf,
+  /// The template file uses the placeholder of a `sensitive` variable (or a value derived
+  /// from one), so its rendered content must not be recorded.
+  #[serde(skip)]
+  pub sensitive: bool,
+}
+
+/// Outcome of `copy_template_dir`.
+#[derive(Debug, Default)]
+pub struct CopyReport {
+  /// Files successfully written, in walk order.
+  pub files: Vec<GeneratedFile>,
+  /// Files (relative to template root) that failed, with the error message.
+  /// Only populated when `continue_on_file_error` is set.
+  pub failed_files: Vec<(PathBuf, String)>,
+  /// Placeholder-like tokens still present after substitution, in walk order.
+  /// Only populated when `strict_placeholders` is set.
+  pub leftover_placeholders: Vec<LeftoverPlaceholder>,
+  /// How each generated file compares with the existing output, in walk order.
+  /// Only populated when `diff` is set.
+  pub diffs: Vec<FileDiff>,
+}
+
+/// How a generated file compares with the file already at its output path.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+  /// Path relative to the output root.
+  pub file: PathBuf,
+  pub change: FileChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+  /// Nothing exists at the output path yet.
+  Added,
+  Unchanged,
+  /// Unified diff from the existing text to the generated text.
+  Modified(String),
+  /// Binary (or non-UTF-8) content that differs, with the existing and generated SHA-256.
+  BinaryModified { existing: String, generated: String },
+}
+
+/// A token in a generated file that looks like an unsubstituted placeholder.
+#[derive(Debug, Clone)]
+pub struct LeftoverPlaceholder {
+  /// Path relative to the output root.
+  pub file: PathBuf,
+  /// 1-based line number.
+  pub line: usize,
+  pub token: String,
+}
+
+/// What counts as a leftover placeholder: `<prefix>name<suffix>` for each delimiter pair
+/// (the filename markers and `--name--`), plus manifest placeholders that were not substituted.
+struct LeftoverScan {
+  delimiters: Vec<(String, String)>,
+  literals: Vec<String>,
+}
+
+impl LeftoverScan {
+  fn new(
+    manifest: &ScaffoldManifest,
+    all_substitutions: &HashMap<String, String>,
+    placeholder_config: &Option<PlaceholderFilenames>,
+  ) -> Self {
+    let markers = placeholder_config.clone().unwrap_or_default();
+    let mut literals: Vec<String> = manifest
+      .variables
+      .iter()
+      .flat_map(|vd| std::iter::once(&vd.placeholder_value).chain(vd.transformations.values()))
+      .chain(manifest.derived.iter().map(|d| &d.placeholder_value))
+      .filter(|p| !p.is_empty() && !all_substitutions.contains_key(*p))
+      .map(|p| manifest.content_token(p))
+      .collect();
+    literals.sort();
+    literals.dedup();
+    LeftoverScan {
+      delimiters: vec![(markers.prefix, markers.suffix), ("--".to_string(), "--".to_string())],
+      literals,
+    }
+  }
+
+  /// Tokens in one line, in order of first appearance and without duplicates.
+  fn scan_line(&self, line: &str) -> Vec<String> {
+    let mut found: Vec<(usize, &str)> = Vec::new();
+    for (prefix, suffix) in &self.delimiters {
+      let mut search_from = 0;
+      while let Some(offset) = line[search_from..].find(prefix.as_str()) {
+        let start = search_from + offset;
+        let name_start = start + prefix.len();
+        let name_len = delimited_name_len(&line[name_start..], suffix);
+        match name_len {
+          Some(len) => {
+            let end = name_start + len + suffix.len();
+            found.push((start, &line[start..end]));
+            search_from = end;
+          }
+          None => search_from = name_start,
+        }
+      }
+    }
+    for literal in &self.literals {
+      if let Some(start) = line.find(literal.as_str()) {
+        found.push((start, literal));
+      }
+    }
+    found.sort();
+    let mut tokens: Vec<String> = Vec::new();
+    for (_, token) in found {
+      if !tokens.iter().any(|t| t == token) {
+        tokens.push(token.to_string());
+      }
+    }
+    tokens
+  }
+
+  /// Scans a whole substituted text, returning `(line number, token)` pairs.
+  fn scan_text(&self, text: &str, first_line: usize) -> Vec<(usize, String)> {
+    text
+      .lines()
+      .enumerate()
+      .flat_map(|(i, line)| self.scan_line(line).into_iter().map(move |t| (first_line + i, t)))
+      .collect()
+  }
+}
+
+/// Length of a placeholder name at the start of `rest`, if `suffix` directly follows it.
+/// Names start with an ASCII letter or digit and contain letters, digits, `_`, `.` and `-`.
+fn delimited_name_len(rest: &str, suffix: &str) -> Option<usize> {
+  if !rest.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+    return None;
+  }
+  for (i, c) in rest.char_indices() {
+    if i > 0 && rest[i..].starts_with(suffix) {
+      return Some(i);
+    }
+    if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+      return None;
+    }
+  }
+  None
+}
+
+/// Resolves the effective filename placeholder config from the manifest and any CLI overrides.
+/// Overrides enable path substitution even if the manifest has no `placeholderFilenames`.
+pub fn resolve_placeholder_filenames(
+  manifest_config: &Option<PlaceholderFilenames>,
+  options: &CopyOptions,
+) -> Result<Option<PlaceholderFilenames>, SpawnError> {
+  if options.placeholder_prefix.is_none() && options.placeholder_suffix.is_none() {
+    return Ok(manifest_config.clone());
+  }
+
+  let base = manifest_config.clone().unwrap_or_default();
+  let resolved = PlaceholderFilenames {
+    prefix: options.placeholder_prefix.clone().unwrap_or(base.prefix),
+    suffix: options.placeholder_suffix.clone().unwrap_or(base.suffix),
+  };
+  if resolved.prefix.is_empty() || resolved.suffix.is_empty() {
+    return Err(SpawnError::GenerationError(
+      "Placeholder prefix and suffix must not be empty.".to_string(),
+    ));
+  }
+  if resolved.prefix == resolved.suffix {
+    return Err(SpawnError::GenerationError(format!(
+      "Placeholder prefix and suffix must differ (both are '{}').",
+      resolved.prefix
+    )));
+  }
+  debug!(
+    "Using filename placeholder prefix '{}' and suffix '{}'",
+    resolved.prefix, resolved.suffix
+  );
+  Ok(Some(resolved))
+}
+
+/// Logs a warning, or returns it as `SpawnError::StrictWarning` when strict mode is on.
+///
+/// Warnings routed through here (and therefore promoted by `--strict-warnings`):
+/// - conditional path variable not found in the provided variables
+/// - invalid `validationRegex` on a variable
+/// - non-UTF8 path component in a template
+/// - a template shadowed by another with the same name and language
+/// - a transformation fallback in derived variable computation
+/// - a placeholder of a skipped (`when`) variable left in the generated output
+/// - a `licenseHolder` variable without a value
+/// - a template with no files to generate
+/// - a `--var` that matches no variable of the template
+/// - hooks skipped because their templates directory is not trusted
+pub fn strict_warn(strict: bool, message: String) -> Result<(), SpawnError> {
+  if strict {
+    error!("{}", message);
+    Err(SpawnError::StrictWarning(message))
+  } else {
+    warn!("{}", message);
+    Ok(())
+  }
+}
+
+/// Log target for the per-placeholder trace of variable computation.
+/// Shown with `-v`, or on its own with `--trace-transforms`.
+pub const TRANSFORM_LOG_TARGET: &str = "spawnpoint::transforms";
+
+/// Default number of memoized transformations kept before a `TransformCache` is cleared.
+const DEFAULT_TRANSFORM_CACHE_CAPACITY: usize = 1024;
+
+/// Memoizes case transformations keyed by (input value, transformation).
+/// Transformations are pure, so a cache can be reused across batch runs of the *same* template.
+/// Use one cache per template; it is cleared whenever it reaches its capacity.
+#[derive(Debug)]
+pub struct TransformCache {
+  entries: HashMap<(String, CaseTransformation), String>,
+  capacity: usize,
+}
+
+impl TransformCache {
+  pub fn new(capacity: usize) -> Self {
+    TransformCache {
+      entries: HashMap::new(),
+      capacity: capacity.max(1),
+    }
+  }
+
+  /// Returns the cached transformation of `value`, computing and storing it on a miss.
+  pub fn get_or_compute(&mut self, value: &str, transform_case: &CaseTransformation) -> String {
+    let key = (value.to_string(), transform_case.clone());
+    if let Some(cached) = self.entries.get(&key) {
+      trace!("Transform cache hit: {:?}({:?})", transform_case, value);
+      return cached.clone();
+    }
+    if self.entries.len() >= self.capacity {
+      debug!("Transform cache reached capacity ({}), clearing.", self.capacity);
+      self.entries.clear();
+    }
+    let transformed = apply_case_transformation(value, transform_case);
+    self.entries.insert(key, transformed.clone());
+    transformed
+  }
+}
+
+impl Default for TransformCache {
+  fn default() -> Self {
+    TransformCache::new(DEFAULT_TRANSFORM_CACHE_CAPACITY)
+  }
+}
+
+/// Applies a single case transformation to a value.
+fn apply_case_transformation(value: &str, transform_case: &CaseTransformation) -> String {
+  match transform_case {
+    CaseTransformation::PascalCase => value.to_pascal_case(),
+    CaseTransformation::CamelCase => value.to_lower_camel_case(),
+    CaseTransformation::SnakeCase => value.to_snake_case(),
+    CaseTransformation::KebabCase => value.to_kebab_case(),
+    CaseTransformation::ShoutySnakeCase => value.to_shouty_snake_case(),
+    CaseTransformation::TitleCase => value.to_title_case(),
+    CaseTransformation::TrainCase => value.to_train_case(),
+    CaseTransformation::PackageName => {
+      // Simple version: lowercase and remove non-alphanumerics
+      // More complex might involve splitting by case/separators first
+      value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+      // Or alternatively, use snake_case: value.to_snake_case()
+    }
+    CaseTransformation::NpmPackageName => package_name(value, &['-', '.', '_', '~']),
+    CaseTransformation::CargoPackageName => package_name(value, &['-', '_']),
+  }
+}
+
+/// Lowercases `value`, keeping ASCII letters and digits. Every run of anything else becomes a
+/// single separator: the run's first `kept` separator, or `-` when it has none (spaces, `/`,
+/// `@`, non-ASCII, ...). The result starts and ends with a letter or digit:
+/// `My Cool.App` -> `my-cool.app` and `a - b` -> `a-b` (npm).
+fn package_name(value: &str, kept: &[char]) -> String {
+  let mut name = String::with_capacity(value.len());
+  // `Some(None)` while inside a run with no kept separator yet
+  let mut run: Option<Option<char>> = None;
+  for c in value.chars() {
+    if c.is_ascii_alphanumeric() {
+      if let Some(kept_separator) = run.take().filter(|_| !name.is_empty()) {
+        name.push(kept_separator.unwrap_or('-'));
+      }
+      name.push(c.to_ascii_lowercase());
+    } else {
+      let kept_separator = run.get_or_insert(None);
+      if kept_separator.is_none() && kept.contains(&c) {
+        *kept_separator = Some(c);
+      }
+    }
+  }
+  name
+}
 
 /// Takes base variables and computes transformed versions based on manifest definitions.
 /// The key in the returned map will be the *placeholder* string (e.g., "__PASCAL_VAR__").
@@ -25,51 +393,155 @@ use crate::error::SpawnError;
 pub fn compute_transformed_variables(
   base_variables: &HashMap<String, String>, // User input keyed by var name (e.g., "appName")
   variable_definitions: &[VariableDefinition], // From manifest
-) -> HashMap<String, String> {
+  derived: &[DerivedVariable],                 // From manifest, evaluated last
+  strict_warnings: bool,
+) -> Result<HashMap<String, String>, SpawnError> {
+  compute_transformed_variables_cached(
+    base_variables,
+    variable_definitions,
+    derived,
+    strict_warnings,
+    &mut TransformCache::default(),
+  )
+}
+
+/// Renders a `varType: license` variable into its placeholder.
+/// The SPDX id comes from the prompted value, or the manifest `default` when there is no prompt.
+fn insert_license_text(
+  var_def: &VariableDefinition,
+  base_variables: &HashMap<String, String>,
+  all_substitutions: &mut HashMap<String, String>,
+  strict_warnings: bool,
+) -> Result<(), SpawnError> {
+  let Some(spdx_id) = base_variables.get(&var_def.name).or(var_def.default.as_ref()) else {
+    return Ok(()); // Skipped (`when`) and no default: leave the placeholder alone
+  };
+  let holder = match &var_def.license_holder {
+    Some(holder_var) => match base_variables.get(holder_var) {
+      Some(holder) => holder.clone(),
+      None => {
+        strict_warn(
+          strict_warnings,
+          format!(
+            "License holder variable '{}' for '{}' has no value; leaving the holder blank.",
+            holder_var, var_def.name
+          ),
+        )?;
+        String::new()
+      }
+    },
+    None => String::new(),
+  };
+  let text = license::render(spdx_id, &holder, license::current_year())?;
+  debug!(
+    target: TRANSFORM_LOG_TARGET,
+    "{}: {} = {} license text (holder {:?})", var_def.name, var_def.placeholder_value, spdx_id, holder
+  );
+  all_substitutions.insert(var_def.placeholder_value.clone(), text);
+  Ok(())
+}
+
+/// Same as `compute_transformed_variables`, but reuses transformations from `cache`.
+/// `generate` and `validate` keep one cache per run and pass it to every step that
+/// transforms values; batch runs generating many projects from one template can share one.
+pub fn compute_transformed_variables_cached(
+  base_variables: &HashMap<String, String>,
+  variable_definitions: &[VariableDefinition],
+  derived: &[DerivedVariable],
+  strict_warnings: bool,
+  cache: &mut TransformCache,
+) -> Result<HashMap<String, String>, SpawnError> {
   let mut all_substitutions = HashMap::new();
   let mut computed_base_transforms: HashMap<String, HashMap<CaseTransformation, String>> =
     HashMap::new(); // Cache base transforms
 
   // --- Pass 1: Compute base transformations and store direct placeholders ---
   for var_def in variable_definitions {
+    if var_def.var_type == VariableType::License {
+      insert_license_text(var_def, base_variables, &mut all_substitutions, strict_warnings)?;
+      continue;
+    }
     if let Some(base_value) = base_variables.get(&var_def.name) {
-      // Insert direct value if it was prompted for
-      if var_def.prompt.is_some() {
+      // Insert direct value if it was prompted for or generated
+      if var_def.prompt.is_some() || var_def.generator.is_some() {
+        debug!(
+          target: TRANSFORM_LOG_TARGET,
+          "{}: {} = {:?} (direct)",
+          var_def.name,
+          var_def.placeholder_value,
+          shown(base_value, var_def.sensitive)
+        );
         all_substitutions.insert(var_def.placeholder_value.clone(), base_value.clone());
+      } else {
+        debug!(
+          target: TRANSFORM_LOG_TARGET,
+          "{}: {} not substituted directly (variable has no prompt or generator)",
+          var_def.name,
+          var_def.placeholder_value
+        );
       }
 
       // Compute and cache transformations
       let mut transforms = HashMap::new();
       for (transform_case, transform_placeholder) in &var_def.transformations {
-        let transformed_value = match transform_case {
-          CaseTransformation::PascalCase => base_value.to_pascal_case(),
-          CaseTransformation::CamelCase => base_value.to_lower_camel_case(),
-          CaseTransformation::SnakeCase => base_value.to_snake_case(),
-          CaseTransformation::KebabCase => base_value.to_kebab_case(),
-          CaseTransformation::ShoutySnakeCase => base_value.to_shouty_snake_case(),
-          CaseTransformation::PackageName => {
-            // Simple version: lowercase and remove non-alphanumerics
-            // More complex might involve splitting by case/separators first
-            base_value
-              .chars()
-              .filter(|c| c.is_ascii_alphanumeric())
-              .collect::<String>()
-              .to_lowercase()
-            // Or alternatively, use snake_case: base_value.to_snake_case()
-          }
-        };
+        let transformed_value = cache.get_or_compute(base_value, transform_case);
+        debug!(
+          target: TRANSFORM_LOG_TARGET,
+          "{}: {} = {:?}({:?}) = {:?}",
+          var_def.name,
+          transform_placeholder,
+          transform_case,
+          shown(base_value, var_def.sensitive),
+          shown(&transformed_value, var_def.sensitive)
+        );
         // Store computed value keyed by placeholder
         all_substitutions.insert(transform_placeholder.clone(), transformed_value.clone());
         // Also cache it keyed by CaseTransformation enum for later use
         transforms.insert(transform_case.clone(), transformed_value);
       }
       computed_base_transforms.insert(var_def.name.clone(), transforms);
+    } else {
+      debug!(
+        target: TRANSFORM_LOG_TARGET,
+        "{}: no value (skipped or not prompted); its placeholders are left as-is",
+        var_def.name
+      );
+    }
+  }
+
+  // --- Pass 2: Manifest `derived` values, in declaration order ---
+  // Later entries can reference earlier ones by name
+  let sensitive = sensitive_names(variable_definitions, derived);
+  let mut derived_values = base_variables.clone();
+  let mut resolved_names: HashSet<&str> = HashSet::new();
+  for derived_def in derived {
+    if resolved_names.contains(derived_def.name.as_str()) {
+      continue; // An earlier entry with the same name already applied
     }
+    if let Some(when) = &derived_def.when {
+      if !evaluate_condition_expr(when, &derived_values, strict_warnings)? {
+        debug!(
+          target: TRANSFORM_LOG_TARGET,
+          "{}: skipped derived entry (condition {} not met)", derived_def.name, when
+        );
+        continue;
+      }
+    }
+    let value = render_derived_template(derived_def, &derived_values, strict_warnings, cache)?;
+    debug!(
+      target: TRANSFORM_LOG_TARGET,
+      "{}: {} = {:?} (derived from {:?})",
+      derived_def.name,
+      derived_def.placeholder_value,
+      shown(&value, sensitive.contains(&derived_def.name)),
+      derived_def.template
+    );
+    all_substitutions.insert(derived_def.placeholder_value.clone(), value.clone());
+    derived_values.insert(derived_def.name.clone(), value);
+    resolved_names.insert(&derived_def.name);
   }
 
-  // --- Pass 2: Compute derived/combined values ---
-  // Example: Specifically compute the full package name
-  // Find the definition for the fullPackageName placeholder
+  // --- Pass 2b: Legacy `fullPackageName` variable (predates `derived`) ---
   if let Some(full_name_def) = variable_definitions
     .iter()
     .find(|vd| vd.name == "fullPackageName")
@@ -78,19 +550,24 @@ pub fn compute_transformed_variables(
     // Get required base variable values from user input map
     let use_scope = base_variables
       .get("useOrgScope")
-      .map_or(false, |s| s == "true");
+      .is_some_and(|s| s == "true");
     let scope = base_variables.get("orgScope").cloned().unwrap_or_default(); // Default to empty if missing
 
     // Get the already computed kebab-case version of projectName
-    let kebab_project_name = computed_base_transforms
+    let kebab_project_name = match computed_base_transforms
       .get("projectName")
       .and_then(|transforms| transforms.get(&CaseTransformation::KebabCase))
-      .cloned()
-      .unwrap_or_else(|| {
-          warn!("KebabCase transformation for 'projectName' not found/computed for 'fullPackageName'. Falling back.");
-          // Fallback: compute it directly if needed (less efficient)
-          base_variables.get("projectName").map_or_else(String::new, |pn| pn.to_kebab_case())
-      });
+    {
+      Some(kebab) => kebab.clone(),
+      None => {
+        strict_warn(
+          strict_warnings,
+          "KebabCase transformation for 'projectName' not found/computed for 'fullPackageName'. Falling back.".to_string(),
+        )?;
+        // Fallback: compute it directly if needed (less efficient)
+        base_variables.get("projectName").map_or_else(String::new, |pn| pn.to_kebab_case())
+      }
+    };
 
     let final_name = if use_scope && !scope.is_empty() && !kebab_project_name.is_empty() {
       format!("{}/{}", scope, kebab_project_name)
@@ -99,11 +576,233 @@ pub fn compute_transformed_variables(
     };
 
     // Insert the final computed name keyed by its placeholder
+    debug!(
+      target: TRANSFORM_LOG_TARGET,
+      "{}: {} = {:?} (derived: useOrgScope={}, orgScope={:?})",
+      full_name_def.name,
+      full_name_def.placeholder_value,
+      final_name,
+      use_scope,
+      scope
+    );
     all_substitutions.insert(full_name_def.placeholder_value.clone(), final_name);
   }
   // --- End Pass 2 ---
 
-  all_substitutions
+  Ok(all_substitutions)
+}
+
+// --- `{{ref}}` templates ---
+// Derived values, defaults, `--output-dir`, step commands and post-generate messages all
+// reference variables as `{{name}}` or `{{name.<suffix>}}`; they share this tokenizer.
+
+/// A piece of a `{{ref}}` template.
+pub(crate) enum TemplatePart<'a> {
+  /// Text outside any reference, as written
+  Text(&'a str),
+  /// A `{{...}}` token as written, and the trimmed reference inside it
+  Reference { token: &'a str, reference: &'a str },
+}
+
+/// Splits `template` into text and references, left to right. A `{{` without a closing `}}`
+/// is plain text.
+pub(crate) fn template_parts(template: &str) -> Vec<TemplatePart<'_>> {
+  let mut parts = Vec::new();
+  let mut rest = template;
+  while let Some(start) = rest.find("{{") {
+    let Some(len) = rest[start + 2..].find("}}") else {
+      break;
+    };
+    if start > 0 {
+      parts.push(TemplatePart::Text(&rest[..start]));
+    }
+    parts.push(TemplatePart::Reference {
+      token: &rest[start..start + 4 + len],
+      reference: rest[start + 2..start + 2 + len].trim(),
+    });
+    rest = &rest[start + 4 + len..];
+  }
+  if !rest.is_empty() {
+    parts.push(TemplatePart::Text(rest));
+  }
+  parts
+}
+
+/// Renders `template`, replacing each reference with what `resolve` returns for it; `None`
+/// leaves the token as written.
+pub(crate) fn render_references<E>(
+  template: &str,
+  mut resolve: impl FnMut(&str) -> Result<Option<String>, E>,
+) -> Result<String, E> {
+  let mut rendered = String::with_capacity(template.len());
+  for part in template_parts(template) {
+    match part {
+      TemplatePart::Text(text) => rendered.push_str(text),
+      TemplatePart::Reference { token, reference } => match resolve(reference)? {
+        Some(value) => rendered.push_str(&value),
+        None => rendered.push_str(token),
+      },
+    }
+  }
+  Ok(rendered)
+}
+
+/// Names referenced as `{{...}}` in a template, in order (trimmed, may include a
+/// `.transformation` suffix).
+pub(crate) fn derived_template_references(template: &str) -> Vec<&str> {
+  template_parts(template)
+    .into_iter()
+    .filter_map(|part| match part {
+      TemplatePart::Reference { reference, .. } => Some(reference),
+      TemplatePart::Text(_) => None,
+    })
+    .collect()
+}
+
+/// Fills the `{{...}}` references of a `derived` template from `values` (base variables plus
+/// earlier derived values). References without a value are left as written.
+fn render_derived_template(
+  derived_def: &DerivedVariable,
+  values: &HashMap<String, String>,
+  strict_warnings: bool,
+  cache: &mut TransformCache,
+) -> Result<String, SpawnError> {
+  render_references(&derived_def.template, |reference| {
+    match resolve_derived_reference(reference, values, cache) {
+      Ok(Some(value)) => Ok(Some(value)),
+      Ok(None) => {
+        strict_warn(
+          strict_warnings,
+          format!(
+            "Derived value '{}' references '{}', which has no value; leaving it as written.",
+            derived_def.name, reference
+          ),
+        )?;
+        Ok(None)
+      }
+      Err(reason) => Err(SpawnError::VariableError {
+        name: derived_def.name.clone(),
+        reason,
+      }),
+    }
+  })
+}
+
+/// Resolves `name` or `name.<transformation>`. Whole names win, so multiSelect members such
+/// as `features.docker` resolve directly.
+pub(crate) fn resolve_derived_reference(
+  reference: &str,
+  values: &HashMap<String, String>,
+  cache: &mut TransformCache,
+) -> Result<Option<String>, String> {
+  if let Some(value) = values.get(reference) {
+    return Ok(Some(value.clone()));
+  }
+  let Some((name, transformation)) = reference.rsplit_once('.') else {
+    return Ok(None);
+  };
+  let Some(value) = values.get(name) else {
+    return Ok(None);
+  };
+  let transform_case = CaseTransformation::from_name(transformation)
+    .ok_or_else(|| format!("unknown transformation '{}' in '{{{{{}}}}}'", transformation, reference))?;
+  Ok(Some(cache.get_or_compute(value, &transform_case)))
+}
+
+/// Matches template-relative paths against the manifest's `exclude` patterns, gitignore-style:
+/// - a pattern without `/` (e.g. `target`, `*.log`) matches an entry of that name at any depth;
+/// - a pattern containing `/` (e.g. `docs/internal`, `/build`) is anchored to the template root;
+/// - a trailing `/` (e.g. `target/`) only matches directories;
+/// - `*` and `?` stay within one path segment, `**` spans any number of them.
+pub struct ExcludeMatcher {
+  any: GlobSet,
+  dirs_only: GlobSet,
+}
+
+impl ExcludeMatcher {
+  pub fn new(patterns: &[String]) -> Result<Self, SpawnError> {
+    let mut any = GlobSetBuilder::new();
+    let mut dirs_only = GlobSetBuilder::new();
+    for pattern in patterns {
+      let trimmed = pattern.trim();
+      let (body, dir_only) = match trimmed.strip_suffix('/') {
+        Some(body) => (body, true),
+        None => (trimmed, false),
+      };
+      let glob_src = match body.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if body.contains('/') => body.to_string(),
+        None => format!("**/{}", body),
+      };
+      let glob = GlobBuilder::new(&glob_src)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| {
+          SpawnError::GenerationError(format!("Invalid exclude pattern '{}': {}", pattern, e))
+        })?;
+      if dir_only {
+        dirs_only.add(glob);
+      } else {
+        any.add(glob);
+      }
+    }
+    let build = |builder: GlobSetBuilder| {
+      builder
+        .build()
+        .map_err(|e| SpawnError::GenerationError(format!("Invalid exclude patterns: {}", e)))
+    };
+    Ok(Self {
+      any: build(any)?,
+      dirs_only: build(dirs_only)?,
+    })
+  }
+
+  /// `relative_path` is a `path_key` ('/'-separated, relative to the template root).
+  pub fn is_excluded(&self, relative_path: &str, is_dir: bool) -> bool {
+    self.any.is_match(relative_path) || (is_dir && self.dirs_only.is_match(relative_path))
+  }
+
+  /// Whether `relative_path` or one of the directories containing it matches.
+  pub fn covers(&self, relative_path: &str, is_dir: bool) -> bool {
+    self.is_excluded(relative_path, is_dir)
+      || relative_path
+        .match_indices('/')
+        .any(|(end, _)| self.is_excluded(&relative_path[..end], true))
+  }
+}
+
+/// Maps a template-relative path to its output-relative path (filename placeholders).
+fn substitute_relative_path(
+  relative_path: &Path,
+  base_variables: &HashMap<String, String>,
+  all_substitutions: &HashMap<String, String>,
+  placeholder_config: &Option<PlaceholderFilenames>,
+  manifest: &ScaffoldManifest,
+  strict_warnings: bool,
+) -> Result<PathBuf, SpawnError> {
+  if placeholder_config.is_none() {
+    return Ok(relative_path.to_path_buf());
+  }
+  let mut substituted_relative_path = PathBuf::new();
+  for component in relative_path.components() {
+    if let Some(segment_str) = component.as_os_str().to_str() {
+      let substituted_segment = substitute_path_segment(
+        segment_str,
+        base_variables,
+        all_substitutions,
+        placeholder_config,
+        &manifest.variables,
+      );
+      substituted_relative_path.push(substituted_segment);
+    } else {
+      strict_warn(
+        strict_warnings,
+        format!("Non-UTF8 path component: {:?}", component),
+      )?;
+      substituted_relative_path.push(component.as_os_str());
+    }
+  }
+  Ok(substituted_relative_path)
 }
 
 pub fn copy_template_dir(
@@ -112,7 +811,31 @@ pub fn copy_template_dir(
   base_variables: &HashMap<String, String>,
   all_substitutions: &HashMap<String, String>,
   manifest: &ScaffoldManifest,
-) -> Result<(), SpawnError> {
+  options: &CopyOptions,
+) -> Result<CopyReport, SpawnError> {
+  let mut created = Vec::new();
+  copy_template_dir_tracked(
+    template_path,
+    output_path,
+    base_variables,
+    all_substitutions,
+    manifest,
+    options,
+    &mut created,
+  )
+}
+
+/// Like `copy_template_dir`, but appends every directory and file it creates (paths that did
+/// not exist before, parents before children) to `created`, also when it fails partway.
+pub fn copy_template_dir_tracked(
+  template_path: &Path,
+  output_path: &Path,
+  base_variables: &HashMap<String, String>,
+  all_substitutions: &HashMap<String, String>,
+  manifest: &ScaffoldManifest,
+  options: &CopyOptions,
+  created: &mut Vec<PathBuf>,
+) -> Result<CopyReport, SpawnError> {
   debug!(
     "Copying template from {} to {}",
     template_path.display(),
@@ -120,7 +843,7 @@ pub fn copy_template_dir(
   );
 
   // --- Pass 1: Count files respecting conditions ---
-  let base_variables_for_condition = manifest
+  let mut base_variables_for_condition = manifest
     .variables
     .iter()
     .filter_map(|vd| {
@@ -129,16 +852,46 @@ pub fn copy_template_dir(
         .map(|val| (vd.name.clone(), val.clone()))
     })
     .collect::<HashMap<String, String>>();
+  expand_multi_select_members(manifest, &mut base_variables_for_condition);
+  let sensitive = sensitive_names(&manifest.variables, &manifest.derived);
+  let sensitive_placeholders: Vec<&str> = all_substitutions
+    .keys()
+    .filter(|placeholder| sensitive.contains(*placeholder))
+    .map(String::as_str)
+    .collect();
 
-  let exclude_set: HashSet<String> = manifest.exclude.iter().cloned().collect();
+  let exclude_patterns: Vec<String> = manifest.exclude.iter().chain(&options.skip).cloned().collect();
+  let exclude = ExcludeMatcher::new(&exclude_patterns)?;
+  // --only: entries outside it are walked (they may contain matches) but not generated
+  let only = (!options.only.is_empty())
+    .then(|| ExcludeMatcher::new(&options.only))
+    .transpose()?;
+  let selected = |relative_path: &str, is_dir: bool| {
+    only.as_ref().is_none_or(|only| only.covers(relative_path, is_dir))
+  };
+  if let Some(delimiters) = &manifest.content_delimiters {
+    if delimiters.open.is_empty() || delimiters.close.is_empty() {
+      return Err(SpawnError::GenerationError(
+        "contentDelimiters: open and close must not be empty.".to_string(),
+      ));
+    }
+  }
+  // Manifest keys and walked paths are both compared in '/' form
+  let conditional_paths: HashMap<String, &ConditionExpr> = manifest
+    .conditional_paths
+    .iter()
+    .map(|(key, condition)| (normalize_path_key(key), condition))
+    .collect();
+  let mut report = CopyReport::default();
+  let placeholder_config =
+    resolve_placeholder_filenames(&manifest.placeholder_filenames, options)?;
 
+  let progress_unit = options.progress.or(manifest.progress).unwrap_or_default();
   let mut file_count: u64 = 0;
+  let mut total_bytes: u64 = 0;
+  let mut conflict: Option<PathBuf> = None;
   let mut count_walker = WalkDir::new(template_path).into_iter();
-  loop {
-    let entry_result = match count_walker.next() {
-      Some(res) => res,
-      None => break,
-    };
+  while let Some(entry_result) = count_walker.next() {
     let entry = match entry_result {
       Ok(e) => e,
       Err(walk_err) => {
@@ -158,24 +911,27 @@ pub fn copy_template_dir(
       continue;
     }
 
-    if let Some(entry_name) = current_path.file_name().and_then(|n| n.to_str()) {
-      if exclude_set.contains(entry_name) {
-        if entry.file_type().is_dir() {
-          count_walker.skip_current_dir(); // Skip directory contents if dir is excluded
-        }
-        // Skip processing this entry entirely (whether file or dir)
-        continue;
-      }
-    }
-
     let relative_path = match entry.path().strip_prefix(template_path) {
       Ok(p) => p,
       Err(_) => continue,
     };
-    let relative_path_str = relative_path.to_string_lossy().to_string();
+    let relative_path_str = path_key(relative_path);
+
+    if exclude.is_excluded(&relative_path_str, entry.file_type().is_dir()) {
+      if entry.file_type().is_dir() {
+        count_walker.skip_current_dir(); // Skip directory contents if dir is excluded
+      }
+      // Skip processing this entry entirely (whether file or dir)
+      continue;
+    }
     let mut skip_entry = false;
-    if let Some(condition) = manifest.conditional_paths.get(&relative_path_str) {
-      if !evaluate_condition(condition, &base_variables) {
+    if let Some(condition) = conditional_paths.get(&relative_path_str) {
+      // Same variables as the copy pass, so the progress total matches what gets written
+      if !evaluate_condition_expr(
+        condition,
+        &base_variables_for_condition,
+        options.strict_warnings,
+      )? {
         skip_entry = true;
         if entry.file_type().is_dir() {
           count_walker.skip_current_dir();
@@ -183,40 +939,64 @@ pub fn copy_template_dir(
       }
     }
 
-    if !skip_entry && entry.file_type().is_file() {
+    if !skip_entry && entry.file_type().is_file() && selected(&relative_path_str, false) {
       // Skip manifest itself
       if entry
         .path()
         .file_name()
-        .map_or(false, |name| name == "scaffold.yaml")
+        .is_some_and(|name| name == "scaffold.yaml")
       {
         continue;
       }
       file_count += 1;
+      if progress_unit == ProgressUnit::Bytes {
+        total_bytes += entry.metadata().map_or(0, |m| m.len());
+      }
+
+      // Fail before anything is written rather than clobbering part of a project
+      if !options.overwrite_existing && !options.dry_run && conflict.is_none() {
+        let output_entry_path = output_path.join(substitute_relative_path(
+          relative_path,
+          base_variables,
+          all_substitutions,
+          &placeholder_config,
+          manifest,
+          options.strict_warnings,
+        )?);
+        if output_entry_path.symlink_metadata().is_ok() {
+          conflict = Some(output_entry_path);
+        }
+      }
     }
   }
+  if let Some(path) = conflict {
+    return Err(SpawnError::GenerationError(format!(
+      "Refusing to overwrite existing file '{}' (use --force to overwrite existing files).",
+      path.display()
+    )));
+  }
   debug!("Total files to process: {}", file_count);
+  if file_count == 0 {
+    strict_warn(
+      options.strict_warnings,
+      format!(
+        "Template '{}' appears empty: no files to generate besides scaffold.yaml (after `exclude`, `conditionalPaths`, --only and --skip). The output will be empty.",
+        template_path.display()
+      ),
+    )?;
+  }
 
   // --- Setup Progress Bar ---
-  let pb = ProgressBar::new(file_count);
-  pb.set_style(
-    ProgressStyle::default_bar()
-      .template(
-        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}",
-      )
-      .expect("Failed to set progress bar style") // Panic if template is invalid
-      .progress_chars("#>-"),
-  );
+  let pb = match progress_unit {
+    ProgressUnit::Files => copy_progress_bar(file_count, progress_unit),
+    ProgressUnit::Bytes => copy_progress_bar(total_bytes, progress_unit),
+  };
   pb.set_message("Copying files...");
 
-  // --- Pass 2: Copy files with progress ---
+  // --- Pass 2: Create directories, collect files ---
+  let mut pending_files: Vec<PendingFile> = Vec::with_capacity(file_count as usize);
   let mut walker = WalkDir::new(template_path).into_iter();
-  loop {
-    let entry_result = match walker.next() {
-      Some(res) => res,
-      None => break, // End of iteration
-    };
-
+  while let Some(entry_result) = walker.next() {
     let entry = match entry_result {
       Ok(e) => e,
       Err(walk_err) => {
@@ -239,20 +1019,6 @@ pub fn copy_template_dir(
       continue;
     }
 
-    if let Some(entry_name) = current_path.file_name().and_then(|n| n.to_str()) {
-      if exclude_set.contains(entry_name) {
-        debug!(
-          "Excluding entry '{}' based on exclude list.",
-          current_path.display()
-        );
-        if entry.file_type().is_dir() {
-          walker.skip_current_dir(); // Skip directory contents if dir is excluded
-        }
-        // Skip processing this entry entirely (whether file or dir)
-        continue;
-      }
-    }
-
     let relative_path = match current_path.strip_prefix(template_path) {
       Ok(p) => p,
       Err(e) => {
@@ -266,13 +1032,29 @@ pub fn copy_template_dir(
       }
     };
 
+    if exclude.is_excluded(&path_key(relative_path), entry.file_type().is_dir()) {
+      debug!(
+        "Excluding entry '{}' based on exclude list.",
+        current_path.display()
+      );
+      if entry.file_type().is_dir() {
+        walker.skip_current_dir(); // Skip directory contents if dir is excluded
+      }
+      // Skip processing this entry entirely (whether file or dir)
+      continue;
+    }
+
     // --- Conditional Check ---
-    // Convert relative_path to string for map lookup (lossy conversion is okay here)
-    let relative_path_str = relative_path.to_string_lossy().to_string();
+    // Convert relative_path to a '/'-separated string for map lookup (lossy conversion is okay here)
+    let relative_path_str = path_key(relative_path);
     let mut skip_entry = false;
-    if let Some(condition) = manifest.conditional_paths.get(&relative_path_str) {
+    if let Some(condition) = conditional_paths.get(&relative_path_str) {
       trace!("Found condition for path: {}", relative_path_str);
-      if !evaluate_condition(condition, &base_variables_for_condition) {
+      if !evaluate_condition_expr(
+        condition,
+        &base_variables_for_condition,
+        options.strict_warnings,
+      )? {
         info!("Condition not met for '{}', skipping.", relative_path_str);
         skip_entry = true;
         // If it's a directory, skip its contents too
@@ -289,123 +1071,764 @@ pub fn copy_template_dir(
     }
     // --- End Conditional Check ---
 
-    // --- Path Substitution Logic ---
-    let mut substituted_relative_path = PathBuf::new();
-    let placeholder_config = &manifest.placeholder_filenames;
-    if manifest.placeholder_filenames.is_some() {
-      for component in relative_path.components() {
-        if let Some(segment_str) = component.as_os_str().to_str() {
-          let substituted_segment = substitute_path_segment(
-            segment_str,
-            base_variables,
-            all_substitutions,
-            placeholder_config,
-            &manifest.variables,
-          );
-          substituted_relative_path.push(substituted_segment);
-        } else {
-          warn!("Non-UTF8 path component: {:?}", component);
-          substituted_relative_path.push(component.as_os_str());
-        }
-      }
-    } else {
-      substituted_relative_path = relative_path.to_path_buf();
+    if !selected(&relative_path_str, entry.file_type().is_dir()) {
+      trace!("Not selected by --only: {}", relative_path_str);
+      continue;
     }
+
+    let substituted_relative_path = substitute_relative_path(
+      relative_path,
+      base_variables,
+      all_substitutions,
+      &placeholder_config,
+      manifest,
+      options.strict_warnings,
+    )?;
     let output_entry_path = output_path.join(&substituted_relative_path);
-    // --- End Path Substitution Logic ---
 
     if entry.file_type().is_dir() {
+      if options.dry_run {
+        continue;
+      }
       // Use entry.file_type() instead of current_path.is_dir()
       trace!("Creating directory: {}", output_entry_path.display());
-      fs::create_dir_all(&output_entry_path).map_err(|e| SpawnError::OutputDirCreation {
-        path: output_entry_path.clone(),
-        source: e,
+      create_dir_all_tracked(&output_entry_path, created).map_err(|e| {
+        SpawnError::OutputDirCreation {
+          path: output_entry_path.clone(),
+          source: e,
+        }
       })?;
     } else if entry.file_type().is_file() {
       if current_path
         .file_name()
-        .map_or(false, |name| name == "scaffold.yaml")
+        .is_some_and(|name| name == "scaffold.yaml")
       {
         continue;
       }
 
-      pb.set_message(format!("Processing {}", relative_path.display()));
+      // Written below, in parallel, once every directory exists
+      pending_files.push(PendingFile {
+        source_path: current_path.to_path_buf(),
+        generated: GeneratedFile {
+          source: relative_path.to_path_buf(),
+          output: substituted_relative_path,
+          sensitive: uses_placeholder(current_path, &sensitive_placeholders),
+        },
+        is_new: output_entry_path.symlink_metadata().is_err(),
+        output_path: output_entry_path,
+      });
+    } else {
+      log::debug!(
+        "Skipping non-file/non-directory entry: {}",
+        current_path.display()
+      );
+    }
+  }
 
-      if let Some(parent) = output_entry_path.parent() {
-        if !parent.exists() {
-          trace!("Creating parent directory for file: {}", parent.display());
-          fs::create_dir_all(parent)?;
-        }
-      }
+  let leftover_scan = options
+    .strict_placeholders
+    .then(|| LeftoverScan::new(manifest, all_substitutions, &placeholder_config));
 
-      if is_binary(relative_path, manifest) {
-        trace!("Copying binary file to: {}", output_entry_path.display());
-        fs::copy(current_path, &output_entry_path)?;
-      } else {
-        trace!(
-          "Reading and substituting text file: {}",
-          current_path.display()
-        );
-        let content = match fs::read_to_string(current_path) {
-          Ok(s) => s,
-          Err(e) => {
-            // Add specific logging if the error is InvalidData
-            if e.kind() == ErrorKind::InvalidData {
-              error!(
-                      "UTF-8 READ ERROR: Failed to read '{}' as UTF-8 text. Check file encoding or if it should be binary.",
-                      current_path.display()
-                   );
-            } else {
-              // Log other IO errors
-              error!("IO Error reading '{}': {}", current_path.display(), e);
-            }
-            // Propagate the original error
-            return Err(SpawnError::Io(e));
+  // With diff/dry_run, files are rendered into a scratch dir first and compared from there
+  let render_dir = (options.diff || options.dry_run)
+    .then(|| tempfile::Builder::new().prefix("spawnpoint_render_").tempdir())
+    .transpose()?;
+
+  // --- Pass 3: Write files in parallel ---
+  // Files are independent and the substitution maps are read-only. Results come back in
+  // walk order, so the report doesn't depend on thread scheduling. Without
+  // --continue-on-file-error the first failure stops the files not started yet (`None`),
+  // and the first failing file in walk order is reported.
+  type FileResult = Result<(Vec<(usize, String)>, Option<FileChange>), SpawnError>;
+  let aborted = AtomicBool::new(false);
+  let results: Vec<Option<FileResult>> = pending_files
+    .par_iter()
+    .map(|file| {
+      if aborted.load(Ordering::Relaxed) {
+        return None;
+      }
+      pb.set_message(format!("Processing {}", file.generated.source.display()));
+      let render_path = match &render_dir {
+        Some(dir) => dir.path().join(&file.generated.output),
+        None => file.output_path.clone(),
+      };
+      let result = write_template_file(
+        &file.source_path,
+        &file.generated.source,
+        &render_path,
+        all_substitutions,
+        manifest,
+        options,
+        leftover_scan.as_ref(),
+      )
+      .and_then(|leftovers| {
+        let change = options
+          .diff
+          .then(|| diff_generated_file(&file.output_path, &render_path, &file.generated.output))
+          .transpose()?;
+        if render_dir.is_some() && !options.dry_run {
+          if let Some(parent) = file.output_path.parent() {
+            fs::create_dir_all(parent)?;
           }
-        };
-        let substituted_content = substitute_content(&content, all_substitutions, manifest);
-        trace!(
-          "Writing substituted file to: {}",
-          output_entry_path.display()
+          fs::copy(&render_path, &file.output_path)?;
+        }
+        Ok((leftovers, change))
+      });
+      pb.inc(match progress_unit {
+        ProgressUnit::Files => 1,
+        // Template file size, so the bar ends exactly at the total from the counting pass
+        ProgressUnit::Bytes => fs::metadata(&file.source_path).map_or(0, |m| m.len()),
+      });
+      if result.is_err() && !options.continue_on_file_error {
+        aborted.store(true, Ordering::Relaxed);
+      }
+      Some(result)
+    })
+    .collect();
+  // Before any early return, so a failed run still reports what it wrote (even partially)
+  created.extend(
+    pending_files
+      .iter()
+      .filter(|file| file.is_new && file.output_path.symlink_metadata().is_ok())
+      .map(|file| file.output_path.clone()),
+  );
+  for (file, result) in pending_files.into_iter().zip(results) {
+    // Skipped after a failure; that failure is returned below
+    let Some(result) = result else {
+      continue;
+    };
+    match result {
+      Ok((leftovers, change)) => {
+        if let Some(change) = change {
+          report.diffs.push(FileDiff {
+            file: file.generated.output.clone(),
+            change,
+          });
+        }
+        report
+          .leftover_placeholders
+          .extend(leftovers.into_iter().map(|(line, token)| LeftoverPlaceholder {
+            file: file.generated.output.clone(),
+            line,
+            token,
+          }));
+        report.files.push(file.generated);
+      }
+      Err(e) if options.continue_on_file_error => {
+        warn!(
+          "Failed to generate '{}': {}. Continuing (--continue-on-file-error).",
+          file.generated.source.display(),
+          e
         );
-        // Use write instead of write_all for potential large files?
-        // For simplicity, fs::write is fine for typical template sizes.
-        fs::write(&output_entry_path, substituted_content)?;
+        report
+          .failed_files
+          .push((file.generated.source, e.to_string()));
       }
-      pb.inc(1);
-    } else {
-      log::debug!(
-        "Skipping non-file/non-directory entry: {}",
+      Err(e) => {
+        pb.abandon();
+        return Err(e);
+      }
+    }
+  }
+
+  pb.finish_with_message("File processing complete."); // Final message
+  Ok(report)
+}
+
+/// `fs::create_dir_all` that appends the directories it had to create to `created`,
+/// outermost first.
+/// Whether the file at `path` contains any of `placeholders`. Unreadable counts as a match.
+fn uses_placeholder(path: &Path, placeholders: &[&str]) -> bool {
+  if placeholders.is_empty() {
+    return false;
+  }
+  fs::read(path).map_or(true, |content| {
+    let content = String::from_utf8_lossy(&content);
+    placeholders.iter().any(|placeholder| content.contains(placeholder))
+  })
+}
+
+pub(crate) fn create_dir_all_tracked(dir: &Path, created: &mut Vec<PathBuf>) -> std::io::Result<()> {
+  let mut missing: Vec<PathBuf> = dir
+    .ancestors()
+    .take_while(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.symlink_metadata().is_err())
+    .map(Path::to_path_buf)
+    .collect();
+  fs::create_dir_all(dir)?;
+  missing.reverse();
+  created.extend(missing);
+  Ok(())
+}
+
+/// Compares the file already at `existing_path` with the freshly rendered one: a unified
+/// diff (labelled with `display_path`) when both are UTF-8 text without NUL bytes, else
+/// their hashes.
+fn diff_generated_file(
+  existing_path: &Path,
+  rendered_path: &Path,
+  display_path: &Path,
+) -> Result<FileChange, SpawnError> {
+  if existing_path.symlink_metadata().is_err() {
+    return Ok(FileChange::Added);
+  }
+  let existing = fs::read(existing_path)?;
+  let generated = fs::read(rendered_path)?;
+  if existing == generated {
+    return Ok(FileChange::Unchanged);
+  }
+  fn as_text(bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(bytes).ok().filter(|_| !bytes.contains(&0))
+  }
+  match (as_text(&existing), as_text(&generated)) {
+    (Some(existing), Some(generated)) => {
+      let label = path_key(display_path);
+      let diff = similar::TextDiff::from_lines(existing, generated)
+        .unified_diff()
+        .header(&format!("a/{}", label), &format!("b/{}", label))
+        .to_string();
+      Ok(FileChange::Modified(diff))
+    }
+    _ => Ok(FileChange::BinaryModified {
+      existing: crate::state::sha256_hex(&existing),
+      generated: crate::state::sha256_hex(&generated),
+    }),
+  }
+}
+
+/// A file collected during the walk, written in the parallel pass of `copy_template_dir`.
+struct PendingFile {
+  source_path: PathBuf,
+  generated: GeneratedFile,
+  output_path: PathBuf,
+  /// Nothing existed at `output_path` before this run.
+  is_new: bool,
+}
+
+/// Writes a single template file to its output path, substituting content where applicable.
+/// With a `leftover_scan`, returns the placeholder-like tokens left in the written text.
+fn write_template_file(
+  current_path: &Path,
+  relative_path: &Path,
+  output_entry_path: &Path,
+  all_substitutions: &HashMap<String, String>,
+  manifest: &ScaffoldManifest,
+  options: &CopyOptions,
+  leftover_scan: Option<&LeftoverScan>,
+) -> Result<Vec<(usize, String)>, SpawnError> {
+  let mut leftovers = Vec::new();
+  if let Some(parent) = output_entry_path.parent() {
+    if !parent.exists() {
+      trace!("Creating parent directory for file: {}", parent.display());
+      fs::create_dir_all(parent)?;
+    }
+  }
+
+  let detected_binary = !is_binary(relative_path, manifest)
+    && manifest.autodetect_binary
+    && sniff_binary(current_path)?;
+  if detected_binary {
+    debug!(
+      "'{}' looks binary (NUL byte or invalid UTF-8); copying it verbatim.",
+      relative_path.display()
+    );
+  }
+  if detected_binary || is_binary(relative_path, manifest) {
+    let replace_bytes = manifest
+      .replace_in_binary
+      .iter()
+      .chain(options.replace_in_binary.iter())
+      .any(|p| path_key(p) == path_key(relative_path));
+    if replace_bytes {
+      trace!(
+        "Substituting placeholders in binary file: {}",
         current_path.display()
       );
+      let content = fs::read(current_path)?;
+      fs::write(
+        output_entry_path,
+        substitute_bytes(&content, all_substitutions),
+      )?;
+    } else {
+      trace!("Copying binary file to: {}", output_entry_path.display());
+      fs::copy(current_path, output_entry_path)?;
     }
+  } else {
+    let strip_bom = !options.preserve_bom && manifest.bom == BomHandling::Strip;
+    let max_bytes = manifest
+      .max_substitution_bytes
+      .unwrap_or(DEFAULT_MAX_SUBSTITUTION_BYTES);
+    if fs::metadata(current_path)?.len() > max_bytes {
+      debug!(
+        "'{}' is larger than {} bytes; substituting line by line.",
+        relative_path.display(),
+        max_bytes
+      );
+      return stream_substitute_file(
+        current_path,
+        output_entry_path,
+        all_substitutions,
+        manifest,
+        strip_bom,
+        leftover_scan,
+      );
+    }
+    trace!(
+      "Reading and substituting text file: {}",
+      current_path.display()
+    );
+    let content = match fs::read_to_string(current_path) {
+      Ok(s) => s,
+      Err(e) => {
+        // Add specific logging if the error is InvalidData
+        if e.kind() == ErrorKind::InvalidData {
+          error!(
+            "UTF-8 READ ERROR: Failed to read '{}' as UTF-8 text. Check file encoding or if it should be binary.",
+            current_path.display()
+          );
+        } else {
+          // Log other IO errors
+          error!("IO Error reading '{}': {}", current_path.display(), e);
+        }
+        // Propagate the original error
+        return Err(SpawnError::Io(e));
+      }
+    };
+    let content = match content.strip_prefix(UTF8_BOM) {
+      Some(without_bom) if strip_bom => {
+        debug!("Stripping UTF-8 BOM from '{}'", relative_path.display());
+        without_bom.to_string()
+      }
+      _ => content,
+    };
+    let substituted_content = substitute_content(&content, all_substitutions, manifest)?;
+    if let Some(scan) = leftover_scan {
+      leftovers = scan.scan_text(&substituted_content, 1);
+    }
+    trace!(
+      "Writing substituted file to: {}",
+      output_entry_path.display()
+    );
+    // Use write instead of write_all for potential large files?
+    // For simplicity, fs::write is fine for typical template sizes.
+    fs::write(output_entry_path, substituted_content)?;
   }
+  Ok(leftovers)
+}
 
-  pb.finish_with_message("File processing complete."); // Final message
+/// Substitutes a large text file one line at a time, so memory use stays bounded.
+/// Lines that aren't valid UTF-8 still get their placeholders replaced byte-wise and are
+/// otherwise copied verbatim (no `${ENV}` expansion), instead of failing the whole file.
+/// A file that substitution would leave unchanged is copied as is.
+fn stream_substitute_file(
+  current_path: &Path,
+  output_entry_path: &Path,
+  all_substitutions: &HashMap<String, String>,
+  manifest: &ScaffoldManifest,
+  strip_bom: bool,
+  leftover_scan: Option<&LeftoverScan>,
+) -> Result<Vec<(usize, String)>, SpawnError> {
+  if let Some(leftovers) =
+    scan_unchanged_file(current_path, all_substitutions, manifest, strip_bom, leftover_scan)?
+  {
+    trace!("'{}' has nothing to substitute; copying it", current_path.display());
+    fs::copy(current_path, output_entry_path)?;
+    return Ok(leftovers);
+  }
+  let mut reader = BufReader::new(fs::File::open(current_path)?);
+  let mut writer = BufWriter::new(fs::File::create(output_entry_path)?);
+  let mut line = Vec::new();
+  let mut first_line = true;
+  let mut line_number = 0;
+  let mut leftovers = Vec::new();
+  loop {
+    line.clear();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+      break;
+    }
+    if first_line && strip_bom && line.starts_with(UTF8_BOM_BYTES) {
+      line.drain(..UTF8_BOM_BYTES.len());
+    }
+    first_line = false;
+    line_number += 1;
+    match std::str::from_utf8(&line) {
+      Ok(text) => {
+        let substituted = substitute_content(text, all_substitutions, manifest)?;
+        if let Some(scan) = leftover_scan {
+          leftovers.extend(scan.scan_text(&substituted, line_number));
+        }
+        writer.write_all(substituted.as_bytes())?
+      }
+      Err(_) => writer.write_all(&substitute_bytes(&line, all_substitutions))?,
+    }
+  }
+  writer.flush()?;
+  Ok(leftovers)
+}
+
+/// Reads a large text file line by line to see whether `stream_substitute_file` would change
+/// it. Returns the leftover scan of the unchanged file, or `None` at the first line that
+/// substitution (or BOM stripping) would change.
+fn scan_unchanged_file(
+  current_path: &Path,
+  all_substitutions: &HashMap<String, String>,
+  manifest: &ScaffoldManifest,
+  strip_bom: bool,
+  leftover_scan: Option<&LeftoverScan>,
+) -> Result<Option<Vec<(usize, String)>>, SpawnError> {
+  let mut reader = BufReader::new(fs::File::open(current_path)?);
+  let mut line = Vec::new();
+  let mut line_number = 0;
+  let mut leftovers = Vec::new();
+  loop {
+    line.clear();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+      break;
+    }
+    if line_number == 0 && strip_bom && line.starts_with(UTF8_BOM_BYTES) {
+      return Ok(None);
+    }
+    line_number += 1;
+    match std::str::from_utf8(&line) {
+      Ok(text) => {
+        if substitute_content(text, all_substitutions, manifest)? != text {
+          return Ok(None);
+        }
+        if let Some(scan) = leftover_scan {
+          leftovers.extend(scan.scan_text(text, line_number));
+        }
+      }
+      Err(_) => {
+        if substitute_bytes(&line, all_substitutions) != line {
+          return Ok(None);
+        }
+      }
+    }
+  }
+  Ok(Some(leftovers))
+}
+
+/// Warns about placeholders of skipped variables (`when` was false) that are still present
+/// in the generated output, either in a path or in a text file's content.
+/// Skipped variables are never substituted, so any hit means the template references a
+/// variable it didn't ask for — usually a missing `conditionalPaths` entry.
+pub fn report_skipped_placeholders(
+  output_root: &Path,
+  report: &CopyReport,
+  manifest: &ScaffoldManifest,
+  base_variables: &HashMap<String, String>,
+  options: &CopyOptions,
+) -> Result<(), SpawnError> {
+  let placeholder_config =
+    resolve_placeholder_filenames(&manifest.placeholder_filenames, options)?.unwrap_or_default();
+  let mut skipped_placeholders: Vec<(&str, String)> = Vec::new();
+  for var_def in manifest
+    .variables
+    .iter()
+    .filter(|vd| vd.when.is_some() && !base_variables.contains_key(&vd.name))
+  {
+    skipped_placeholders.push((&var_def.name, var_def.placeholder_value.clone()));
+    skipped_placeholders.push((
+      &var_def.name,
+      format!(
+        "{}{}{}",
+        placeholder_config.prefix, var_def.name, placeholder_config.suffix
+      ),
+    ));
+    for placeholder in var_def.transformations.values() {
+      skipped_placeholders.push((&var_def.name, placeholder.clone()));
+    }
+  }
+  if skipped_placeholders.is_empty() {
+    return Ok(());
+  }
+
+  for generated in &report.files {
+    let path_str = generated.output.to_string_lossy();
+    // Non-UTF8 (binary) content is not scanned
+    let content = fs::read(output_root.join(&generated.output))
+      .ok()
+      .and_then(|bytes| String::from_utf8(bytes).ok());
+    for (var_name, placeholder) in &skipped_placeholders {
+      let in_path = path_str.contains(placeholder.as_str());
+      let in_content = content.as_deref().is_some_and(|c| c.contains(placeholder.as_str()));
+      if in_path || in_content {
+        strict_warn(
+          options.strict_warnings,
+          format!(
+            "Placeholder '{}' of skipped variable '{}' left in {} of '{}'.",
+            placeholder,
+            var_name,
+            if in_path { "the path" } else { "the content" },
+            generated.output.display()
+          ),
+        )?;
+      }
+    }
+  }
   Ok(())
 }
 
+/// Dry scan of a template: which of `candidates` appear in some template file's relative path
+/// or (text) content. Walks like `copy_template_dir` (skipping `exclude`d entries and the
+/// manifest) but ignores `conditionalPaths`, so every file that could be generated counts.
+/// Binary files are only scanned when listed in `replaceInBinary`.
+pub fn find_placeholders_in_template(
+  template_path: &Path,
+  manifest: &ScaffoldManifest,
+  candidates: &[&str],
+) -> Result<HashSet<String>, SpawnError> {
+  let exclude = ExcludeMatcher::new(&manifest.exclude)?;
+  let byte_level: HashSet<String> = manifest.replace_in_binary.iter().map(|p| path_key(p)).collect();
+  let mut found = HashSet::new();
+  let mut walker = WalkDir::new(template_path).min_depth(1).into_iter();
+  while let Some(entry_result) = walker.next() {
+    let entry = match entry_result {
+      Ok(e) => e,
+      Err(walk_err) => {
+        warn!("Error accessing path during scan: {}", walk_err);
+        continue;
+      }
+    };
+    let Ok(relative_path) = entry.path().strip_prefix(template_path) else {
+      continue;
+    };
+    let relative_key = path_key(relative_path);
+    if exclude.is_excluded(&relative_key, entry.file_type().is_dir()) {
+      if entry.file_type().is_dir() {
+        walker.skip_current_dir();
+      }
+      continue;
+    }
+    if relative_key == "scaffold.yaml" {
+      continue;
+    }
+    found.extend(candidates.iter().filter(|c| relative_key.contains(**c)).map(|c| c.to_string()));
+    if !entry.file_type().is_file() {
+      continue;
+    }
+
+    let binary = is_binary(relative_path, manifest)
+      || (manifest.autodetect_binary && sniff_binary(entry.path())?);
+    if binary && !byte_level.contains(&relative_key) {
+      continue;
+    }
+    let content = fs::read(entry.path())?;
+    let content = String::from_utf8_lossy(&content);
+    let tokens = manifest.content_delimiters.as_ref().map(|d| delimited_tokens(&content, d));
+    found.extend(
+      candidates
+        .iter()
+        .filter(|c| match &tokens {
+          Some(tokens) => tokens.contains(**c),
+          None => content.contains(**c),
+        })
+        .map(|c| c.to_string()),
+    );
+  }
+  Ok(found)
+}
+
+/// Name of the derived variable telling whether `choice` was selected in a `multiSelect` variable.
+pub fn multi_select_member(var_name: &str, choice: &str) -> String {
+  format!("{}.{}", var_name, choice)
+}
+
+/// Records each choice of a `multiSelect` variable as `<name>.<choice>` = `"true"`/`"false"`,
+/// so conditions can test membership (see `evaluate_condition`).
+pub fn insert_multi_select_members(
+  var_def: &VariableDefinition,
+  value: &str,
+  base_variables: &mut HashMap<String, String>,
+) {
+  if var_def.var_type != VariableType::MultiSelect {
+    return;
+  }
+  let selected: HashSet<&str> = value.split(var_def.separator()).collect();
+  for choice in &var_def.choices {
+    base_variables.insert(
+      multi_select_member(&var_def.name, choice),
+      selected.contains(choice.as_str()).to_string(),
+    );
+  }
+}
+
+/// Adds the membership variables of every `multiSelect` variable that has a value,
+/// e.g. for `testVariables` or variables restored from a state file.
+pub fn expand_multi_select_members(
+  manifest: &ScaffoldManifest,
+  base_variables: &mut HashMap<String, String>,
+) {
+  for var_def in &manifest.variables {
+    if let Some(value) = base_variables.get(&var_def.name).cloned() {
+      insert_multi_select_members(var_def, &value, base_variables);
+    }
+  }
+}
+
+#[cfg(feature = "regex")]
+fn condition_regex_matches(condition: &Condition, actual_value: &str) -> Result<bool, SpawnError> {
+  let regex = regex::Regex::new(&condition.value).map_err(|e| {
+    SpawnError::GenerationError(format!(
+      "Invalid regex in condition on '{}': {}",
+      condition.variable, e
+    ))
+  })?;
+  Ok(regex.is_match(actual_value))
+}
+
+#[cfg(not(feature = "regex"))]
+fn condition_regex_matches(condition: &Condition, _actual_value: &str) -> Result<bool, SpawnError> {
+  Err(SpawnError::GenerationError(format!(
+    "Condition on '{}' uses `matches`, which requires the `regex` feature.",
+    condition.variable
+  )))
+}
+
+/// Evaluates a condition group: `all` needs every member, `any` at least one
+/// (an empty `all` is met, an empty `any` is not). Members are evaluated in order and
+/// evaluation stops as soon as the result is known.
+pub fn evaluate_condition_expr(
+  expr: &ConditionExpr,
+  base_variables: &HashMap<String, String>,
+  strict_warnings: bool,
+) -> Result<bool, SpawnError> {
+  match expr {
+    ConditionExpr::All { all } => {
+      for member in all {
+        if !evaluate_condition_expr(member, base_variables, strict_warnings)? {
+          return Ok(false);
+        }
+      }
+      Ok(true)
+    }
+    ConditionExpr::Any { any } => {
+      for member in any {
+        if evaluate_condition_expr(member, base_variables, strict_warnings)? {
+          return Ok(true);
+        }
+      }
+      Ok(false)
+    }
+    ConditionExpr::Single(condition) => evaluate_condition(condition, base_variables, strict_warnings),
+  }
+}
+
 /// Evaluates a condition based on the provided base variables.
-fn evaluate_condition(condition: &Condition, base_variables: &HashMap<String, String>) -> bool {
+/// For `multiSelect` variables, `variable: features.auth` tests whether `auth` was selected
+/// (compare against `"true"`/`"false"`); the joined value itself is compared as a whole.
+/// A condition on a variable without a value is never met, whatever its operator.
+pub fn evaluate_condition(
+  condition: &Condition,
+  base_variables: &HashMap<String, String>,
+  strict_warnings: bool,
+) -> Result<bool, SpawnError> {
   match base_variables.get(&condition.variable) {
-    Some(actual_value) => actual_value.eq_ignore_ascii_case(&condition.value),
+    Some(actual_value) => match condition.operator {
+      ConditionOperator::Equals => Ok(actual_value.eq_ignore_ascii_case(&condition.value)),
+      ConditionOperator::NotEquals => Ok(!actual_value.eq_ignore_ascii_case(&condition.value)),
+      ConditionOperator::In => Ok(
+        condition
+          .candidates()
+          .iter()
+          .any(|c| actual_value.eq_ignore_ascii_case(c)),
+      ),
+      ConditionOperator::NotIn => Ok(
+        !condition
+          .candidates()
+          .iter()
+          .any(|c| actual_value.eq_ignore_ascii_case(c)),
+      ),
+      ConditionOperator::Matches => condition_regex_matches(condition, actual_value),
+    },
     None => {
+      strict_warn(
+        strict_warnings,
+        format!(
+          "Conditional variable '{}' not found in provided variables.",
+          condition.variable
+        ),
+      )?;
+      Ok(false) // Condition cannot be met if variable doesn't exist
+    }
+  }
+}
+
+/// Builds the file-copy progress bar, styled by `SPAWNPOINT_PROGRESS_TEMPLATE`:
+/// unset/`default`, `minimal`, `none`/`off` (hidden), or a custom indicatif template.
+/// `default` and `minimal` show byte counts for `ProgressUnit::Bytes`.
+/// Always hidden when the info log level is disabled.
+/// An invalid custom template falls back to the default style with a warning.
+fn copy_progress_bar(len: u64, unit: ProgressUnit) -> ProgressBar {
+  // Progress is info-level output: hide it along with the info logs (`--quiet`)
+  if !log::log_enabled!(log::Level::Info) {
+    return ProgressBar::hidden();
+  }
+  let requested = std::env::var(PROGRESS_TEMPLATE_ENV).ok();
+  let (default_template, minimal_template) = match unit {
+    ProgressUnit::Files => (DEFAULT_PROGRESS_TEMPLATE, MINIMAL_PROGRESS_TEMPLATE),
+    ProgressUnit::Bytes => (DEFAULT_BYTES_PROGRESS_TEMPLATE, MINIMAL_BYTES_PROGRESS_TEMPLATE),
+  };
+  let template = match requested.as_deref().map(str::trim) {
+    None | Some("") | Some("default") => default_template,
+    Some("minimal") => minimal_template,
+    Some("none") | Some("off") => return ProgressBar::hidden(),
+    Some(custom) => custom,
+  };
+
+  let style = ProgressStyle::default_bar()
+    .template(template)
+    .or_else(|e| {
       warn!(
-        "Conditional variable '{}' not found in provided variables.",
-        condition.variable
+        "Invalid {} '{}': {}. Using the default progress style.",
+        PROGRESS_TEMPLATE_ENV, template, e
       );
-      false // Condition cannot be met if variable doesn't exist
-    }
+      ProgressStyle::default_bar().template(default_template)
+    })
+    .unwrap_or_else(|_| ProgressStyle::default_bar());
+  let pb = ProgressBar::new(len);
+  pb.set_style(style.progress_chars("#>-"));
+  pb
+}
+
+/// Normalizes a manifest path key to '/' separators, so keys authored on any OS match.
+fn normalize_path_key(key: &str) -> String {
+  key.replace('\\', "/")
+}
+
+/// A relative path as a '/'-separated string, for comparison with manifest path keys.
+/// `Path::to_string_lossy` alone would keep backslash separators on Windows.
+pub(crate) fn path_key(path: &Path) -> String {
+  normalize_path_key(&path.to_string_lossy())
+}
+
+/// Bytes inspected by `sniff_binary`.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Content check for files not registered as binary: a NUL byte or invalid UTF-8 in the
+/// first `BINARY_SNIFF_BYTES` means binary. A multi-byte character cut off at the end of the
+/// sample doesn't count as invalid.
+fn sniff_binary(path: &Path) -> Result<bool, SpawnError> {
+  let mut sample = Vec::with_capacity(BINARY_SNIFF_BYTES);
+  fs::File::open(path)?
+    .take(BINARY_SNIFF_BYTES as u64)
+    .read_to_end(&mut sample)?;
+  if sample.contains(&0) {
+    return Ok(true);
   }
+  Ok(match std::str::from_utf8(&sample) {
+    Ok(_) => false,
+    Err(e) => e.error_len().is_some(),
+  })
 }
 
 /// Checks if a path (relative to the template root) should be treated as binary.
 fn is_binary(relative_path: &Path, manifest: &ScaffoldManifest) -> bool {
   // Check by specific file path first
+  let relative_key = path_key(relative_path);
   if manifest
     .binary_files
     .iter()
-    .any(|bin_file| bin_file == relative_path)
+    .any(|bin_file| path_key(bin_file) == relative_key)
   {
     return true;
   }
@@ -423,21 +1846,213 @@ fn is_binary(relative_path: &Path, manifest: &ScaffoldManifest) -> bool {
     }
   }
 
-  false
+  false
+}
+
+/// A run of substituted content: template text as written, or a placeholder's value.
+enum Piece<'a> {
+  Template(&'a [u8]),
+  Value(&'a [u8]),
+}
+
+/// Replaces placeholders inside raw bytes (for binary files opted into substitution).
+/// Scans left to right once, trying longer placeholders first, so replaced values are never re-scanned.
+/// Replacement values may differ in length from their placeholders.
+fn substitute_bytes(content: &[u8], substitutions: &HashMap<String, String>) -> Vec<u8> {
+  concat_pieces(placeholder_pieces(content, substitutions))
+}
+
+/// `content` split into template text and the values of the placeholders found in it, for
+/// `substitute_bytes`.
+fn placeholder_pieces<'a>(content: &'a [u8], substitutions: &'a HashMap<String, String>) -> Vec<Piece<'a>> {
+  let mut placeholders: Vec<(&[u8], &[u8])> = substitutions
+    .iter()
+    .filter(|(placeholder, _)| !placeholder.is_empty())
+    .map(|(placeholder, value)| (placeholder.as_bytes(), value.as_bytes()))
+    .collect();
+  placeholders.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(b.0)));
+  // Most positions can't start a placeholder; skip them without trying each one
+  let mut starts_placeholder = [false; 256];
+  for (placeholder, _) in &placeholders {
+    starts_placeholder[placeholder[0] as usize] = true;
+  }
+
+  let mut pieces = Vec::new();
+  let mut text_start = 0;
+  let mut pos = 0;
+  while pos < content.len() {
+    let matched = if starts_placeholder[content[pos] as usize] {
+      placeholders
+        .iter()
+        .find(|(placeholder, _)| content[pos..].starts_with(placeholder))
+    } else {
+      None
+    };
+    match matched {
+      Some((placeholder, value)) => {
+        pieces.push(Piece::Template(&content[text_start..pos]));
+        pieces.push(Piece::Value(value));
+        pos += placeholder.len();
+        text_start = pos;
+      }
+      None => pos += 1,
+    }
+  }
+  pieces.push(Piece::Template(&content[text_start..]));
+  pieces
+}
+
+fn concat_pieces(pieces: Vec<Piece<'_>>) -> Vec<u8> {
+  let mut result = Vec::new();
+  for piece in pieces {
+    let (Piece::Template(bytes) | Piece::Value(bytes)) = piece;
+    result.extend_from_slice(bytes);
+  }
+  result
+}
+
+/// `substitute_bytes` for text: one left-to-right scan taking the longest placeholder that
+/// matches at each position, so values are never re-scanned and map order doesn't matter.
+fn replace_placeholders(content: &str, substitutions: &HashMap<String, String>) -> String {
+  bytes_to_string(substitute_bytes(content.as_bytes(), substitutions))
+}
+
+// A UTF-8 placeholder can only match at a character boundary of UTF-8 content, so pieces of
+// UTF-8 content and their concatenation are valid UTF-8
+fn bytes_to_string(bytes: Vec<u8>) -> String {
+  match String::from_utf8(bytes) {
+    Ok(result) => result,
+    Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+  }
+}
+
+/// Replaces placeholders in text content (see `contentDelimiters`). With `expandEnvInContent`,
+/// `${ENV}` references in the template text are expanded too; substituted values are kept
+/// as entered.
+pub fn substitute_content(
+  content: &str,
+  substitutions: &HashMap<String, String>,
+  manifest: &ScaffoldManifest,
+) -> Result<String, SpawnError> {
+  let pieces = match &manifest.content_delimiters {
+    Some(delimiters) => delimited_pieces(content, substitutions, delimiters),
+    // Legacy: every literal occurrence of a placeholder
+    None => placeholder_pieces(content.as_bytes(), substitutions),
+  };
+  if !manifest.expand_env_in_content {
+    return Ok(bytes_to_string(concat_pieces(pieces)));
+  }
+  let mut result = String::with_capacity(content.len());
+  for piece in pieces {
+    match piece {
+      Piece::Template(text) => {
+        let text = String::from_utf8_lossy(text);
+        let expanded =
+          expand_env_vars(&text, manifest.undefined_env, |name| std::env::var(name).ok())
+            .map_err(SpawnError::GenerationError)?;
+        result.push_str(&expanded);
+      }
+      Piece::Value(value) => result.push_str(&String::from_utf8_lossy(value)),
+    }
+  }
+  Ok(result)
+}
+
+/// The (trimmed) insides of every `<open>...<close>` token in `content`.
+fn delimited_tokens(content: &str, delimiters: &ContentDelimiters) -> HashSet<String> {
+  let (open, close) = (delimiters.open.as_str(), delimiters.close.as_str());
+  let mut tokens = HashSet::new();
+  let mut rest = content;
+  while let Some(start) = rest.find(open) {
+    rest = &rest[start + open.len()..];
+    let Some(len) = rest.find(close) else {
+      break;
+    };
+    tokens.insert(rest[..len].trim().to_string());
+  }
+  tokens
 }
 
-/// Performs simple string replacement based on manifest variables and placeholder values.
-pub fn substitute_content(
+/// Single pass over `content`, replacing each `<open>placeholder<close>` token whose (trimmed)
+/// inside is a known placeholder. Unknown tokens and bare placeholders are left alone, and
+/// substituted values are never re-scanned.
+fn delimited_pieces<'a>(
+  content: &'a str,
+  substitutions: &'a HashMap<String, String>,
+  delimiters: &ContentDelimiters,
+) -> Vec<Piece<'a>> {
+  let (open, close) = (delimiters.open.as_str(), delimiters.close.as_str());
+  let mut pieces = Vec::new();
+  let mut text_start = 0;
+  let mut pos = 0;
+  while let Some(start) = content[pos..].find(open).map(|offset| pos + offset) {
+    let inner_start = start + open.len();
+    let Some(len) = content[inner_start..].find(close) else {
+      break;
+    };
+    match substitutions.get(content[inner_start..inner_start + len].trim()) {
+      Some(value) => {
+        pieces.push(Piece::Template(&content.as_bytes()[text_start..start]));
+        pieces.push(Piece::Value(value.as_bytes()));
+        pos = inner_start + len + close.len();
+        text_start = pos;
+      }
+      // Keep the opening delimiter and look for a token after it (e.g. `{{{{name}}`)
+      None => pos = inner_start,
+    }
+  }
+  pieces.push(Piece::Template(&content.as_bytes()[text_start..]));
+  pieces
+}
+
+/// Expands `${NAME}` from the process environment (in template text, not substituted values).
+/// `$${NAME}` is an escape that emits a literal `${NAME}`; anything that isn't
+/// `${` + identifier + `}` is left untouched. With `UndefinedEnv::Error`, an unset
+/// variable fails with a message naming it.
+pub(crate) fn expand_env_vars(
   content: &str,
-  substitutions: &HashMap<String, String>,
-  _manifest: &ScaffoldManifest, // Keep for potential future use, but not needed now
-) -> String {
-  let mut current_content = content.to_string();
-  // Iterate directly over the substitutions map
-  for (placeholder, value) in substitutions {
-    current_content = current_content.replace(placeholder, value);
+  undefined: UndefinedEnv,
+  lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+  let mut result = String::with_capacity(content.len());
+  let mut rest = content;
+  while let Some(pos) = rest.find("${") {
+    // `$${` escape: keep one `$` and the literal `${`
+    if pos > 0 && rest.as_bytes()[pos - 1] == b'$' {
+      result.push_str(&rest[..pos - 1]);
+      result.push_str("${");
+      rest = &rest[pos + 2..];
+      continue;
+    }
+    result.push_str(&rest[..pos]);
+    let after = &rest[pos + 2..];
+    let name_len = after
+      .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+      .unwrap_or(after.len());
+    let name = &after[..name_len];
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+    if !is_identifier || !after[name_len..].starts_with('}') {
+      result.push_str("${");
+      rest = after;
+      continue;
+    }
+    match lookup(name) {
+      Some(value) => result.push_str(&value),
+      None if undefined == UndefinedEnv::Keep => {
+        trace!("Environment variable '{}' is not set; leaving ${{{}}} as-is", name, name);
+        result.push_str(&rest[pos..pos + 2 + name_len + 1]);
+      }
+      None => {
+        return Err(format!(
+          "Environment variable '{}' (referenced as ${{{}}}) is not set.",
+          name, name
+        ))
+      }
+    }
+    rest = &after[name_len + 1..];
   }
-  current_content
+  result.push_str(rest);
+  Ok(result)
 }
 
 /// Performs variable substitution on a single path segment (filename or directory name).
@@ -454,21 +2069,19 @@ fn substitute_path_segment(
     return segment.to_string();
   };
 
-  let mut current_segment = segment.to_string();
-
-  // --- Pass 1: Handle __VAR_...__ placeholders ---
-  // These are substituted with the *base* variable value.
+  // --- __VAR_...__ markers ---
+  // These are substituted with the *base* variable value (the raw user input).
+  let mut markers = HashMap::new();
   for var_def in variable_definitions {
     let var_marker = format!("{}{}{}", config.prefix, var_def.name, config.suffix);
-    if current_segment.contains(&var_marker) {
+    if segment.contains(&var_marker) {
       if let Some(base_value) = base_variables.get(&var_def.name) {
-        // Replace __VAR_name__ with the raw user input for 'name'
-        current_segment = current_segment.replace(&var_marker, base_value);
         trace!(
-          "Path Segment Subst (Pass 1): Replaced '{}' with base value '{}'",
+          "Path Segment Subst: Marker '{}' -> base value '{}'",
           var_marker,
           base_value
         );
+        markers.insert(var_marker, base_value.clone());
       } else {
         warn!(
           "Variable '{}' used in path marker '{}' but not found in base variables map.",
@@ -478,22 +2091,18 @@ fn substitute_path_segment(
     }
   }
 
-  // --- Pass 2: Handle ALL other placeholders (including transformations and direct base placeholders) ---
-  // This uses the pre-computed map of all placeholders (e.g., __PascalName__, --kebab-case--, --base-placeholder--)
-  // to their final transformed/base values.
-  for (placeholder, final_value) in all_substitutions {
-    // Directly replace any remaining occurrences of placeholders from the comprehensive map.
-    // This naturally handles transformation placeholders and any direct base placeholders
-    // that weren't substituted via the __VAR_ mechanism in Pass 1.
-    if current_segment.contains(placeholder) {
-      trace!(
-        "Path Segment Subst (Pass 2): Replacing placeholder '{}' with value '{}'",
-        placeholder,
-        final_value
-      );
-      current_segment = current_segment.replace(placeholder, final_value);
-    }
-  }
+  // --- Markers and ALL other placeholders in one pass ---
+  // `all_substitutions` maps every placeholder (e.g., __PascalName__, --kebab-case--,
+  // --base-placeholder--) to its final value. Scanning once, longest placeholder first,
+  // means a shorter placeholder that is part of a longer one (`__Name__` in
+  // `__NameSuffix__`) can't break it, and substituted values are never re-scanned.
+  let current_segment = if markers.is_empty() {
+    replace_placeholders(segment, all_substitutions)
+  } else {
+    let mut substitutions = all_substitutions.clone();
+    substitutions.extend(markers);
+    replace_placeholders(segment, &substitutions)
+  };
 
   // Debug log the final result
   if segment != current_segment {
@@ -506,32 +2115,150 @@ fn substitute_path_segment(
   current_segment
 }
 
+/// Where step output is logged without `--step-log-dir`, relative to the project directory.
+pub const DEFAULT_STEP_LOG_DIR: &str = ".spawnpoint/logs";
+
+/// Directory collecting the output of every executed step: `<step>.stdout`/`<step>.stderr`
+/// files for `--step-log-dir`, or one `<step>.log` per step in the default location.
+#[derive(Debug)]
+pub struct StepLogDir {
+  dir: PathBuf,
+  /// Write stdout and stderr into one `<step>.log` instead of separate files.
+  combined: bool,
+  /// File stems already written, so repeated step names get `-2`, `-3`, ... suffixes.
+  /// Behind a mutex because parallel validation steps write concurrently.
+  used_stems: Mutex<HashSet<String>>,
+}
+
+impl StepLogDir {
+  pub fn create(dir: &Path) -> Result<Self, SpawnError> {
+    fs::create_dir_all(dir).map_err(|e| SpawnError::OutputDirCreation {
+      path: dir.to_path_buf(),
+      source: e,
+    })?;
+    Ok(StepLogDir {
+      dir: dir.to_path_buf(),
+      combined: false,
+      used_stems: Mutex::new(HashSet::new()),
+    })
+  }
+
+  /// The default logs of a run in `project_dir` (`.spawnpoint/logs`), created once a step runs.
+  pub fn default_in(project_dir: &Path) -> Self {
+    StepLogDir {
+      dir: project_dir.join(DEFAULT_STEP_LOG_DIR),
+      combined: true,
+      used_stems: Mutex::new(HashSet::new()),
+    }
+  }
+
+  /// `--step-log-dir` if given, else the default logs in `project_dir`.
+  pub fn for_run(step_log_dir: Option<&Path>, project_dir: &Path) -> Result<Self, SpawnError> {
+    match step_log_dir {
+      Some(dir) => StepLogDir::create(dir),
+      None => Ok(StepLogDir::default_in(project_dir)),
+    }
+  }
+
+  /// Writes a step's captured output, returning the written file paths (stderr last).
+  fn write(&self, step_name: &str, output: &Output) -> Result<Vec<PathBuf>, SpawnError> {
+    let base: String = step_name
+      .trim()
+      .chars()
+      .map(|c| {
+        if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+          c
+        } else {
+          '_'
+        }
+      })
+      .collect::<String>()
+      .trim_start_matches('.')
+      .to_string();
+    let base = if base.is_empty() { "step".to_string() } else { base };
+
+    let stem = {
+      let mut used_stems = self.used_stems.lock().unwrap_or_else(|e| e.into_inner());
+      let mut stem = base.clone();
+      let mut n = 2;
+      while used_stems.contains(&stem) {
+        stem = format!("{}-{}", base, n);
+        n += 1;
+      }
+      used_stems.insert(stem.clone());
+      stem
+    };
+
+    fs::create_dir_all(&self.dir).map_err(|e| SpawnError::OutputDirCreation {
+      path: self.dir.clone(),
+      source: e,
+    })?;
+    if self.combined {
+      let log_path = self.dir.join(format!("{}.log", stem));
+      let mut log = Vec::with_capacity(output.stdout.len() + output.stderr.len() + 32);
+      for (label, bytes) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        log.extend_from_slice(format!("--- {} ---\n", label).as_bytes());
+        log.extend_from_slice(bytes);
+        if !bytes.is_empty() && !bytes.ends_with(b"\n") {
+          log.push(b'\n');
+        }
+      }
+      fs::write(&log_path, log)?;
+      return Ok(vec![log_path]);
+    }
+    let stdout_path = self.dir.join(format!("{}.stdout", stem));
+    let stderr_path = self.dir.join(format!("{}.stderr", stem));
+    fs::write(&stdout_path, &output.stdout)?;
+    fs::write(&stderr_path, &output.stderr)?;
+    Ok(vec![stdout_path, stderr_path])
+  }
+}
+
 /// Executes a validation step command.
+/// With a `step_log`, the captured stdout/stderr are also written there (even if the step fails).
 pub fn run_command(
   step: &ValidationStep,
   working_dir: &Path,
-  base_variables: &HashMap<String, String>,
+  variables: StepVariables<'_>,
+  step_log: Option<&StepLogDir>,
 ) -> Result<Output, SpawnError> {
-  // 1. Substitute command string
-  let substituted_command = substitute_command_for_validation(&step.command, base_variables);
+  // 1. Substitute variables and build the process (shell command or argument vector)
+  let (expression, command_display) = step_expression(step, variables)?;
 
-  // 2. Prepare timeout duration
+  // 2. Prepare timeout duration and env values (which may use `{{varName}}` too)
   let timeout_duration = step.timeout_secs.map(Duration::from_secs);
+  let env: HashMap<String, String> = step
+    .env
+    .iter()
+    .map(|(key, value)| (key.clone(), variables.render(value)))
+    .collect();
 
   // 3. Call the execution helper
   let exec_result = execute_command_with_duct(
     &step.name,
-    &substituted_command,
+    expression,
+    &command_display,
     working_dir,
-    &step.env,
+    &env,
+    step.clean_env,
     timeout_duration,
   );
 
-  // 4. Process the result from the helper (interpret status, stderr, ignore_errors)
+  // 4. Process the result from the helper (interpret status, stderr, ignored failures)
   match exec_result {
     Ok(output) => {
       // Includes non-zero exits because of unchecked()
       debug!("Step '{}' executed. Status: {:?}", step.name, output.status);
+      // The warnings below only quote the first stderr line; point them at the full output
+      let mut full_output_hint = String::new();
+      if let Some(step_log) = step_log {
+        let paths = step_log.write(&step.name, &output)?;
+        let shown: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        info!("Step '{}' output logged to {}", step.name, shown.join(" and "));
+        if let Some(stderr_path) = shown.last() {
+          full_output_hint = format!(" (full stderr: {})", stderr_path);
+        }
+      }
       if log::log_enabled!(log::Level::Trace) {
         trace!(
           "Step '{}' stdout:\n{}",
@@ -545,59 +2272,43 @@ pub fn run_command(
         );
       }
 
-      // Check status, respecting ignore_errors
+      // Check status, respecting ignored failures
       if !output.status.success() {
         let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
         let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
         // Log non-zero exit status correctly
-        let status_display = output
-          .status
-          .code()
-          .map(|c| c.to_string())
-          .or_else(|| output.status.signal().map(|s| format!("signal {}", s)))
-          .unwrap_or_else(|| "unknown".to_string());
+        let status_display = describe_exit_status(&output.status);
         warn!(
-          "Step '{}' failed with status: {}. Stderr: {}",
+          "Step '{}' failed with status: {}{}.{}",
           step.name,
           status_display,
-          stderr_string.lines().next().unwrap_or("<empty stderr>")
+          full_output_hint,
+          output_tail(&stdout_string, &stderr_string)
         );
 
-        // Check if the specific error is "command not found" (127 on Unix)
-        // This provides a more specific error message than CommandFailedStatus
-        #[cfg(unix)]
-        if output.status.code() == Some(127) {
-          if !step.ignore_errors {
+        // Check if the specific error is "command not found" (127 for sh, 9009 for cmd.exe)
+        // This provides a more specific error message than CommandFailedStatus.
+        // Programs run via `args` report a missing binary as a spawn error instead.
+        if step.args.is_empty() && output.status.code() == Some(COMMAND_NOT_FOUND_EXIT_CODE) {
+          if !step.ignores(StepFailure::Status) {
             return Err(SpawnError::CommandExecError {
               step_name: step.name.clone(),
-              source: format!("Command not found (exit code 127): {}", substituted_command).into(),
+              source: format!(
+                "Command not found (exit code {}): {}",
+                COMMAND_NOT_FOUND_EXIT_CODE, command_display
+              )
+              .into(),
             });
           } else {
             info!(
-              "Ignoring failed status (command not found) for step '{}' (ignore_errors=true).",
+              "Ignoring failed status (command not found) for step '{}' (ignored failure: status).",
               step.name
             );
             // Fall through to check stderr below if needed
           }
         } else {
           // Handle other non-zero exits
-          if !step.ignore_errors {
-            return Err(SpawnError::CommandFailedStatus {
-              step_name: step.name.clone(),
-              status: output.status,
-              stdout: stdout_string,
-              stderr: stderr_string,
-            });
-          } else {
-            info!(
-              "Ignoring failed status ({}) for step '{}' (ignore_errors=true).",
-              status_display, step.name
-            );
-          }
-        }
-        #[cfg(not(unix))] // Fallback for non-unix
-        {
-          if !step.ignore_errors {
+          if !step.ignores(StepFailure::Status) {
             return Err(SpawnError::CommandFailedStatus {
               step_name: step.name.clone(),
               status: output.status,
@@ -606,26 +2317,27 @@ pub fn run_command(
             });
           } else {
             info!(
-              "Ignoring failed status ({}) for step '{}' (ignore_errors=true).",
+              "Ignoring failed status ({}) for step '{}' (ignored failure: status).",
               status_display, step.name
             );
           }
         }
       } // end if !output.status.success()
 
-      // Check stderr content, respecting ignore_errors
-      // This check runs even if the command failed but ignore_errors=true
+      // Check stderr content, respecting ignored failures
+      // This check runs even if the command failed but its status was ignored
       if step.check_stderr && !output.stderr.is_empty() {
         let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
         let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
         warn!(
-          "Step '{}' produced stderr (check_stderr=true): {}",
+          "Step '{}' produced stderr (check_stderr=true){}.{}",
           step.name,
-          stderr_string.lines().next().unwrap_or("<empty stderr>")
+          full_output_hint,
+          output_tail("", &stderr_string)
         );
-        if !step.ignore_errors {
+        if !step.ignores(StepFailure::Stderr) {
           // Only fail if the command *also* succeeded OR if status failure was ignored
-          if output.status.success() || step.ignore_errors {
+          if output.status.success() || step.ignores(StepFailure::Status) {
             return Err(SpawnError::CommandStderrNotEmpty {
               step_name: step.name.clone(),
               stdout: stdout_string,
@@ -635,7 +2347,7 @@ pub fn run_command(
           // Otherwise, the CommandFailedStatus error takes precedence
         } else {
           info!(
-            "Ignoring non-empty stderr for step '{}' (ignore_errors=true).",
+            "Ignoring non-empty stderr for step '{}' (ignored failure: stderr).",
             step.name
           );
         }
@@ -648,6 +2360,8 @@ pub fn run_command(
     Err(e) => {
       // Error from execute_command_with_duct (timeout, spawn error, wait error)
       error!("Execution error for step '{}': {}", step.name, e);
+      // A timeout ignored only via `ignore: [timeout]` is still returned: callers would take
+      // the placeholder failure status below for a real exit status
       if !step.ignore_errors {
         Err(e) // Propagate the execution error
       } else {
@@ -656,11 +2370,7 @@ pub fn run_command(
           step.name
         );
         // Construct a dummy error Output when ignoring execution errors
-        let exit_status = if cfg!(unix) {
-          ExitStatus::from_raw(1) // Use 1 as generic error code
-        } else {
-          ExitStatus::from_raw(1)
-        };
+        let exit_status = generic_failure_status();
 
         Ok(Output {
           status: exit_status,
@@ -672,17 +2382,318 @@ pub fn run_command(
   }
 }
 
+// --- Sensitive values ---
+
+/// Printed in place of a sensitive value.
+pub const REDACTED: &str = "****";
+
+/// A `sensitive: true` variable's value: `Debug` and `Display` print `****`, so it cannot end
+/// up in a log by accident. `expose` gives the real value for substitution and commands.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SensitiveValue(String);
+
+impl SensitiveValue {
+  pub fn new(value: impl Into<String>) -> Self {
+    SensitiveValue(value.into())
+  }
+
+  pub fn expose(&self) -> &str {
+    &self.0
+  }
+
+  pub fn into_inner(self) -> String {
+    self.0
+  }
+}
+
+impl fmt::Debug for SensitiveValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(REDACTED)
+  }
+}
+
+impl fmt::Display for SensitiveValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(REDACTED)
+  }
+}
+
+/// Names whose values must not be logged: each `sensitive` variable with its placeholder and
+/// transformation placeholders, and every `derived` entry whose template references one of
+/// them. Masking goes by these names, never by searching text for a value.
+pub fn sensitive_names(variables: &[VariableDefinition], derived: &[DerivedVariable]) -> HashSet<String> {
+  let mut names = HashSet::new();
+  for var_def in variables.iter().filter(|vd| vd.sensitive) {
+    names.insert(var_def.name.clone());
+    names.insert(var_def.placeholder_value.clone());
+    names.extend(var_def.transformations.values().cloned());
+  }
+  // In declaration order, so a derived value built from another derived one is covered
+  for derived_def in derived {
+    let uses_sensitive = template_parts(&derived_def.template).into_iter().any(|part| match part {
+      TemplatePart::Reference { reference, .. } => is_sensitive_reference(reference, &names),
+      TemplatePart::Text(_) => false,
+    });
+    if uses_sensitive {
+      names.insert(derived_def.name.clone());
+      names.insert(derived_def.placeholder_value.clone());
+    }
+  }
+  names
+}
+
+/// Whether a `{{reference}}` (`varName` or `varName.<transformation>`) names a sensitive value.
+pub fn is_sensitive_reference(reference: &str, sensitive: &HashSet<String>) -> bool {
+  let name = reference.split('.').next().unwrap_or(reference);
+  sensitive.contains(reference) || sensitive.contains(name)
+}
+
+/// `value` as it may appear in logs and error messages: `****` if it is sensitive.
+pub fn shown(value: &str, sensitive: bool) -> &str {
+  if sensitive {
+    REDACTED
+  } else {
+    value
+  }
+}
+
+/// A variables map (sorted) with the values of `sensitive` names masked, for debug logging.
+pub fn redacted_variables<'a>(
+  variables: &'a HashMap<String, String>,
+  sensitive: &HashSet<String>,
+) -> BTreeMap<&'a str, &'a str> {
+  variables
+    .iter()
+    .map(|(name, value)| (name.as_str(), shown(value, sensitive.contains(name))))
+    .collect()
+}
+
+/// Values for `{{varName}}` references in step commands and env, with the names whose values
+/// are sensitive: those are passed to the process but shown as `****` in the logged command.
+#[derive(Clone, Copy)]
+pub struct StepVariables<'a> {
+  pub values: &'a HashMap<String, String>,
+  pub sensitive: &'a HashSet<String>,
+}
+
+impl<'a> StepVariables<'a> {
+  pub fn new(values: &'a HashMap<String, String>, sensitive: &'a HashSet<String>) -> Self {
+    StepVariables { values, sensitive }
+  }
+
+  /// `command_template` with references filled in, for running it.
+  fn render(&self, command_template: &str) -> String {
+    substitute_command_for_validation(command_template, self.values)
+  }
+
+  /// `command_template` with references filled in and sensitive ones masked, for logs.
+  fn render_for_display(&self, command_template: &str) -> String {
+    let rendered = render_references(command_template, |reference| {
+      let value = self.values.get(reference);
+      let masked = is_sensitive_reference(reference, self.sensitive);
+      Ok::<_, std::convert::Infallible>(value.map(|v| shown(v, masked).to_string()))
+    });
+    rendered.unwrap_or_else(|never| match never {})
+  }
+}
+
 // Helper specific for commands, using {{varName}} convention
-fn substitute_command_for_validation(
+pub(crate) fn substitute_command_for_validation(
   command_template: &str,
   base_variables: &HashMap<String, String>,
 ) -> String {
-  let mut command = command_template.to_string();
-  for (key, value) in base_variables {
-    let placeholder = format!("{{{{{}}}}}", key); // Match {{variable_name}}
-    command = command.replace(&placeholder, value);
+  // Unknown references stay as written, so shell syntax like `{{` passes through
+  let rendered = render_references(command_template, |reference| {
+    Ok::<_, std::convert::Infallible>(base_variables.get(reference).cloned())
+  });
+  rendered.unwrap_or_else(|never| match never {})
+}
+
+/// Resolves a program like the shell would: paths containing a separator are checked as
+/// given, bare names are searched in `PATH` (trying `PATHEXT` extensions on Windows).
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+  let is_executable = |path: &Path| {
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+      path.is_file()
+    }
+  };
+  let candidates = |base: PathBuf| -> Vec<PathBuf> {
+    #[cfg(windows)]
+    if base.extension().is_none() {
+      let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+      return std::iter::once(base.clone())
+        .chain(exts.split(';').filter(|e| e.len() > 1).map(|e| base.with_extension(&e[1..])))
+        .collect();
+    }
+    vec![base]
+  };
+
+  if program.contains('/') || program.contains(std::path::MAIN_SEPARATOR) {
+    return candidates(PathBuf::from(program)).into_iter().find(|p| is_executable(p));
+  }
+  let path_var = std::env::var_os("PATH")?;
+  std::env::split_paths(&path_var)
+    .flat_map(|dir| candidates(dir.join(program)))
+    .find(|p| is_executable(p))
+}
+
+/// Exit code the platform shell reports when a command does not exist.
+#[cfg(windows)]
+const COMMAND_NOT_FOUND_EXIT_CODE: i32 = 9009;
+#[cfg(not(windows))]
+const COMMAND_NOT_FOUND_EXIT_CODE: i32 = 127;
+
+/// Builds the process for a step with `{{varName}}` filled in, plus its printable form for logs
+/// (with sensitive values masked).
+/// `args` runs the program directly (preferred over `command` when both are set, since it
+/// needs no quoting); otherwise `command` runs through the step's `shell` or the platform shell.
+fn step_expression(
+  step: &ValidationStep,
+  variables: StepVariables<'_>,
+) -> Result<(duct::Expression, String), SpawnError> {
+  if let Some((program, rest)) = step.args.split_first() {
+    if !step.command.trim().is_empty() {
+      debug!("Step '{}' sets both `args` and `command`; running `args`.", step.name);
+    }
+    let display = step
+      .args
+      .iter()
+      .map(|arg| format!("{:?}", variables.render_for_display(arg)))
+      .collect::<Vec<_>>()
+      .join(" ");
+    let program = variables.render(program);
+    let rest: Vec<String> = rest.iter().map(|arg| variables.render(arg)).collect();
+    return Ok((cmd(program, rest), display));
+  }
+  if step.command.trim().is_empty() {
+    return Err(SpawnError::CommandExecError {
+      step_name: step.name.clone(),
+      source: "step has neither a `command` nor `args`".into(),
+    });
+  }
+  let command = variables.render(&step.command);
+  let expression = match &step.shell {
+    Some(shell) => cmd!(shell, shell_command_flag(shell), &command),
+    None => shell_command(&command),
+  };
+  Ok((expression, variables.render_for_display(&step.command)))
+}
+
+/// Flag that makes `shell` run a command string: `/C` for cmd, `-Command` for PowerShell,
+/// `-c` for everything else (sh, bash, zsh, ...).
+fn shell_command_flag(shell: &str) -> &'static str {
+  let name = Path::new(shell)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+    .unwrap_or_default();
+  match name.as_str() {
+    "cmd" => "/C",
+    "powershell" | "pwsh" => "-Command",
+    _ => "-c",
+  }
+}
+
+/// Wraps a step command in the platform shell: `cmd /C` on Windows, `sh -c` elsewhere.
+fn shell_command(command_str: &str) -> duct::Expression {
+  #[cfg(windows)]
+  {
+    cmd!("cmd", "/C", command_str)
+  }
+  #[cfg(not(windows))]
+  {
+    cmd!("sh", "-c", command_str)
+  }
+}
+
+/// Stand-in status for ignored execution errors (raw value 1 as a generic error).
+fn generic_failure_status() -> ExitStatus {
+  #[cfg(unix)]
+  use std::os::unix::process::ExitStatusExt;
+  #[cfg(windows)]
+  use std::os::windows::process::ExitStatusExt;
+  ExitStatus::from_raw(1)
+}
+
+/// Sends SIGKILL to the process groups led by `handle`'s processes (see the `process_group(0)`
+/// in `execute_command_with_duct`), taking their descendants down with them.
+#[cfg(unix)]
+fn kill_process_groups(handle: &Handle) {
+  for pid in handle.pids() {
+    let Ok(pgid) = libc::pid_t::try_from(pid) else {
+      continue;
+    };
+    // SAFETY: kill(2) has no memory-safety preconditions; a negative pid names a process group
+    if unsafe { libc::kill(-pgid, libc::SIGKILL) } != 0 {
+      debug!("Could not kill process group {}: {}", pgid, std::io::Error::last_os_error());
+    }
+  }
+}
+
+/// Windows has no process groups to signal; `Handle::kill` only stops the direct child.
+#[cfg(not(unix))]
+fn kill_process_groups(_handle: &Handle) {}
+
+/// Trailing lines of step output quoted by `output_tail` unless `set_output_tail_lines` says otherwise.
+pub const DEFAULT_OUTPUT_TAIL_LINES: usize = 20;
+
+/// Set once from the CLI (`--output-tail-lines`, or all lines with `-vv`).
+static OUTPUT_TAIL_LINES: AtomicUsize = AtomicUsize::new(DEFAULT_OUTPUT_TAIL_LINES);
+
+/// How many trailing lines `output_tail` quotes; `None` quotes everything.
+pub fn set_output_tail_lines(lines: Option<usize>) {
+  OUTPUT_TAIL_LINES.store(lines.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// The end of a failed step's stderr (or of its stdout when stderr is empty), as a block to
+/// append to a warning or error message. A compile error is usually at the end, not the top.
+pub fn output_tail(stdout: &str, stderr: &str) -> String {
+  let (label, text) = match (stderr.trim_end(), stdout.trim_end()) {
+    ("", "") => return " (no output)".to_string(),
+    ("", stdout) => ("Stdout", stdout),
+    (stderr, _) => ("Stderr", stderr),
+  };
+  let lines: Vec<&str> = text.lines().collect();
+  let limit = OUTPUT_TAIL_LINES.load(Ordering::Relaxed);
+  if lines.len() <= limit {
+    return format!("\n{}:\n{}", label, text);
   }
-  command
+  format!(
+    "\n{} (last {} of {} lines):\n{}",
+    label,
+    limit,
+    lines.len(),
+    lines[lines.len() - limit..].join("\n")
+  )
+}
+
+/// Exit code, or the terminating signal on Unix.
+fn describe_exit_status(status: &ExitStatus) -> String {
+  if let Some(code) = status.code() {
+    return code.to_string();
+  }
+  #[cfg(unix)]
+  {
+    use std::os::unix::process::ExitStatusExt;
+    if let Some(signal) = status.signal() {
+      return format!("signal {}", signal);
+    }
+  }
+  "unknown".to_string()
+}
+
+/// Message of a panic caught by `JoinHandle::join` (panics carry a `&str` or `String`).
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+  payload
+    .downcast_ref::<&str>()
+    .map(|message| message.to_string())
+    .or_else(|| payload.downcast_ref::<String>().cloned())
+    .unwrap_or_else(|| "unknown panic".to_string())
 }
 
 /// Executes a command using duct, waits for completion (or timeout), then captures output.
@@ -691,9 +2702,11 @@ fn substitute_command_for_validation(
 /// Uses duct's internal background threads for capture and unchecked() to get Output on non-zero exit.
 fn execute_command_with_duct(
   step_name: &str,
-  command_str: &str,
+  expression: duct::Expression,
+  command_str: &str, // For logs only
   working_dir: &Path,
   env_overrides: &HashMap<String, String>,
+  clean_env: bool,
   timeout: Option<Duration>,
 ) -> Result<Output, SpawnError> {
   info!(
@@ -704,16 +2717,45 @@ fn execute_command_with_duct(
   );
 
   // 1. Configure command, including capture and unchecked()
-  let mut command_expr = cmd!("sh", "-c", command_str)
+  let mut command_expr = expression
     .dir(working_dir)
     .stdout_capture() // Capture stdout - duct reads in background thread
     .stderr_capture() // Capture stderr - duct reads in background thread
     .unchecked(); // <<< --- Add this back! Ensures Ok(Output) on non-zero exit
 
-  // 2. Apply environment overrides iteratively using .env()
-  //    This preserves the inherited environment.
-  for (key, value) in env_overrides {
-    command_expr = command_expr.env(key, value); // Add/override specific vars
+  // 2. Apply environment overrides: on top of the inherited environment, or with
+  //    `cleanEnv` on top of just the preserved variables
+  if clean_env {
+    let mut environment: HashMap<OsString, OsString> = CLEAN_ENV_PRESERVED
+      .iter()
+      .filter_map(|name| std::env::var_os(name).map(|value| (OsString::from(name), value)))
+      .collect();
+    environment.extend(
+      env_overrides
+        .iter()
+        .map(|(key, value)| (OsString::from(key), OsString::from(value))),
+    );
+    debug!(
+      "Step '{}' runs with a clean environment: {:?}",
+      step_name,
+      environment.keys().collect::<Vec<_>>()
+    );
+    command_expr = command_expr.full_env(environment);
+  } else {
+    for (key, value) in env_overrides {
+      command_expr = command_expr.env(key, value); // Add/override specific vars
+    }
+  }
+
+  // A step that can time out gets its own process group, so the kill also reaches whatever
+  // the shell started (compilers, package managers) instead of orphaning it
+  #[cfg(unix)]
+  if timeout.is_some() {
+    use std::os::unix::process::CommandExt;
+    command_expr = command_expr.before_spawn(|command| {
+      command.process_group(0);
+      Ok(())
+    });
   }
 
   // 2. Start the command, get a handle
@@ -786,6 +2828,7 @@ fn execute_command_with_duct(
                 "Step '{}' timed out after {:?}. Killing process.",
                 step_name, duration
               );
+              kill_process_groups(&handle);
               if let Err(kill_err) = handle.kill() {
                 // Attempt to kill
                 warn!(
@@ -793,10 +2836,9 @@ fn execute_command_with_duct(
                   step_name, kill_err
                 );
               }
-              break Err(SpawnError::CommandExecError {
-                // Return timeout error
+              break Err(SpawnError::CommandTimeout {
                 step_name: step_name.to_string(),
-                source: format!("Step timed out after {} seconds", duration.as_secs()).into(),
+                secs: duration.as_secs(),
               });
             } else {
               // Still within time, sleep a bit
@@ -846,3 +2888,452 @@ fn execute_command_with_duct(
 
   final_result // Return the Ok(Output) or Err(SpawnError)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn step(yaml: &str) -> ValidationStep {
+    serde_yaml::from_str(yaml).unwrap()
+  }
+
+  fn manifest(extra_yaml: &str) -> ScaffoldManifest {
+    serde_yaml::from_str(&format!(
+      "name: Sample\ndescription: Test template\nlanguage: test\n{}",
+      extra_yaml
+    ))
+    .unwrap()
+  }
+
+  fn substitutions(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+  }
+
+  #[test]
+  fn ascii_token_is_replaced_inside_a_binary_blob() {
+    let blob = [&[0x00, 0xFF, 0xFE][..], b"APP_NAME", &[0x00, 0x80], b"APP_NAME!"].concat();
+    let expected = [&[0x00, 0xFF, 0xFE][..], b"demo-app", &[0x00, 0x80], b"demo-app!"].concat();
+    let substitutions = substitutions(&[("APP_NAME", "demo-app")]);
+    assert_eq!(substitute_bytes(&blob, &substitutions), expected);
+
+    // Through the copy: only files listed in `replaceInBinary` are substituted
+    let template = tempfile::tempdir().unwrap();
+    fs::write(template.path().join("patched.bin"), &blob).unwrap();
+    fs::write(template.path().join("verbatim.bin"), &blob).unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let manifest = manifest(
+      "variables: []\nbinaryExtensions: [\".bin\"]\nreplaceInBinary: [patched.bin]\n",
+    );
+    copy_template_dir(
+      template.path(),
+      output.path(),
+      &HashMap::new(),
+      &substitutions,
+      &manifest,
+      &CopyOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(fs::read(output.path().join("patched.bin")).unwrap(), expected);
+    assert_eq!(fs::read(output.path().join("verbatim.bin")).unwrap(), blob);
+  }
+
+  const WHEN_MANIFEST: &str = r#"
+variables:
+  - { name: useDb, prompt: "Database?", varType: boolean, placeholderValue: use_db }
+  - name: dbUrl
+    prompt: "Database URL:"
+    placeholderValue: "--db-url--"
+    when: { variable: useDb, value: "true" }
+  - { name: useDocker, prompt: "Docker?", varType: boolean, placeholderValue: use_docker }
+conditionalPaths:
+  db.env: { variable: useDb, value: "true" }
+  docker.env: { variable: useDocker, value: "true" }
+"#;
+
+  /// Generates a template using `dbUrl` (asked `when: useDb`) from gathered `base` values.
+  fn generate_when_template(
+    base: &[(&str, &str)],
+  ) -> (tempfile::TempDir, ScaffoldManifest, HashMap<String, String>, CopyReport) {
+    let manifest = manifest(WHEN_MANIFEST);
+    let template = tempfile::tempdir().unwrap();
+    fs::write(template.path().join("db.env"), "DATABASE_URL=--db-url--\n").unwrap();
+    // Template bug: uses the database URL without being conditional on `useDb`
+    fs::write(template.path().join("docker.env"), "DB=--db-url--\n").unwrap();
+    let base_variables = substitutions(base);
+    let all_substitutions =
+      compute_transformed_variables(&base_variables, &manifest.variables, &[], false).unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let report = copy_template_dir(
+      template.path(),
+      output.path(),
+      &base_variables,
+      &all_substitutions,
+      &manifest,
+      &CopyOptions::default(),
+    )
+    .unwrap();
+    (output, manifest, base_variables, report)
+  }
+
+  fn strict() -> CopyOptions {
+    CopyOptions {
+      strict_warnings: true,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn npm_package_names_keep_npm_separators_and_collapse_runs() {
+    let npm = |value: &str| apply_case_transformation(value, &CaseTransformation::NpmPackageName);
+    assert_eq!(npm("My Cool.App"), "my-cool.app");
+    assert_eq!(npm("my_app~v2"), "my_app~v2");
+    assert_eq!(npm("a - b"), "a-b");
+    assert_eq!(npm("a . b"), "a.b");
+    assert_eq!(npm("a/-_b"), "a-b");
+    assert_eq!(npm("@acme/Widget Kit"), "acme-widget-kit");
+    assert_eq!(npm("  --Café Bar!  "), "caf-bar");
+  }
+
+  #[test]
+  fn cargo_package_names_keep_cargo_separators_and_collapse_runs() {
+    let cargo = |value: &str| apply_case_transformation(value, &CaseTransformation::CargoPackageName);
+    assert_eq!(cargo("my_cool app"), "my_cool-app");
+    assert_eq!(cargo("My.Cool.App"), "my-cool-app");
+    assert_eq!(cargo("a - b"), "a-b");
+    assert_eq!(cargo("a _ b"), "a_b");
+    assert_eq!(cargo("a._b"), "a_b");
+    assert_eq!(cargo("__Lib 2__"), "lib-2");
+  }
+
+  #[test]
+  fn skipped_variable_placeholder_is_left_intact_and_reported() {
+    // `gather_variables` leaves `dbUrl` out: its condition doesn't hold
+    let (output, manifest, base_variables, report) =
+      generate_when_template(&[("useDb", "false"), ("useDocker", "true")]);
+    let condition = manifest.variables[1].when.as_ref().unwrap();
+    assert!(!evaluate_condition(condition, &base_variables, false).unwrap());
+
+    assert!(!output.path().join("db.env").exists());
+    assert_eq!(
+      fs::read_to_string(output.path().join("docker.env")).unwrap(),
+      "DB=--db-url--\n"
+    );
+    let reported =
+      report_skipped_placeholders(output.path(), &report, &manifest, &base_variables, &strict());
+    match reported {
+      Err(SpawnError::StrictWarning(message)) => {
+        assert!(message.contains("--db-url--") && message.contains("dbUrl"), "{}", message)
+      }
+      other => panic!("expected a strict warning, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn skipped_variable_in_an_excluded_conditional_file_is_not_reported() {
+    let (output, manifest, base_variables, report) =
+      generate_when_template(&[("useDb", "false"), ("useDocker", "false")]);
+    assert!(report.files.is_empty());
+    report_skipped_placeholders(output.path(), &report, &manifest, &base_variables, &strict())
+      .unwrap();
+  }
+
+  #[test]
+  fn variable_whose_condition_holds_is_substituted() {
+    let (output, manifest, base_variables, report) = generate_when_template(&[
+      ("useDb", "true"),
+      ("dbUrl", "postgres://db"),
+      ("useDocker", "true"),
+    ]);
+    assert_eq!(
+      fs::read_to_string(output.path().join("db.env")).unwrap(),
+      "DATABASE_URL=postgres://db\n"
+    );
+    report_skipped_placeholders(output.path(), &report, &manifest, &base_variables, &strict())
+      .unwrap();
+  }
+
+  #[test]
+  fn slash_keyed_manifest_paths_match_backslash_separated_paths() {
+    // A relative path as walked on Windows; on Unix the backslashes are just characters,
+    // so this exercises the same normalization on every platform.
+    let windows_path = Path::new("docker\\Dockerfile");
+    assert_eq!(path_key(windows_path), "docker/Dockerfile");
+    assert_eq!(path_key(Path::new("docker/Dockerfile")), "docker/Dockerfile");
+    assert_eq!(normalize_path_key("docker\\Dockerfile"), "docker/Dockerfile");
+
+    let manifest = manifest("variables: []\nbinaryFiles: [assets/logo.dat]\n");
+    assert!(is_binary(Path::new("assets\\logo.dat"), &manifest));
+    assert!(is_binary(&Path::new("assets").join("logo.dat"), &manifest));
+  }
+
+  #[test]
+  fn slash_keyed_condition_excludes_a_nested_file() {
+    let template = tempfile::tempdir().unwrap();
+    fs::create_dir_all(template.path().join("docker")).unwrap();
+    fs::write(template.path().join("docker").join("Dockerfile"), "FROM scratch\n").unwrap();
+    fs::write(template.path().join("README.md"), "readme\n").unwrap();
+    let manifest = manifest(
+      "variables: []\n\
+       conditionalPaths:\n  docker/Dockerfile: { variable: useDocker, value: \"true\" }\n",
+    );
+    let base_variables = substitutions(&[("useDocker", "false")]);
+    let output = tempfile::tempdir().unwrap();
+    copy_template_dir(
+      template.path(),
+      output.path(),
+      &base_variables,
+      &HashMap::new(),
+      &manifest,
+      &CopyOptions::default(),
+    )
+    .unwrap();
+    assert!(output.path().join("README.md").exists());
+    assert!(!output.path().join("docker").join("Dockerfile").exists());
+  }
+
+  /// Copies a BOM-prefixed `main.rs` with `manifest_yaml` and returns the generated bytes.
+  fn generate_bom_file(manifest_yaml: &str, options: &CopyOptions) -> Vec<u8> {
+    let template = tempfile::tempdir().unwrap();
+    fs::write(
+      template.path().join("main.rs"),
+      [UTF8_BOM_BYTES, b"fn app_name() {}\n"].concat(),
+    )
+    .unwrap();
+    let output = tempfile::tempdir().unwrap();
+    copy_template_dir(
+      template.path(),
+      output.path(),
+      &HashMap::new(),
+      &substitutions(&[("app_name", "demo")]),
+      &manifest(&format!("variables: []\n{}", manifest_yaml)),
+      options,
+    )
+    .unwrap();
+    fs::read(output.path().join("main.rs")).unwrap()
+  }
+
+  #[test]
+  fn utf8_bom_is_stripped_from_generated_text_files() {
+    let options = CopyOptions::default();
+    assert_eq!(generate_bom_file("", &options), b"fn demo() {}\n");
+    // Large files are substituted line by line; the BOM goes there too
+    assert_eq!(
+      generate_bom_file("maxSubstitutionBytes: 1\n", &options),
+      b"fn demo() {}\n"
+    );
+  }
+
+  #[test]
+  fn utf8_bom_is_kept_when_preserved() {
+    let with_bom = [UTF8_BOM_BYTES, b"fn demo() {}\n"].concat();
+    assert_eq!(generate_bom_file("bom: preserve\n", &CopyOptions::default()), with_bom);
+    let preserve = CopyOptions {
+      preserve_bom: true,
+      ..Default::default()
+    };
+    assert_eq!(generate_bom_file("", &preserve), with_bom);
+  }
+
+  #[test]
+  fn large_files_are_copied_as_is_only_when_nothing_would_change() {
+    let template = tempfile::tempdir().unwrap();
+    let plain = b"fn main() {}\n\xff\xfe not utf-8\nlast line".to_vec();
+    fs::write(template.path().join("plain.rs"), &plain).unwrap();
+    fs::write(template.path().join("late.rs"), "fn main() {}\nfn app_name() {}\n").unwrap();
+    fs::write(
+      template.path().join("bom.rs"),
+      [UTF8_BOM_BYTES, b"fn main() {}\n"].concat(),
+    )
+    .unwrap();
+    let output = tempfile::tempdir().unwrap();
+    copy_template_dir(
+      template.path(),
+      output.path(),
+      &HashMap::new(),
+      &substitutions(&[("app_name", "demo")]),
+      &manifest("variables: []\nmaxSubstitutionBytes: 1\nautodetectBinary: false\n"),
+      &CopyOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(fs::read(output.path().join("plain.rs")).unwrap(), plain);
+    assert_eq!(
+      fs::read_to_string(output.path().join("late.rs")).unwrap(),
+      "fn main() {}\nfn demo() {}\n"
+    );
+    assert_eq!(fs::read(output.path().join("bom.rs")).unwrap(), b"fn main() {}\n");
+  }
+
+  fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars = substitutions(pairs);
+    move |name| vars.get(name).cloned()
+  }
+
+  #[test]
+  fn env_references_are_expanded_with_the_lookup() {
+    let content = "user=${USER_NAME} unset=${UNSET_NAME} literal=$${USER_NAME} \
+                   not_a_name=${1X} unclosed=${USER_NAME";
+    assert_eq!(
+      expand_env_vars(content, UndefinedEnv::Keep, env(&[("USER_NAME", "jane")])).unwrap(),
+      "user=jane unset=${UNSET_NAME} literal=${USER_NAME} not_a_name=${1X} unclosed=${USER_NAME"
+    );
+  }
+
+  #[test]
+  fn unset_env_reference_fails_when_undefined_env_is_error() {
+    let message = expand_env_vars("x=${MISSING_NAME}", UndefinedEnv::Error, env(&[])).unwrap_err();
+    assert!(message.contains("MISSING_NAME"), "{}", message);
+    // Escaped references don't need the variable to be set
+    assert_eq!(
+      expand_env_vars("x=$${MISSING_NAME}", UndefinedEnv::Error, env(&[])).unwrap(),
+      "x=${MISSING_NAME}"
+    );
+  }
+
+  #[test]
+  fn env_references_in_content_are_plain_text_without_the_opt_in() {
+    let content = "user=${USER_NAME} literal=$${USER_NAME} app=app_name";
+    let substitutions = substitutions(&[("app_name", "demo")]);
+    assert_eq!(
+      substitute_content(content, &substitutions, &manifest("variables: []\n")).unwrap(),
+      "user=${USER_NAME} literal=$${USER_NAME} app=demo"
+    );
+  }
+
+  #[test]
+  fn env_references_in_substituted_values_are_kept_as_entered() {
+    let manifest = manifest("variables: []\nexpandEnvInContent: true\nundefinedEnv: error\n");
+    let substitutions = substitutions(&[("app_name", "${SPAWNPOINT_TEST_UNSET_NAME}")]);
+    // Expanding the value would fail on the unset name
+    assert_eq!(
+      substitute_content("app=app_name", &substitutions, &manifest).unwrap(),
+      "app=${SPAWNPOINT_TEST_UNSET_NAME}"
+    );
+  }
+
+  #[test]
+  fn placeholder_in_a_substituted_value_is_not_substituted_again() {
+    let substitutions = substitutions(&[
+      ("__first__", "uses __second__"),
+      ("__second__", "uses __first__"),
+      ("__first__second__", "combined"),
+    ]);
+    let content = "a=__first__ b=__second__ c=__first__second__";
+    let manifest = manifest("variables: []\n");
+    // Every fresh map iterates in its own order; the result must not depend on it
+    for _ in 0..16 {
+      let reordered: HashMap<String, String> =
+        substitutions.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+      assert_eq!(
+        substitute_content(content, &reordered, &manifest).unwrap(),
+        "a=uses __second__ b=uses __first__ c=combined"
+      );
+    }
+  }
+
+  #[test]
+  fn shorter_placeholder_does_not_break_a_longer_one_it_prefixes() {
+    let manifest = manifest(
+      r#"
+placeholderFilenames: {}
+variables:
+  - { name: name, prompt: "Name:", placeholderValue: appName }
+  - { name: nameSuffix, prompt: "Suffix:", placeholderValue: appNameSuffix }
+"#,
+    );
+    let base_variables = substitutions(&[("name", "demo"), ("nameSuffix", "-cli")]);
+    let all_substitutions = substitutions(&[("appName", "demo"), ("appNameSuffix", "-cli")]);
+
+    assert_eq!(
+      substitute_content("appName/appNameSuffix", &all_substitutions, &manifest).unwrap(),
+      "demo/-cli"
+    );
+    assert_eq!(
+      substitute_path_segment(
+        "appName_appNameSuffix.rs",
+        &base_variables,
+        &all_substitutions,
+        &manifest.placeholder_filenames,
+        &manifest.variables,
+      ),
+      "demo_-cli.rs"
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn step_output_is_logged_to_the_default_location_without_a_step_log_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let step_log = StepLogDir::for_run(None, dir.path()).unwrap();
+    // Nothing is created until a step runs
+    assert!(!dir.path().join(".spawnpoint").exists());
+
+    let (values, sensitive) = (HashMap::new(), HashSet::new());
+    let variables = StepVariables::new(&values, &sensitive);
+    let noisy = step("name: npm install\ncommand: \"echo done; echo careful >&2\"\n");
+    run_command(&noisy, dir.path(), variables, Some(&step_log)).unwrap();
+    run_command(&noisy, dir.path(), variables, Some(&step_log)).unwrap();
+
+    let logs = dir.path().join(DEFAULT_STEP_LOG_DIR);
+    let expected = "--- stdout ---\ndone\n--- stderr ---\ncareful\n";
+    assert_eq!(fs::read_to_string(logs.join("npm_install.log")).unwrap(), expected);
+    assert_eq!(fs::read_to_string(logs.join("npm_install-2.log")).unwrap(), expected);
+  }
+
+  #[test]
+  fn sensitive_values_are_masked_by_name_not_by_value() {
+    let manifest = manifest(
+      r#"
+variables:
+  - { name: apiToken, prompt: "Token:", placeholderValue: api_token, sensitive: true,
+      transformations: { shoutySnakeCase: API_TOKEN } }
+  - { name: region, prompt: "Region:", placeholderValue: region_name }
+derived:
+  - { name: authHeader, placeholderValue: auth_header, template: "Bearer {{apiToken}}" }
+  - { name: endpoint, placeholderValue: endpoint_url, template: "https://{{region}}.example" }
+"#,
+    );
+    let sensitive = sensitive_names(&manifest.variables, &manifest.derived);
+    for name in ["apiToken", "api_token", "API_TOKEN", "authHeader", "auth_header"] {
+      assert!(sensitive.contains(name), "{}", name);
+    }
+    assert!(!sensitive.contains("region") && !sensitive.contains("endpoint"));
+
+    // The token happens to equal the region; only the token is masked
+    let values = substitutions(&[("apiToken", "eu"), ("region", "eu")]);
+    let logged = redacted_variables(&values, &sensitive);
+    assert_eq!(logged.get("apiToken"), Some(&REDACTED));
+    assert_eq!(logged.get("region"), Some(&"eu"));
+
+    let variables = StepVariables::new(&values, &sensitive);
+    let command = "deploy --region {{region}} --token {{apiToken}}";
+    assert_eq!(variables.render(command), "deploy --region eu --token eu");
+    assert_eq!(variables.render_for_display(command), "deploy --region eu --token ****");
+    assert_eq!(SensitiveValue::new("eu").to_string(), REDACTED);
+    assert_eq!(format!("{:?}", SensitiveValue::new("eu")), REDACTED);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn timed_out_step_takes_its_background_children_down() {
+    let dir = tempfile::tempdir().unwrap();
+    let step = step(
+      "name: background\ncommand: \"sleep 30 & echo $! > pidfile; wait\"\ntimeoutSecs: 1\n",
+    );
+    let (values, sensitive) = (HashMap::new(), HashSet::new());
+    let result = run_command(&step, dir.path(), StepVariables::new(&values, &sensitive), None);
+    assert!(matches!(result, Err(SpawnError::CommandTimeout { .. })), "{:?}", result);
+
+    let pid: libc::pid_t = fs::read_to_string(dir.path().join("pidfile"))
+      .unwrap()
+      .trim()
+      .parse()
+      .unwrap();
+    // The killed `sleep` is reaped by init shortly after; until then it is a zombie
+    let deadline = Instant::now() + Duration::from_secs(5);
+    // SAFETY: kill(2) with signal 0 only checks whether the process exists
+    while unsafe { libc::kill(pid, 0) } == 0 && Instant::now() < deadline {
+      thread::sleep(Duration::from_millis(50));
+    }
+    assert_ne!(unsafe { libc::kill(pid, 0) }, 0, "background process {} survived the timeout", pid);
+  }
+}
@@ -1,48 +1,70 @@
 // src/validate.rs
-use std::collections::HashMap;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use indicatif::ProgressBar;
 use log::{debug, error, info};
-use tempfile::Builder;
+use tempfile::{Builder, TempDir};
 
-use crate::cli::ValidateArgs;
-use crate::config::ValidationStep;
+use crate::cli::{StderrPolicy, ValidateArgs};
+use crate::config::{StepFailure, ValidationConfig, ValidationStep};
 use crate::error::SpawnError;
-use crate::generate::find_available_templates;
+use crate::generator::ValueGenerator;
+use crate::lint;
+use crate::list::read_manifest_with_overrides;
+use crate::registry::{self, TemplateRegistry};
 use crate::utils;
 
-pub fn run_validate(args: ValidateArgs, templates_dir: &Path) -> Result<(), SpawnError> {
+pub fn run_validate(
+  args: ValidateArgs,
+  registry: &TemplateRegistry,
+  strict_warnings: bool,
+  stderr_policy: StderrPolicy,
+) -> Result<(), SpawnError> {
+  if args.only_manifest_validation {
+    let selected = args.language.as_deref().zip(args.template.as_deref());
+    return lint::run_manifest_lint(registry, selected, strict_warnings);
+  }
+  // clap requires both unless --all, which implies --only-manifest-validation
+  let (Some(language), Some(template_name)) = (args.language.clone(), args.template.clone()) else {
+    return Err(SpawnError::GenerationError(
+      "Both a language and a template name are required.".to_string(),
+    ));
+  };
+
   info!(
     "Running validate command for template '{}' (lang: '{}')...",
-    args.template, args.language
+    template_name, language
   );
   debug!(
-    "Args: {:?}, Templates Dir: {}",
+    "Args: {:?}, Templates Dirs: {:?}",
     args,
-    templates_dir.display()
+    registry.roots()
   );
 
-  // --- 1. Find Template & Manifest (REVISED) ---
-
-  // Find all templates first
-  let available_templates = find_available_templates(templates_dir)?;
+  // --- 1. Find Template & Manifest ---
+  // The specific template matching language and manifest name
+  let template_version = args
+    .template_version
+    .as_deref()
+    .map(registry::parse_version_selector)
+    .transpose()?;
+  let found_template = registry
+    .find_version(&language, &template_name, template_version.as_ref())
+    .cloned();
 
-  // Find the specific template matching language and manifest name
-  let found_template = available_templates
-    .into_iter()
-    .find(|(_dir_name, _path, manifest)| {
-      manifest.language == args.language && manifest.name == args.template
-    });
-
-  let (template_dir_name, template_path, manifest) = match found_template {
+  let (template_dir_name, template_path, mut manifest) = match found_template {
     Some(t) => t,
     None => {
-      return Err(SpawnError::GenerationError(format!(
-        // Use GenerationError for consistency? Or keep specific error?
-        "Template '{}' for language '{}' not found.",
-        args.template, args.language
+      let version_note = template_version
+        .map(|v| format!(" with version {}", v))
+        .unwrap_or_default();
+      return Err(SpawnError::TemplateNotFound(format!(
+        // Use TemplateNotFound for consistency? Or keep specific error?
+        "Template '{}' for language '{}'{} not found.",
+        template_name, language, version_note
       )));
     }
   };
@@ -51,6 +73,15 @@ pub fn run_validate(args: ValidateArgs, templates_dir: &Path) -> Result<(), Spaw
     manifest.name,
     template_path.display()
   );
+  // Lays base templates (`extends`) underneath; a composed directory lives as long as `resolved`
+  let resolved = registry.resolve(&template_path, &manifest)?;
+  let template_path = resolved.path.clone();
+  manifest = resolved.manifest.clone();
+  if !args.set.is_empty() {
+    manifest = read_manifest_with_overrides(&template_path.join("scaffold.yaml"), &args.set)?;
+    info!("Applied {} manifest override(s): {:?}", args.set.len(), args.set);
+  }
+  manifest.force_check_stderr(stderr_policy.forced());
 
   // --- Validation Config Check ---
   let validation_config = match &manifest.validation {
@@ -66,71 +97,202 @@ pub fn run_validate(args: ValidateArgs, templates_dir: &Path) -> Result<(), Spaw
 
   info!("Found validation config for template '{}'", manifest.name);
 
-  // --- 2. Create Temporary Directory ---
-  let temp_dir = Builder::new()
-    // Use the actual directory name for the prefix, which is likely more filesystem-friendly
-    .prefix(&format!("spawnpoint_validate_{}_", template_dir_name))
-    .tempdir()
-    .map_err(SpawnError::Io)?; // Simplified error mapping
-  let temp_path = temp_dir.path();
-  info!("Created temporary directory: {}", temp_path.display());
+  // --- 2. Create Project Directory ---
+  // A temp dir deleted on drop, unless --keep or --output-dir ask to keep the project
+  let mut temp_dir: Option<TempDir> = None;
+  let project_path: PathBuf = match &args.output_dir {
+    Some(dir) => {
+      prepare_output_dir(dir)?;
+      info!("Generating test project into: {}", dir.display());
+      dir.clone()
+    }
+    None => {
+      let dir = Builder::new()
+        // Use the actual directory name for the prefix, which is likely more filesystem-friendly
+        .prefix(&format!("spawnpoint_validate_{}_", template_dir_name))
+        .tempdir()
+        .map_err(SpawnError::Io)?; // Simplified error mapping
+      info!("Created temporary directory: {}", dir.path().display());
+      if args.keep {
+        dir.keep()
+      } else {
+        temp_dir.insert(dir).path().to_path_buf()
+      }
+    }
+  };
+  let kept = temp_dir.is_none();
+  let temp_path = project_path.as_path();
 
   // --- 2b. Compute Test Variables (Base + Transformed) ---
   // Use validation_config.test_variables as the base map
-  let all_test_substitutions = utils::compute_transformed_variables(
-    &validation_config.test_variables, // Base vars from test_variables
+  let mut test_variables = validation_config.test_variables.clone();
+  let value_generator = ValueGenerator::new(Some(args.seed));
+  for var_def in &manifest.variables {
+    if let Some(generator) = &var_def.generator {
+      if !test_variables.contains_key(&var_def.name) {
+        let value = value_generator.generate(&var_def.name, generator);
+        test_variables.insert(var_def.name.clone(), value);
+      }
+    }
+  }
+  utils::expand_multi_select_members(&manifest, &mut test_variables);
+  let mut transform_cache = utils::TransformCache::default();
+  let all_test_substitutions = utils::compute_transformed_variables_cached(
+    &test_variables, // Base vars from test_variables
     &manifest.variables,
-  );
+    &manifest.derived,
+    strict_warnings,
+    &mut transform_cache,
+  )?;
+  let sensitive = utils::sensitive_names(&manifest.variables, &manifest.derived);
   debug!(
     "Computed all test substitutions (keyed by placeholder): {:?}",
-    all_test_substitutions
+    utils::redacted_variables(&all_test_substitutions, &sensitive)
   );
 
+  let tag_filter = TagFilter {
+    include: args.tags.clone(),
+    exclude: args.skip_tags.clone(),
+  };
+  let step_log = utils::StepLogDir::for_run(args.step_log_dir.as_deref(), temp_path)?;
+
   // --- 3. Generate into Temp Dir ---
   info!("Generating template into temporary directory...");
-  utils::copy_template_dir(
+  let result = utils::copy_template_dir(
     &template_path, // Use the correctly found path
     temp_path,
-    &validation_config.test_variables,
+    &test_variables,
     &all_test_substitutions,
     &manifest,
-  )?;
-  info!("Template generation complete.");
-
-  // --- 4. Run Validation Steps ---
-  info!("Running validation steps...");
-  // Pass the test_variables (base map) for command substitution,
-  // as commands likely use the original {{varName}} syntax, not placeholders.
-  // Or, update run_command to use the placeholder-keyed map if commands use placeholders. Let's assume commands use {{varName}} for now.
-  let result = run_validation_lifecycle(
-    validation_config,
-    temp_path,
-    &validation_config.test_variables,
-  );
+    &utils::CopyOptions {
+      strict_warnings,
+      placeholder_prefix: args.placeholder_prefix.clone(),
+      placeholder_suffix: args.placeholder_suffix.clone(),
+      ..Default::default()
+    },
+  )
+  .and_then(|_| {
+    info!("Template generation complete.");
+
+    // --- 4. Run Validation Steps ---
+    info!("Running validation steps...");
+    // Pass the test_variables (base map) for command substitution,
+    // as commands likely use the original {{varName}} syntax, not placeholders.
+    // Or, update run_command to use the placeholder-keyed map if commands use placeholders. Let's assume commands use {{varName}} for now.
+    run_validation_lifecycle(
+      validation_config,
+      temp_path,
+      utils::StepVariables::new(&test_variables, &sensitive),
+      &tag_filter,
+      Some(&step_log),
+    )
+    .map_err(validation_failure)
+  });
 
-  // --- 5. Report Result (temp dir cleans up automatically) ---
+  // --- 5. Report Result (temp dir cleans up automatically unless kept) ---
   match result {
     Ok(_) => {
       info!("✅ Validation successful for template '{}'!", manifest.name);
+      if kept {
+        info!("Generated project kept at: {}", temp_path.display());
+      }
       Ok(())
     }
     Err(e) => {
       error!("Validation failed for template '{}': {}", manifest.name, e);
+      if kept {
+        error!("Generated project kept for inspection at: {}", temp_path.display());
+      }
       // Propagate the validation error
       Err(e)
     }
   }
 }
 
+/// Creates `--output-dir`, refusing to mix the test project with existing files.
+fn prepare_output_dir(dir: &Path) -> Result<(), SpawnError> {
+  if dir.exists() {
+    let is_empty = dir.is_dir() && fs::read_dir(dir)?.next().is_none();
+    if !is_empty {
+      return Err(SpawnError::GenerationError(format!(
+        "Output directory '{}' must be empty or not exist yet.",
+        dir.display()
+      )));
+    }
+    return Ok(());
+  }
+  fs::create_dir_all(dir).map_err(|e| SpawnError::OutputDirCreation {
+    path: dir.to_path_buf(),
+    source: e,
+  })
+}
+
 // --- Helper Functions ---
 
+/// A failing step here means the template failed validation (exit code 4), not that a hook
+/// failed, so step errors are reported as `ValidationError`.
+fn validation_failure(e: SpawnError) -> SpawnError {
+  match &e {
+    SpawnError::CommandExecError { step_name, .. }
+    | SpawnError::CommandTimeout { step_name, .. }
+    | SpawnError::CommandFailedStatus { step_name, .. }
+    | SpawnError::CommandStderrNotEmpty { step_name, .. } => SpawnError::ValidationError {
+      step_name: step_name.clone(),
+      reason: e.to_string(),
+    },
+    _ => e,
+  }
+}
+
+/// Selects validation steps by their `tags`.
+/// `include` only applies to the main steps; setup/teardown run unless matched by `exclude`.
+struct TagFilter {
+  include: Vec<String>,
+  exclude: Vec<String>,
+}
+
+impl TagFilter {
+  fn allows(&self, step: &ValidationStep, apply_include: bool) -> bool {
+    if step.tags.iter().any(|t| self.exclude.contains(t)) {
+      return false;
+    }
+    !apply_include || self.include.is_empty() || step.tags.iter().any(|t| self.include.contains(t))
+  }
+
+  /// Returns the steps of a phase that pass the filter, reporting the ones skipped.
+  fn filter_phase(
+    &self,
+    phase_name: &str,
+    steps: &[ValidationStep],
+    apply_include: bool,
+  ) -> Vec<ValidationStep> {
+    let (kept, skipped): (Vec<_>, Vec<_>) = steps
+      .iter()
+      .cloned()
+      .partition(|step| self.allows(step, apply_include));
+    for step in &skipped {
+      info!(
+        "Skipping {} step '{}' due to tag filter (tags: {:?}).",
+        phase_name, step.name, step.tags
+      );
+    }
+    kept
+  }
+}
+
 fn run_validation_lifecycle(
-  config: &crate::config::ValidationConfig,
+  config: &ValidationConfig,
   temp_path: &Path,
-  test_variables_for_commands: &HashMap<String, String>,
+  test_variables_for_commands: utils::StepVariables<'_>,
+  tag_filter: &TagFilter,
+  step_log: Option<&utils::StepLogDir>,
 ) -> Result<(), SpawnError> {
+  let setup_steps = tag_filter.filter_phase("Setup", &config.setup, false);
+  let main_steps = tag_filter.filter_phase("Validation", &config.steps, true);
+  let teardown_steps = tag_filter.filter_phase("Teardown", &config.teardown, false);
+
   // Calculate total steps once
-  let total_steps = config.setup.len() + config.steps.len() + config.teardown.len();
+  let total_steps = setup_steps.len() + main_steps.len() + teardown_steps.len();
   // Use an AtomicUsize for the shared counter across phases
   let step_counter = AtomicUsize::new(0);
 
@@ -143,37 +305,50 @@ fn run_validation_lifecycle(
   // Run relative to original CWD by default
   let setup_result = execute_phase_steps(
     "Setup",
-    &config.setup,
+    &setup_steps,
     &original_cwd,
     temp_path, // Pass temp_path for potential workingDir resolution
     test_variables_for_commands,
     &pb,
     &step_counter,
     total_steps,
+    step_log,
   );
-  if let Err(e) = setup_result {
-    return Err(e);
-  } // Exit early on setup failure
+  setup_result?; // Exit early on setup failure
 
   // --- Main Validation Steps ---
   // Run relative to temp_path by default
-  let validation_result = execute_phase_steps(
-    "Validation",
-    &config.steps,
-    temp_path, // Default base is temp_path
-    temp_path, // Pass temp_path for potential workingDir resolution
-    test_variables_for_commands,
-    &pb,
-    &step_counter,
-    total_steps,
-  );
+  let validation_result = if config.parallel {
+    execute_phase_steps_parallel(
+      "Validation",
+      &main_steps,
+      temp_path,
+      test_variables_for_commands,
+      &pb,
+      &step_counter,
+      total_steps,
+      step_log,
+    )
+  } else {
+    execute_phase_steps(
+      "Validation",
+      &main_steps,
+      temp_path, // Default base is temp_path
+      temp_path, // Pass temp_path for potential workingDir resolution
+      test_variables_for_commands,
+      &pb,
+      &step_counter,
+      total_steps,
+      step_log,
+    )
+  };
   // Don't return early on validation failure yet, need to run teardown if applicable
 
   // --- Teardown Steps ---
   let mut teardown_result = Ok(()); // Track teardown result separately
-  if !config.teardown.is_empty() {
-    pb.println("--- Running Teardown phase ---".to_string());
-    for step in &config.teardown {
+  if !teardown_steps.is_empty() {
+    pb.println("--- Running Teardown phase ---");
+    for step in &teardown_steps {
       let current_step_num = step_counter.fetch_add(1, Ordering::SeqCst) + 1;
       let base_path = &original_cwd;
       // Allow workingDir relative to temp_path even for teardown
@@ -195,9 +370,9 @@ fn run_validation_lifecycle(
             ""
           }
         ));
-        match utils::run_command(step, &run_path, test_variables_for_commands) {
+        match utils::run_command(step, &run_path, test_variables_for_commands, step_log) {
           Ok(output) => {
-            if !output.status.success() && !step.ignore_errors {
+            if !output.status.success() && !step.ignores(StepFailure::Status) {
               pb.println(format!(
                 "❌ Teardown step '{}' failed (status: {:?}).",
                 step.name,
@@ -212,7 +387,10 @@ fn run_validation_lifecycle(
                   stderr: String::from_utf8_lossy(&output.stderr).to_string(),
                 });
               }
-            } else if step.check_stderr && !output.stderr.is_empty() && !step.ignore_errors {
+            } else if step.check_stderr
+              && !output.stderr.is_empty()
+              && !step.ignores(StepFailure::Stderr)
+            {
               pb.println(format!(
                 "❌ Teardown step '{}' failed (check_stderr=true).",
                 step.name
@@ -233,7 +411,7 @@ fn run_validation_lifecycle(
               "❌ Teardown step '{}' execution error: {}",
               step.name, e
             ));
-            if teardown_result.is_ok() && !step.ignore_errors {
+            if teardown_result.is_ok() && !step.ignores_error(&e) {
               teardown_result = Err(e);
             }
           }
@@ -245,7 +423,7 @@ fn run_validation_lifecycle(
         ));
       }
     }
-    pb.println("--- Finished Teardown phase ---".to_string());
+    pb.println("--- Finished Teardown phase ---");
   }
 
   // Final result prioritizes setup/validation errors over teardown errors
@@ -254,15 +432,17 @@ fn run_validation_lifecycle(
 
 /// Executes a sequence of validation steps for a given phase.
 /// Returns Ok(()) if all non-ignored steps succeed, or the first critical Err encountered.
+#[allow(clippy::too_many_arguments)]
 fn execute_phase_steps(
   phase_name: &str,
   steps: &[ValidationStep],
   default_base_dir: &Path, // Base path (e.g., original CWD or temp dir)
   temp_path: &Path,        // Always pass temp_path for resolving potential workingDir overrides
-  test_variables_for_commands: &HashMap<String, String>,
+  test_variables_for_commands: utils::StepVariables<'_>,
   pb: &ProgressBar,           // Pass progress bar for printing
   step_counter: &AtomicUsize, // Shared counter
   total_steps: usize,
+  step_log: Option<&utils::StepLogDir>,
 ) -> Result<(), SpawnError> {
   // Return Result to propagate errors
   if steps.is_empty() {
@@ -271,9 +451,6 @@ fn execute_phase_steps(
 
   pb.println(format!("--- Running {} phase ---", phase_name));
   for step in steps {
-    // Increment counter *before* running the step
-    let current_step_num = step_counter.fetch_add(1, Ordering::SeqCst) + 1;
-
     // Determine working directory: use step's if specified (relative to temp_path), else use default_base_dir
     let run_path = step
       .working_dir
@@ -282,68 +459,256 @@ fn execute_phase_steps(
       // Otherwise, use the default_base_dir passed for the phase.
       .map_or(default_base_dir.to_path_buf(), |wd| temp_path.join(wd));
 
-    pb.println(format!(
-      "[{}/{}] Running step: '{}'...",
-      current_step_num, total_steps, step.name
-    ));
+    run_phase_step(
+      step,
+      &run_path,
+      test_variables_for_commands,
+      pb,
+      step_counter,
+      total_steps,
+      step_log,
+    )?;
+  }
+  pb.println(format!("--- Finished {} phase ---", phase_name));
+  Ok(())
+}
 
-    match utils::run_command(step, &run_path, test_variables_for_commands) {
-      Ok(output) => {
-        // Check status AFTER command runs
-        if !output.status.success() {
-          let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
-          let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
-          pb.println(format!(
-            "❌ Step '{}' failed (status: {:?}).",
-            step.name,
-            output.status.code()
-          ));
-          if !step.ignore_errors {
-            // CONSTRUCT THE ERROR INSTANCE
-            return Err(SpawnError::CommandFailedStatus {
-              step_name: step.name.clone(),
-              status: output.status,
-              stdout: stdout_string,
-              stderr: stderr_string,
-            });
-          } else {
-            pb.println(format!("   (Ignoring error for step '{}')", step.name));
-          }
-        } else if step.check_stderr && !output.stderr.is_empty() {
-          let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
-          let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
-          pb.println(format!(
-            "❌ Step '{}' failed (check_stderr=true, stderr not empty).",
-            step.name
-          ));
-          if !step.ignore_errors {
-            // CONSTRUCT THE ERROR INSTANCE
-            return Err(SpawnError::CommandStderrNotEmpty {
-              step_name: step.name.clone(),
-              stdout: stdout_string,
-              stderr: stderr_string,
-            });
-          } else {
-            pb.println(format!("   (Ignoring stderr for step '{}')", step.name));
-          }
+/// Like `execute_phase_steps`, but starts every step at once on its own thread (`parallel: true`).
+/// All steps run to completion; the first failing non-ignored step in manifest order is returned.
+#[allow(clippy::too_many_arguments)]
+fn execute_phase_steps_parallel(
+  phase_name: &str,
+  steps: &[ValidationStep],
+  temp_path: &Path, // Default base and root for workingDir overrides
+  test_variables_for_commands: utils::StepVariables<'_>,
+  pb: &ProgressBar,
+  step_counter: &AtomicUsize,
+  total_steps: usize,
+  step_log: Option<&utils::StepLogDir>,
+) -> Result<(), SpawnError> {
+  if steps.is_empty() {
+    return Ok(());
+  }
+
+  pb.println(format!("--- Running {} phase ({} steps in parallel) ---", phase_name, steps.len()));
+  // pb.println writes whole lines, so output from concurrent steps never interleaves mid-line
+  let results: Vec<Result<(), SpawnError>> = thread::scope(|scope| {
+    let handles: Vec<_> = steps
+      .iter()
+      .map(|step| {
+        let run_path = step
+          .working_dir
+          .as_ref()
+          .map_or(temp_path.to_path_buf(), |wd| temp_path.join(wd));
+        scope.spawn(move || {
+          run_phase_step(
+            step,
+            &run_path,
+            test_variables_for_commands,
+            pb,
+            step_counter,
+            total_steps,
+            step_log,
+          )
+        })
+      })
+      .collect();
+    // A panicking step fails that step instead of aborting the run
+    handles
+      .into_iter()
+      .zip(steps)
+      .map(|(handle, step)| {
+        handle.join().unwrap_or_else(|panic| {
+          Err(SpawnError::ValidationError {
+            step_name: step.name.clone(),
+            reason: format!("step thread panicked: {}", utils::panic_message(&*panic)),
+          })
+        })
+      })
+      .collect()
+  });
+  results.into_iter().collect::<Result<Vec<()>, SpawnError>>()?;
+  pb.println(format!("--- Finished {} phase ---", phase_name));
+  Ok(())
+}
+
+/// Runs one step and reports its outcome. Returns Err only for failures the step doesn't ignore.
+fn run_phase_step(
+  step: &ValidationStep,
+  run_path: &Path,
+  test_variables_for_commands: utils::StepVariables<'_>,
+  pb: &ProgressBar,
+  step_counter: &AtomicUsize,
+  total_steps: usize,
+  step_log: Option<&utils::StepLogDir>,
+) -> Result<(), SpawnError> {
+  // Increment counter *before* running the step
+  let current_step_num = step_counter.fetch_add(1, Ordering::SeqCst) + 1;
+  pb.println(format!(
+    "[{}/{}] Running step: '{}'...",
+    current_step_num, total_steps, step.name
+  ));
+
+  match utils::run_command(step, run_path, test_variables_for_commands, step_log) {
+    Ok(output) => {
+      // Check status AFTER command runs
+      if !output.status.success() {
+        let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
+        pb.println(format!(
+          "❌ Step '{}' failed (status: {:?}).",
+          step.name,
+          output.status.code()
+        ));
+        if !step.ignores(StepFailure::Status) {
+          // CONSTRUCT THE ERROR INSTANCE
+          return Err(SpawnError::CommandFailedStatus {
+            step_name: step.name.clone(),
+            status: output.status,
+            stdout: stdout_string,
+            stderr: stderr_string,
+          });
         } else {
-          pb.println(format!("✅ Step '{}' successful.", step.name));
+          pb.println(format!("   (Ignoring error for step '{}')", step.name));
         }
-      }
-      Err(e) => {
-        // Execution errors (spawn, timeout, wait) - run_command returns these directly now
-        pb.println(format!("❌ Step '{}' execution error: {}", step.name, e));
-        if !step.ignore_errors {
-          return Err(e); // Propagate the execution error (already SpawnError::CommandExecError)
+      } else if step.check_stderr && !output.stderr.is_empty() {
+        let stderr_string = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout_string = String::from_utf8_lossy(&output.stdout).to_string();
+        pb.println(format!(
+          "❌ Step '{}' failed (check_stderr=true, stderr not empty).",
+          step.name
+        ));
+        if !step.ignores(StepFailure::Stderr) {
+          // CONSTRUCT THE ERROR INSTANCE
+          return Err(SpawnError::CommandStderrNotEmpty {
+            step_name: step.name.clone(),
+            stdout: stdout_string,
+            stderr: stderr_string,
+          });
         } else {
-          pb.println(format!(
-            "   (Ignoring execution error for step '{}')",
-            step.name
-          ));
+          pb.println(format!("   (Ignoring stderr for step '{}')", step.name));
         }
+      } else {
+        pb.println(format!("✅ Step '{}' successful.", step.name));
+      }
+    }
+    Err(e) => {
+      // Execution errors (spawn, timeout, wait) - run_command returns these directly now
+      pb.println(format!("❌ Step '{}' execution error: {}", step.name, e));
+      if !step.ignores_error(&e) {
+        return Err(e); // Propagate the execution error (already SpawnError::CommandExecError)
+      } else {
+        pb.println(format!(
+          "   (Ignoring execution error for step '{}')",
+          step.name
+        ));
       }
     }
   }
-  pb.println(format!("--- Finished {} phase ---", phase_name));
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use clap::Parser;
+
+  fn tagged_steps(yaml: &str) -> Vec<ValidationStep> {
+    serde_yaml::from_str(yaml).unwrap()
+  }
+
+  fn names(steps: &[ValidationStep]) -> Vec<&str> {
+    steps.iter().map(|s| s.name.as_str()).collect()
+  }
+
+  #[test]
+  fn tag_filter_includes_and_excludes_steps() {
+    let steps = tagged_steps(
+      r#"
+- { name: lint, command: "true", tags: [fast] }
+- { name: integration, command: "true", tags: [slow] }
+- { name: flaky, command: "true", tags: [fast, flaky] }
+- { name: untagged, command: "true" }
+"#,
+    );
+    let filter = |include: &[&str], exclude: &[&str]| TagFilter {
+      include: include.iter().map(|t| t.to_string()).collect(),
+      exclude: exclude.iter().map(|t| t.to_string()).collect(),
+    };
+
+    let all = filter(&[], &[]).filter_phase("Validation", &steps, true);
+    assert_eq!(names(&all), ["lint", "integration", "flaky", "untagged"]);
+
+    let fast = filter(&["fast"], &[]).filter_phase("Validation", &steps, true);
+    assert_eq!(names(&fast), ["lint", "flaky"]);
+
+    let not_slow = filter(&[], &["slow"]).filter_phase("Validation", &steps, true);
+    assert_eq!(names(&not_slow), ["lint", "flaky", "untagged"]);
+
+    // Exclusion wins over inclusion
+    let fast_not_flaky = filter(&["fast"], &["flaky"]).filter_phase("Validation", &steps, true);
+    assert_eq!(names(&fast_not_flaky), ["lint"]);
+
+    // Setup/teardown ignore `--tags` but still honour `--skip-tags`
+    let setup = filter(&["fast"], &["slow"]).filter_phase("Setup", &steps, false);
+    assert_eq!(names(&setup), ["lint", "flaky", "untagged"]);
+  }
+
+  const MANIFEST: &str = r#"
+name: Sample
+description: Template for the validate path
+language: test
+variables:
+  - name: projectName
+    prompt: "Project name:"
+    placeholderValue: sample-project
+    transformations:
+      pascalCase: SampleProject
+  - name: features
+    prompt: "Features:"
+    varType: multiSelect
+    choices: [auth, metrics, logging]
+    separator: "+"
+    placeholderValue: "--features--"
+conditionalPaths:
+  metrics.txt: { variable: features.metrics, value: "true" }
+validation:
+  testVariables:
+    projectName: my-app
+    features: auth+logging
+  steps: []
+"#;
+
+  #[test]
+  fn validate_substitutes_transformed_and_multi_select_test_variables() {
+    let templates = tempfile::tempdir().unwrap();
+    let template_dir = templates.path().join("sample");
+    fs::create_dir_all(&template_dir).unwrap();
+    fs::write(template_dir.join("scaffold.yaml"), MANIFEST).unwrap();
+    fs::write(
+      template_dir.join("README.md"),
+      "# SampleProject (sample-project)\nfeatures: --features--\n",
+    )
+    .unwrap();
+    fs::write(template_dir.join("metrics.txt"), "metrics\n").unwrap();
+
+    let output = tempfile::tempdir().unwrap();
+    let project = output.path().join("project");
+    let registry = TemplateRegistry::scan(templates.path(), false).unwrap();
+    let args = ValidateArgs::parse_from([
+      "validate",
+      "test",
+      "Sample",
+      "--output-dir",
+      project.to_str().unwrap(),
+    ]);
+    run_validate(args, &registry, false, StderrPolicy::Respect).unwrap();
+
+    assert_eq!(
+      fs::read_to_string(project.join("README.md")).unwrap(),
+      "# MyApp (my-app)\nfeatures: auth+logging\n"
+    );
+    // `features.metrics` is "false", so the conditional file is left out
+    assert!(!project.join("metrics.txt").exists());
+  }
+}
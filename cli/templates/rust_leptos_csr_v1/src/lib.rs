@@ -8,11 +8,13 @@ fn PlaceholderAppComponent() -> impl IntoView {
     let (count, set_count) = signal(0);
 
     view! {
-        <h1>"Welcome to Leptos!"</h1>
-        <p>"Page Title Placeholder: Leptos Placeholder App"</p>
-        <button on:click=move |_| set_count.update(|n| *n += 1)>
-            "Click Me: " {count}
-        </button>
+        <main class="--kebab-component-name--">
+            <h1>"Welcome to Leptos!"</h1>
+            <p>"Page Title Placeholder: Leptos Placeholder App"</p>
+            <button on:click=move |_| set_count.update(|n| *n += 1)>
+                "Click Me: " {count}
+            </button>
+        </main>
     }
 }
 